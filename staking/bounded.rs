@@ -0,0 +1,184 @@
+//! Vector with a capacity known at compile time.
+//!
+//! Per-account entries are stored in a `BoundedVec` so that the number of
+//! entries a message iterates over, and with it its worst-case weight, is
+//! bounded statically. It is encoded and laid out in storage exactly like a
+//! `Vec`, so entries written before the bound existed stay readable.
+
+use core::ops::{Deref, DerefMut};
+use ink_prelude::vec::Vec;
+use ink_primitives::Key;
+use ink_storage::traits::{
+    forward_clear_packed, forward_pull_packed, forward_push_packed, KeyPtr, PackedLayout,
+    SpreadLayout,
+};
+
+/// A `Vec` holding at most `N` elements.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BoundedVec<T, const N: usize>(Vec<T>);
+
+impl<T, const N: usize> BoundedVec<T, N> {
+    /// Maximal number of elements.
+    pub const CAPACITY: usize = N;
+
+    /// Wraps `vec` as is, even above `N` elements, e.g. entries decoded from
+    /// before the bound existed.
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Self(vec)
+    }
+
+    /// Whether no further element can be pushed.
+    pub fn is_full(&self) -> bool {
+        self.0.len() >= N
+    }
+
+    /// Appends `value`, handing it back if the vector is full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        self.0.push(value);
+        Ok(())
+    }
+
+    /// Removes and returns the element at `index`, shifting the later ones.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        self.0.remove(index)
+    }
+}
+
+impl<T, const N: usize> Default for BoundedVec<T, N> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T, const N: usize> Deref for BoundedVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> DerefMut for BoundedVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a BoundedVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: scale::Encode, const N: usize> scale::Encode for BoundedVec<T, N> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<O: scale::Output + ?Sized>(&self, dest: &mut O) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl<T: scale::Decode, const N: usize> scale::Decode for BoundedVec<T, N> {
+    /// Accepts more than `N` elements, so that entries stored before the
+    /// bound can still be read and drained.
+    fn decode<I: scale::Input>(input: &mut I) -> Result<Self, scale::Error> {
+        Vec::<T>::decode(input).map(Self::from_vec)
+    }
+}
+
+impl<T: PackedLayout, const N: usize> SpreadLayout for BoundedVec<T, N> {
+    const FOOTPRINT: u64 = 1;
+
+    fn pull_spread(ptr: &mut KeyPtr) -> Self {
+        forward_pull_packed::<Self>(ptr)
+    }
+
+    fn push_spread(&self, ptr: &mut KeyPtr) {
+        forward_push_packed::<Self>(self, ptr)
+    }
+
+    fn clear_spread(&self, ptr: &mut KeyPtr) {
+        forward_clear_packed::<Self>(self, ptr)
+    }
+}
+
+impl<T: PackedLayout, const N: usize> PackedLayout for BoundedVec<T, N> {
+    fn pull_packed(&mut self, at: &Key) {
+        for element in self.0.iter_mut() {
+            element.pull_packed(at);
+        }
+    }
+
+    fn push_packed(&self, at: &Key) {
+        for element in self.0.iter() {
+            element.push_packed(at);
+        }
+    }
+
+    fn clear_packed(&self, at: &Key) {
+        for element in self.0.iter() {
+            element.clear_packed(at);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: scale_info::TypeInfo + 'static, const N: usize> scale_info::TypeInfo for BoundedVec<T, N> {
+    type Identity = Vec<T>;
+
+    fn type_info() -> scale_info::Type {
+        Vec::<T>::type_info()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, const N: usize> ink_storage::traits::StorageLayout for BoundedVec<T, N>
+where
+    T: scale_info::TypeInfo + PackedLayout + 'static,
+{
+    fn layout(key_ptr: &mut KeyPtr) -> ink_metadata::layout::Layout {
+        <Vec<T> as ink_storage::traits::StorageLayout>::layout(key_ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale::{Decode, Encode};
+
+    #[test]
+    fn rejects_pushes_above_capacity() {
+        let mut vec = BoundedVec::<u32, 2>::default();
+        assert_eq!(vec.try_push(1), Ok(()));
+        assert_eq!(vec.try_push(2), Ok(()));
+        assert!(vec.is_full());
+        assert_eq!(vec.try_push(3), Err(3));
+        assert_eq!(vec.remove(0), 1);
+        assert_eq!(vec.try_push(3), Ok(()));
+        assert_eq!(&vec[..], &[2, 3]);
+    }
+
+    #[test]
+    fn encodes_like_a_vec() {
+        let mut vec = BoundedVec::<u32, 2>::default();
+        vec.try_push(7).unwrap();
+        assert_eq!(vec.encode(), vec![7u32].encode());
+        // Entries stored before the bound decode in full.
+        let legacy = vec![1u32, 2, 3].encode();
+        let decoded = BoundedVec::<u32, 2>::decode(&mut &legacy[..]).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert!(decoded.is_full());
+    }
+}
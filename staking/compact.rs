@@ -0,0 +1,231 @@
+//! Compact storage encoding of the stakes of an account.
+//!
+//! Timestamps are stored as deltas from the earliest stake of the account and
+//! all numbers as SCALE compacts, which shrinks an entry from 64 bytes to
+//! roughly a third for typical amounts, and with it the storage deposit.
+//! The encoding is only used in storage, decoded stakes always carry
+//! absolute values.
+
+use crate::bounded::BoundedVec;
+use core::ops::{Deref, DerefMut};
+use ink_primitives::Key;
+use ink_storage::traits::{
+    forward_clear_packed, forward_pull_packed, forward_push_packed, KeyPtr, PackedLayout,
+    SpreadLayout,
+};
+use scale::{Compact, Decode, Encode};
+use staking_traits::{Balance, Stake};
+
+/// First byte of the compact encoding. Never the first byte of a SCALE
+/// encoded `Vec` length, so entries written as plain `Vec<Stake>` before
+/// stay readable.
+const COMPACT_MARKER: u8 = 0xff;
+
+/// At most `N` stakes, encoded compactly.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompactStakes<const N: usize>(BoundedVec<Stake, N>);
+
+impl<const N: usize> CompactStakes<N> {
+    /// Maximal number of stakes.
+    pub const CAPACITY: usize = N;
+}
+
+impl<const N: usize> Deref for CompactStakes<N> {
+    type Target = BoundedVec<Stake, N>;
+
+    fn deref(&self) -> &BoundedVec<Stake, N> {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for CompactStakes<N> {
+    fn deref_mut(&mut self) -> &mut BoundedVec<Stake, N> {
+        &mut self.0
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a CompactStakes<N> {
+    type Item = &'a Stake;
+    type IntoIter = core::slice::Iter<'a, Stake>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<const N: usize> Encode for CompactStakes<N> {
+    fn encode_to<O: scale::Output + ?Sized>(&self, dest: &mut O) {
+        dest.push_byte(COMPACT_MARKER);
+        Compact(self.len() as u32).encode_to(dest);
+        let base = match self.iter().map(|stake| stake.timestamp).min() {
+            Some(base) => base,
+            None => return,
+        };
+        Compact(base).encode_to(dest);
+        for stake in self.iter() {
+            Compact(stake.amount).encode_to(dest);
+            Compact(stake.timestamp - base).encode_to(dest);
+            Compact(stake.multiplier).encode_to(dest);
+            Compact(stake.id).encode_to(dest);
+            Compact(stake.schedule).encode_to(dest);
+            Compact(stake.released).encode_to(dest);
+        }
+    }
+}
+
+impl<const N: usize> Decode for CompactStakes<N> {
+    /// Accepts more than `N` stakes, like `BoundedVec`, and the plain `Vec`
+    /// encoding of entries written before the compact one.
+    fn decode<I: scale::Input>(input: &mut I) -> Result<Self, scale::Error> {
+        let first = input.read_byte()?;
+        let mut stakes = ink_prelude::vec::Vec::new();
+        if first != COMPACT_MARKER {
+            let len = decode_len_after(first, input)?;
+            for _ in 0..len {
+                stakes.push(Stake::decode(input)?);
+            }
+            return Ok(Self(BoundedVec::from_vec(stakes)));
+        }
+        let len = Compact::<u32>::decode(input)?.0;
+        if len == 0 {
+            return Ok(Self::default());
+        }
+        let base = Compact::<Balance>::decode(input)?.0;
+        for _ in 0..len {
+            let amount = Compact::<Balance>::decode(input)?.0;
+            let delta = Compact::<Balance>::decode(input)?.0;
+            stakes.push(Stake {
+                amount,
+                timestamp: base.checked_add(delta).ok_or("timestamp out of range")?,
+                multiplier: Compact::<u32>::decode(input)?.0,
+                id: Compact::<u64>::decode(input)?.0,
+                schedule: Compact::<u32>::decode(input)?.0,
+                released: Compact::<Balance>::decode(input)?.0,
+            });
+        }
+        Ok(Self(BoundedVec::from_vec(stakes)))
+    }
+}
+
+/// Decodes the rest of a compact `u32` whose `first` byte has been read.
+fn decode_len_after<I: scale::Input>(first: u8, input: &mut I) -> Result<u32, scale::Error> {
+    let mut bytes = [first, 0, 0, 0];
+    let len = match first & 0b11 {
+        0b00 => 1,
+        0b01 => 2,
+        0b10 => 4,
+        _ => return Err("invalid stakes length".into()),
+    };
+    input.read(&mut bytes[1..len])?;
+    Ok(u32::from_le_bytes(bytes) >> 2)
+}
+
+impl<const N: usize> SpreadLayout for CompactStakes<N> {
+    const FOOTPRINT: u64 = 1;
+
+    fn pull_spread(ptr: &mut KeyPtr) -> Self {
+        forward_pull_packed::<Self>(ptr)
+    }
+
+    fn push_spread(&self, ptr: &mut KeyPtr) {
+        forward_push_packed::<Self>(self, ptr)
+    }
+
+    fn clear_spread(&self, ptr: &mut KeyPtr) {
+        forward_clear_packed::<Self>(self, ptr)
+    }
+}
+
+impl<const N: usize> PackedLayout for CompactStakes<N> {
+    fn pull_packed(&mut self, at: &Key) {
+        self.0.pull_packed(at)
+    }
+
+    fn push_packed(&self, at: &Key) {
+        self.0.push_packed(at)
+    }
+
+    fn clear_packed(&self, at: &Key) {
+        self.0.clear_packed(at)
+    }
+}
+
+/// Described as opaque bytes: the compact encoding depends on the entries,
+/// e.g. the base timestamp is left out without stakes, which type metadata
+/// can't express. Storage readers decode it with `CompactStakes::decode`.
+#[cfg(feature = "std")]
+impl<const N: usize> scale_info::TypeInfo for CompactStakes<N> {
+    type Identity = Self;
+
+    fn type_info() -> scale_info::Type {
+        scale_info::Type::builder()
+            .path(scale_info::Path::new("CompactStakes", module_path!()))
+            .docs(&["Opaque compact encoding of the stakes of an account."])
+            .composite(scale_info::build::Fields::unnamed().field(|f| f.ty::<Vec<u8>>()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> ink_storage::traits::StorageLayout for CompactStakes<N> {
+    fn layout(key_ptr: &mut KeyPtr) -> ink_metadata::layout::Layout {
+        use ink_metadata::layout::{CellLayout, Layout, LayoutKey};
+        Layout::Cell(CellLayout::new::<Self>(LayoutKey::from(
+            key_ptr.advance_by(1),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stake(id: u64, timestamp: Balance) -> Stake {
+        Stake {
+            amount: 1_000_000_000_000_000_000,
+            timestamp,
+            multiplier: 1000,
+            id,
+            schedule: 0,
+            released: 0,
+        }
+    }
+
+    fn stakes(entries: &[Stake]) -> CompactStakes<8> {
+        let mut stakes = CompactStakes::default();
+        for entry in entries {
+            stakes.try_push(*entry).unwrap();
+        }
+        stakes
+    }
+
+    #[test]
+    fn round_trips_absolute_values() {
+        let stakes = stakes(&[
+            stake(3, 1_700_000_000_000),
+            stake(4, 1_600_000_000_000),
+            stake(5, 1_700_086_400_000),
+        ]);
+        let encoded = stakes.encode();
+        assert_eq!(
+            CompactStakes::<8>::decode(&mut &encoded[..]).unwrap(),
+            stakes
+        );
+        assert!(encoded.len() < stakes.len() * Stake::default().encode().len() / 2);
+        let empty = CompactStakes::<8>::default();
+        assert_eq!(
+            CompactStakes::<8>::decode(&mut &empty.encode()[..]).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn reads_plain_vec_encoding() {
+        let plain = vec![stake(1, 5), stake(2, 6)];
+        let decoded = CompactStakes::<8>::decode(&mut &plain.encode()[..]).unwrap();
+        assert_eq!(decoded.to_vec(), plain);
+        // Lengths taking two bytes as a compact.
+        let plain = vec![stake(1, 5); 70];
+        let decoded = CompactStakes::<8>::decode(&mut &plain.encode()[..]).unwrap();
+        assert_eq!(decoded.to_vec(), plain);
+    }
+}
@@ -0,0 +1,39 @@
+//! Off-chain routing of the calls to other contracts (feature `e2e`).
+//!
+//! The off-chain environment of ink! can't call other contracts, so with this
+//! feature `call_contract` hands its calls to the dispatcher registered by the
+//! end-to-end tests, which runs the called contract in the same environment.
+//! The dispatcher lives per thread, i.e. per test.
+
+use core::cell::Cell;
+use ink_prelude::vec::Vec;
+use staking_traits::{AccountId, Balance};
+
+pub use crate::math::{INITIAL_STEP, MULTIPLIER_BASE};
+
+/// Answers the call of `selector` with the SCALE encoded input made by the
+/// first account to the second one, transferring the balance. `None` if the
+/// callee traps.
+pub type Dispatcher = fn(AccountId, AccountId, [u8; 4], &[u8], Balance) -> Option<Vec<u8>>;
+
+std::thread_local! {
+    static DISPATCHER: Cell<Option<Dispatcher>> = Cell::new(None);
+}
+
+/// Routes the calls made by the staking contract on this thread to `dispatcher`.
+pub fn set_dispatcher(dispatcher: Dispatcher) {
+    DISPATCHER.with(|cell| cell.set(Some(dispatcher)))
+}
+
+/// Answers the call through the registered dispatcher, `None` without one.
+pub(crate) fn call(
+    caller: AccountId,
+    callee: AccountId,
+    selector: [u8; 4],
+    input: &[u8],
+    value: Balance,
+) -> Option<Vec<u8>> {
+    DISPATCHER
+        .with(Cell::get)
+        .and_then(|dispatch| dispatch(caller, callee, selector, input, value))
+}
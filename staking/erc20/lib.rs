@@ -4,11 +4,15 @@ pub use self::erc20::{Erc20, Erc20Ref};
 
 use ink_lang as ink;
 
-#[ink::contract]
+#[ink::contract(env = staking_traits::StakingEnvironment)]
 mod erc20 {
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
     use ink_prelude::{vec, vec::Vec};
     use ink_storage::{traits::SpreadAllocate, Mapping};
 
+    /// Identifier of a balance snapshot, starting at 1.
+    pub type SnapshotId = u32;
+
     /// A simple ERC-20 contract.
     #[ink(storage)]
     #[derive(SpreadAllocate)]
@@ -24,6 +28,16 @@ mod erc20 {
         // symbol: String,
         nonce: Balance,
         owner: AccountId,
+        /// Identifier of the latest snapshot, `0` before the first one.
+        current_snapshot_id: SnapshotId,
+        /// Balances before their first change after each snapshot, by ascending
+        /// snapshot id.
+        balance_snapshots: Mapping<AccountId, Vec<(SnapshotId, Balance)>>,
+        /// Total supplies before their first change after each snapshot, by
+        /// ascending snapshot id.
+        total_supply_snapshots: Vec<(SnapshotId, Balance)>,
+        /// Nonce of the next `permit` signed by each owner.
+        permit_nonces: Mapping<AccountId, u64>,
     }
 
     /// Event emitted when a token transfer occurs.
@@ -55,18 +69,45 @@ mod erc20 {
         value: Balance,
     }
 
+    /// Event emitted when the owner takes a balance snapshot.
+    #[ink(event)]
+    pub struct Snapshot {
+        #[ink(topic)]
+        id: SnapshotId,
+    }
+
     /// The ERC-20 error types.
+    ///
+    /// The SCALE index of each variant is its stable error code, shown in the
+    /// contract metadata.
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         /// Returned if not enough balance to fulfill a request is available.
+        #[codec(index = 0)]
         InsufficientBalance,
         /// Returned if not enough allowance to fulfill a request is available.
+        #[codec(index = 1)]
         InsufficientAllowance,
         /// Returned if deadline of permit signature expired.
+        #[codec(index = 2)]
         DeadlineExpired,
         /// Returned if invalid nonce is passed to permit func.
+        #[codec(index = 3)]
         InvalidNonce,
+        /// Returned if the receiving contract rejected the tokens, which have been
+        /// returned to the sender.
+        #[codec(index = 4)]
+        Rejected,
+        /// Returned if the caller is not the owner.
+        #[codec(index = 5)]
+        NotOwner,
+        /// Returned if the snapshot has not been taken yet.
+        #[codec(index = 6)]
+        InvalidSnapshot,
+        /// Returned if a permit is not signed by its owner.
+        #[codec(index = 7)]
+        InvalidSignature,
     }
 
     /// The ERC-20 result type.
@@ -108,6 +149,7 @@ mod erc20 {
             let caller = Self::env().caller();
             let pre_balance = self.balance_of_impl(&caller);
 
+            self.update_snapshots(&caller);
             self.balances.insert(&caller, &(pre_balance + _amount));
             // self.name = name;
             // self.symbol = symbol;
@@ -119,15 +161,16 @@ mod erc20 {
             Ok(())
         }
 
-        /// burn some erc20 token.
+        /// burn some of caller's erc20 token.
         #[ink(message)]
         pub fn burn(&mut self, _amount: Balance) -> Result<()> {
-            let caller = Self::env().account_id();
+            let caller = Self::env().caller();
             let pre_balance = self.balance_of_impl(&caller);
 
             if pre_balance < _amount {
                 return Err(Error::InsufficientBalance);
             }
+            self.update_snapshots(&caller);
             self.balances.insert(&caller, &(pre_balance - _amount));
             // self.name = name;
             // self.symbol = symbol;
@@ -157,6 +200,92 @@ mod erc20 {
             self.total_supply
         }
 
+        /// Takes a snapshot of all balances and the total supply.
+        ///
+        /// Returns the identifier of the snapshot, to be passed to `balance_of_at`
+        /// and `total_supply_at`, e.g. to distribute dividends pro-rata.
+        ///
+        /// A `Snapshot` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        #[ink(message)]
+        pub fn snapshot(&mut self) -> Result<SnapshotId> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            self.current_snapshot_id += 1;
+            let id = self.current_snapshot_id;
+            self.env().emit_event(Snapshot { id });
+            Ok(id)
+        }
+
+        /// Returns the identifier of the latest snapshot, `0` if none has been taken.
+        #[ink(message)]
+        pub fn current_snapshot_id(&self) -> SnapshotId {
+            self.current_snapshot_id
+        }
+
+        /// Returns the balance of `owner` at the snapshot `snapshot_id`.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InvalidSnapshot` error if the snapshot has not been taken.
+        #[ink(message)]
+        pub fn balance_of_at(&self, owner: AccountId, snapshot_id: SnapshotId) -> Result<Balance> {
+            self.ensure_snapshot(snapshot_id)?;
+            let snapshots = self.balance_snapshots.get(&owner).unwrap_or_default();
+            Ok(Self::value_at(&snapshots, snapshot_id)
+                .unwrap_or_else(|| self.balance_of_impl(&owner)))
+        }
+
+        /// Returns the total token supply at the snapshot `snapshot_id`.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InvalidSnapshot` error if the snapshot has not been taken.
+        #[ink(message)]
+        pub fn total_supply_at(&self, snapshot_id: SnapshotId) -> Result<Balance> {
+            self.ensure_snapshot(snapshot_id)?;
+            Ok(Self::value_at(&self.total_supply_snapshots, snapshot_id)
+                .unwrap_or(self.total_supply))
+        }
+
+        fn ensure_snapshot(&self, snapshot_id: SnapshotId) -> Result<()> {
+            if snapshot_id == 0 || snapshot_id > self.current_snapshot_id {
+                return Err(Error::InvalidSnapshot);
+            }
+            Ok(())
+        }
+
+        /// Returns the value recorded for `snapshot_id`, or `None` if it has not
+        /// changed since, in which case the current value applies.
+        fn value_at(
+            snapshots: &[(SnapshotId, Balance)],
+            snapshot_id: SnapshotId,
+        ) -> Option<Balance> {
+            let index = snapshots.partition_point(|&(id, _)| id < snapshot_id);
+            snapshots.get(index).map(|&(_, value)| value)
+        }
+
+        /// Records the balance of `owner` and the total supply for the current
+        /// snapshot unless done already. Must be called before changing them.
+        fn update_snapshots(&mut self, owner: &AccountId) {
+            let id = self.current_snapshot_id;
+            if id == 0 {
+                return;
+            }
+            let mut snapshots = self.balance_snapshots.get(owner).unwrap_or_default();
+            if snapshots.last().map_or(true, |&(last, _)| last < id) {
+                snapshots.push((id, self.balance_of_impl(owner)));
+                self.balance_snapshots.insert(owner, &snapshots);
+            }
+            if self.total_supply_snapshots.last().map_or(true, |&(last, _)| last < id) {
+                self.total_supply_snapshots.push((id, self.total_supply));
+            }
+        }
+
         /// Returns the account balance for the specified `owner`.
         ///
         /// Returns `0` if the account is non-existent.
@@ -213,6 +342,56 @@ mod erc20 {
             self.transfer_from_to(&from, &to, value)
         }
 
+        /// Transfers `value` amount of tokens from the caller's account to account `to`
+        /// and, if `to` is a contract, notifies it by calling its `on_token_received`.
+        ///
+        /// This lets the staking contract stake tokens sent to it without a prior
+        /// `approve`. `data` is passed on unchanged.
+        ///
+        /// # Note
+        ///
+        /// The receiving contract implements
+        /// `on_token_received(from: AccountId, value: Balance, data: Vec<u8>) -> Result<(), E>`.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        ///
+        /// Returns `Rejected` error if the receiving contract failed or returned an
+        /// error, after returning the tokens to the caller.
+        #[ink(message)]
+        pub fn transfer_and_call(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let from = self.env().caller();
+            self.transfer_from_to(&from, &to, value)?;
+            if !self.env().is_contract(&to) {
+                return Ok(());
+            }
+            let accepted = build_call::<staking_traits::StakingEnvironment>()
+                .call_type(Call::new().callee(to).gas_limit(0))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink_lang::selector_bytes!(
+                        "on_token_received"
+                    )))
+                    .push_arg(from)
+                    .push_arg(value)
+                    .push_arg(data),
+                )
+                .returns::<core::result::Result<(), staking_traits::Error>>()
+                .fire();
+            if !matches!(accepted, Ok(Ok(()))) {
+                // Errors don't revert the storage, hand the tokens back explicitly.
+                self.transfer_from_to(&to, &from, value)?;
+                return Err(Error::Rejected);
+            }
+            Ok(())
+        }
+
         /// Allows `spender` to withdraw from the caller's account multiple times, up to
         /// the `value` amount.
         ///
@@ -253,6 +432,72 @@ mod erc20 {
             Ok(())
         }
 
+        /// Allows `spender` to withdraw from `owner`'s account multiple times, up to the
+        /// `value` amount, on behalf of `owner` who signed the approval off-chain.
+        ///
+        /// This lets a contract approve and pull tokens in a single transaction, e.g.
+        /// `stake_with_permit` of the staking contract.
+        ///
+        /// If this function is called again it overwrites the current allowance with `value`.
+        ///
+        /// An `Approval` event is emitted.
+        ///
+        /// # Note
+        ///
+        /// `signature` is the recoverable ECDSA signature of the Blake2x256 hash of the
+        /// SCALE encoded `(token, owner, spender, value, nonce, deadline)`, where `token`
+        /// is this contract and `nonce` is `permit_nonce(owner)`. `owner` must be the
+        /// account of the signing key, i.e. the Blake2x256 hash of its compressed public
+        /// key. sr25519 signatures can't be verified, as contracts only have access to
+        /// ECDSA recovery.
+        ///
+        /// # Errors
+        ///
+        /// Returns `DeadlineExpired` error if the block timestamp is past `deadline`.
+        ///
+        /// Returns `InvalidSignature` error if `owner` didn't sign the permit.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: u64,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            use ink_env::hash::{Blake2x256, HashOutput};
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::DeadlineExpired);
+            }
+            let nonce = self.permit_nonce(owner);
+            let message = (self.env().account_id(), owner, spender, value, nonce, deadline);
+            let mut message_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&message, &mut message_hash);
+            let mut public_key = [0u8; 33];
+            self.env()
+                .ecdsa_recover(&signature, &message_hash, &mut public_key)
+                .map_err(|_| Error::InvalidSignature)?;
+            let mut signer = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_bytes::<Blake2x256>(&public_key, &mut signer);
+            if AccountId::from(signer) != owner {
+                return Err(Error::InvalidSignature);
+            }
+            self.permit_nonces.insert(&owner, &(nonce + 1));
+            self.allowances.insert((&owner, &spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Returns the nonce `owner` has to sign into the next `permit`.
+        #[ink(message)]
+        pub fn permit_nonce(&self, owner: AccountId) -> u64 {
+            self.permit_nonces.get(&owner).unwrap_or_default()
+        }
+
         /// Permits `spender` to withdraw from the caller's account multiple times, up to
         /// the `value` amount.
         /// This function requires signature and hash for ECDSA recover algorithm.
@@ -373,6 +618,8 @@ mod erc20 {
                 return Err(Error::InsufficientBalance);
             }
 
+            self.update_snapshots(from);
+            self.update_snapshots(to);
             self.balances.insert(from, &(from_balance - value));
             let to_balance = self.balance_of_impl(to);
             self.balances.insert(to, &(to_balance + value));
@@ -739,6 +986,50 @@ mod erc20 {
             )
         }
 
+        #[ink::test]
+        fn permit_requires_owner_signature() {
+            let mut erc20 = Erc20::new(100);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            assert_eq!(
+                erc20.permit(accounts.alice, accounts.bob, 10, 0, [0; 65]),
+                Err(Error::InvalidSignature)
+            );
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(1);
+            assert_eq!(
+                erc20.permit(accounts.alice, accounts.bob, 10, 0, [0; 65]),
+                Err(Error::DeadlineExpired)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(erc20.permit_nonce(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn snapshots_keep_past_balances() {
+            let mut erc20 = Erc20::new(100);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            assert_eq!(erc20.balance_of_at(accounts.alice, 1), Err(Error::InvalidSnapshot));
+
+            assert_eq!(erc20.snapshot(), Ok(1));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.snapshot(), Ok(2));
+            assert_eq!(erc20.transfer(accounts.bob, 20), Ok(()));
+            assert_eq!(erc20.mint(50), Ok(()));
+
+            assert_eq!(erc20.balance_of_at(accounts.alice, 1), Ok(100));
+            assert_eq!(erc20.balance_of_at(accounts.bob, 1), Ok(0));
+            assert_eq!(erc20.balance_of_at(accounts.alice, 2), Ok(90));
+            assert_eq!(erc20.balance_of_at(accounts.bob, 2), Ok(10));
+            assert_eq!(erc20.total_supply_at(1), Ok(100));
+            assert_eq!(erc20.total_supply_at(2), Ok(100));
+            assert_eq!(erc20.total_supply(), 150);
+
+            // Only the owner takes snapshots.
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.snapshot(), Err(Error::NotOwner));
+        }
+
         /// For calculating the event topic hash.
         struct PrefixedValue<'a, 'b, T> {
             pub prefix: &'a [u8],
@@ -0,0 +1,160 @@
+//! End-to-end scenarios of the staking contract against the ERC20 contract.
+//!
+//! Both contracts run in the off-chain environment of ink!. The staking
+//! contract is built with its `e2e` feature, which hands its calls to other
+//! contracts to `dispatch`, and `dispatch` executes them on the deployed token
+//! under the token's account, so every transfer moves real token balances.
+//!
+//! Upgrades through `set_code_hash` need a contracts node, they are not
+//! covered here.
+
+use erc20::Erc20;
+use scale::{Decode, Encode};
+use staking_traits::{AccountId, Balance, StakingEnvironment as Environment};
+use std::cell::RefCell;
+use Staking::{
+    e2e::{self, INITIAL_STEP, MULTIPLIER_BASE},
+    Staking as Pool,
+};
+
+#[cfg(test)]
+mod scenarios;
+
+const TOTAL_SUPPLY: [u8; 4] = ink_lang::selector_bytes!("total_supply");
+const BALANCE_OF: [u8; 4] = ink_lang::selector_bytes!("balance_of");
+const NONCE: [u8; 4] = ink_lang::selector_bytes!("nonce");
+const TRANSFER: [u8; 4] = ink_lang::selector_bytes!("transfer");
+const TRANSFER_FROM: [u8; 4] = ink_lang::selector_bytes!("transfer_from");
+const TRANSFER_WITH_SIGNATURE: [u8; 4] = ink_lang::selector_bytes!("transfer_with_signature");
+const APPROVE: [u8; 4] = ink_lang::selector_bytes!("approve");
+const ALLOWANCE: [u8; 4] = ink_lang::selector_bytes!("allowance");
+const BURN: [u8; 4] = ink_lang::selector_bytes!("burn");
+const MINT: [u8; 4] = ink_lang::selector_bytes!("mint");
+
+std::thread_local! {
+    static TOKEN: RefCell<Option<Erc20>> = RefCell::new(None);
+}
+
+/// Account of the deployed token.
+pub fn token_account() -> AccountId {
+    AccountId::from([0x10; 32])
+}
+
+/// Account of the deployed staking pool.
+pub fn pool_account() -> AccountId {
+    AccountId::from([0x50; 32])
+}
+
+/// Runs `f` as a call of `caller` to `callee`, restoring the current call after.
+pub fn as_call<T>(caller: AccountId, callee: AccountId, f: impl FnOnce() -> T) -> T {
+    let outer_caller = ink_env::caller::<Environment>();
+    let outer_callee = ink_env::account_id::<Environment>();
+    ink_env::test::set_caller::<Environment>(caller);
+    ink_env::test::set_callee::<Environment>(callee);
+    let output = f();
+    ink_env::test::set_caller::<Environment>(outer_caller);
+    ink_env::test::set_callee::<Environment>(outer_callee);
+    output
+}
+
+/// Deploys the token with `supply` minted to `owner`.
+pub fn deploy_token(owner: AccountId, supply: Balance) {
+    let token = as_call(owner, token_account(), || Erc20::new(supply));
+    TOKEN.with(|cell| *cell.borrow_mut() = Some(token));
+    e2e::set_dispatcher(dispatch);
+}
+
+/// Deploys a pool of the token owned by `owner`, charging `withdrawal_fee_bps`
+/// on early claims.
+pub fn deploy_pool(owner: AccountId, withdrawal_fee_bps: u32) -> Pool {
+    as_call(owner, pool_account(), || {
+        Pool::new(
+            token_account(),
+            0,
+            MULTIPLIER_BASE,
+            withdrawal_fee_bps,
+            INITIAL_STEP,
+        )
+    })
+}
+
+/// Runs `f` on the deployed token as a call of `caller`.
+pub fn with_token<T>(caller: AccountId, f: impl FnOnce(&mut Erc20) -> T) -> T {
+    as_call(caller, token_account(), || {
+        TOKEN.with(|cell| f(cell.borrow_mut().as_mut().expect("token not deployed")))
+    })
+}
+
+/// Returns the token balance of `owner`.
+pub fn balance_of(owner: AccountId) -> Balance {
+    with_token(owner, |token| token.balance_of(owner))
+}
+
+/// Executes the call of `selector` made by the staking contract on the token.
+/// Selectors the token doesn't implement, e.g. `decimals`, trap.
+fn dispatch(
+    caller: AccountId,
+    callee: AccountId,
+    selector: [u8; 4],
+    mut input: &[u8],
+    _value: Balance,
+) -> Option<Vec<u8>> {
+    if callee != token_account() {
+        return None;
+    }
+    let input = &mut input;
+    with_token(caller, |token| {
+        let output = match selector {
+            TOTAL_SUPPLY => token.total_supply().encode(),
+            BALANCE_OF => token.balance_of(AccountId::decode(input).ok()?).encode(),
+            NONCE => token.nonce().encode(),
+            TRANSFER => {
+                let (to, value) = <(AccountId, Balance)>::decode(input).ok()?;
+                encode_result(token.transfer(to, value))
+            }
+            TRANSFER_FROM => {
+                let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(input).ok()?;
+                encode_result(token.transfer_from(from, to, value))
+            }
+            TRANSFER_WITH_SIGNATURE => {
+                let (from, to, value, deadline, nonce, signature, message_hash) = <(
+                    AccountId,
+                    AccountId,
+                    Balance,
+                    u64,
+                    Balance,
+                    [u8; 65],
+                    [u8; 32],
+                )>::decode(input)
+                .ok()?;
+                encode_result(token.transfer_with_signature(
+                    from,
+                    to,
+                    value,
+                    deadline,
+                    nonce,
+                    signature,
+                    message_hash,
+                ))
+            }
+            APPROVE => {
+                let (spender, value) = <(AccountId, Balance)>::decode(input).ok()?;
+                encode_result(token.approve(spender, value))
+            }
+            ALLOWANCE => {
+                let (owner, spender) = <(AccountId, AccountId)>::decode(input).ok()?;
+                token.allowance(owner, spender).encode()
+            }
+            BURN => encode_result(token.burn(Balance::decode(input).ok()?)),
+            MINT => encode_result(token.mint(Balance::decode(input).ok()?)),
+            _ => return None,
+        };
+        Some(output)
+    })
+}
+
+/// Encodes the result of a token message the way the staking contract decodes
+/// it, the token's errors being fieldless.
+fn encode_result<E: Encode>(result: Result<(), E>) -> Vec<u8> {
+    result.map_err(|error| error.encode()[0]).encode()
+}
@@ -0,0 +1,110 @@
+//! Full scenarios across the deployed token and staking pool.
+
+use super::*;
+use staking_traits::{Error, Timestamp};
+
+/// Tokens handed to each staker.
+const WALLET: Balance = 10_000;
+
+fn accounts() -> ink_env::test::DefaultAccounts<Environment> {
+    ink_env::test::default_accounts::<Environment>()
+}
+
+fn set_timestamp(timestamp: Timestamp) {
+    ink_env::test::set_block_timestamp::<Environment>(timestamp);
+}
+
+/// Runs `f` as a call of `caller` to the pool.
+fn call<T>(caller: AccountId, f: impl FnOnce() -> T) -> T {
+    as_call(caller, pool_account(), f)
+}
+
+/// Deploys the token owned by alice and a pool of it charging
+/// `withdrawal_fee_bps`, and hands tokens to bob and charlie.
+fn deploy(withdrawal_fee_bps: u32) -> Pool {
+    let accounts = accounts();
+    set_timestamp(0);
+    deploy_token(accounts.alice, 1_000_000);
+    for staker in [accounts.bob, accounts.charlie] {
+        assert!(with_token(accounts.alice, |token| token.transfer(staker, WALLET)).is_ok());
+    }
+    deploy_pool(accounts.alice, withdrawal_fee_bps)
+}
+
+/// A matured stake is paid back in full.
+#[ink_lang::test]
+fn stake_and_claim_move_tokens() {
+    let mut pool = deploy(0);
+    let bob = accounts().bob;
+    assert_eq!(call(bob, || pool.stake(1000, None)), Ok(0));
+    assert_eq!(balance_of(bob), WALLET - 1000);
+    assert_eq!(balance_of(pool_account()), 1000);
+    set_timestamp(7 * INITIAL_STEP);
+    assert_eq!(call(bob, || pool.claim_all(false)), Ok(1000));
+    assert_eq!(balance_of(bob), WALLET);
+    assert_eq!(balance_of(pool_account()), 0);
+    assert!(call(bob, || pool.health()).ledger_balanced);
+}
+
+/// Claiming before maturity withholds the withdrawal fee in the pool.
+#[ink_lang::test]
+fn early_claim_pays_the_penalty() {
+    let mut pool = deploy(500);
+    let bob = accounts().bob;
+    assert_eq!(call(bob, || pool.stake(1000, None)), Ok(0));
+    set_timestamp(INITIAL_STEP);
+    let claimed = call(bob, || pool.claim(500, None, None)).unwrap();
+    assert!(claimed < 500);
+    assert_eq!(balance_of(bob), WALLET - 1000 + claimed);
+    assert_eq!(balance_of(pool_account()), 1000 - claimed);
+    assert_eq!(pool.get_ledger().treasury, 500 - claimed);
+    assert!(call(bob, || pool.health()).ledger_balanced);
+}
+
+/// Rewards funded in the staked token are pulled from the owner and paid to
+/// the stakers.
+#[ink_lang::test]
+fn funded_rewards_are_paid_in_tokens() {
+    let mut pool = deploy(0);
+    let (alice, bob) = (accounts().alice, accounts().bob);
+    assert_eq!(call(bob, || pool.stake(1000, None)), Ok(0));
+    let funded = 864_000;
+    assert_eq!(call(alice, || pool.fund_rewards(funded)), Ok(()));
+    assert_eq!(balance_of(pool_account()), 1000 + funded);
+    set_timestamp(INITIAL_STEP);
+    let rewards = call(bob, || pool.claim_rewards(None)).unwrap();
+    assert!(rewards > 0 && rewards <= funded);
+    assert_eq!(balance_of(bob), WALLET - 1000 + rewards);
+    assert!(call(bob, || pool.health()).ledger_balanced);
+}
+
+/// Pausing stops new stakes without touching the stakers' tokens.
+#[ink_lang::test]
+fn pause_blocks_stakes() {
+    let mut pool = deploy(0);
+    let (alice, bob) = (accounts().alice, accounts().bob);
+    assert_eq!(call(alice, || pool.set_paused(true)), Ok(()));
+    assert_eq!(call(bob, || pool.stake(1000, None)), Err(Error::Paused));
+    assert_eq!(balance_of(bob), WALLET);
+    assert_eq!(call(alice, || pool.set_paused(false)), Ok(()));
+    assert_eq!(call(bob, || pool.stake(1000, None)), Ok(0));
+    assert_eq!(balance_of(pool_account()), 1000);
+}
+
+/// Staking from an allowance needs the staker's approval of the pool first.
+#[ink_lang::test]
+fn stake_from_allowance_needs_the_approval() {
+    let mut pool = deploy(0);
+    let bob = accounts().bob;
+    assert_eq!(
+        call(bob, || pool.stake_from_allowance(1000, None)),
+        Err(Error::InsufficientAllowance {
+            allowance: 0,
+            missing: 1000
+        })
+    );
+    assert!(with_token(bob, |token| token.approve(pool_account(), 1000)).is_ok());
+    assert_eq!(call(bob, || pool.stake_from_allowance(1000, None)), Ok(0));
+    assert_eq!(balance_of(bob), WALLET - 1000);
+    assert_eq!(balance_of(pool_account()), 1000);
+}
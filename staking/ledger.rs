@@ -0,0 +1,202 @@
+//! Double-entry book keeping of the amounts held by the staking contract.
+//!
+//! Every movement is posted as a debit of one book and a credit of another.
+//! `Holdings` and `NativeHoldings` are the asset books of the token and of the
+//! native currency, all other books are owed to someone, so the ledger is
+//! balanced as long as each asset book equals the sum of the books owed in its
+//! currency. The reward books are owed in the native currency when rewards are
+//! paid in it, and in the token otherwise.
+
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use staking_traits::Balance;
+
+/// Books of the ledger.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Book {
+    /// Tokens held against the books owed in tokens.
+    Holdings,
+    /// Native currency held against the books owed in the native currency.
+    NativeHoldings,
+    /// Staked principal owed to the stakers.
+    Principal,
+    /// Released principal and rewards held back after failed payouts (escrow).
+    Released,
+    /// Funded rewards not emitted yet.
+    RewardFunds,
+    /// Emitted rewards not paid out yet.
+    RewardsOwed,
+    /// Rewards locked into the boost vault.
+    Boosted,
+    /// Withdrawal fees owed to the treasury.
+    Treasury,
+    /// Gifted stakes waiting for acceptance by their recipient.
+    Gifts,
+    /// Released principal listed for sale to a counterparty.
+    Otc,
+    /// Native currency sponsoring the storage deposits of new stakers.
+    RentPot,
+    /// Deposits waiting to be confirmed as stakes or refunded.
+    Deposits,
+}
+
+impl Book {
+    /// Whether the book holds the amounts owed in the other books.
+    fn is_asset(self) -> bool {
+        matches!(self, Book::Holdings | Book::NativeHoldings)
+    }
+}
+
+/// Balances of all books.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct Ledger {
+    pub holdings: Balance,
+    pub native_holdings: Balance,
+    pub principal: Balance,
+    pub released: Balance,
+    pub reward_funds: Balance,
+    pub rewards_owed: Balance,
+    pub boosted: Balance,
+    pub treasury: Balance,
+    pub gifts: Balance,
+    pub otc: Balance,
+    pub rent_pot: Balance,
+    pub deposits: Balance,
+}
+
+impl Ledger {
+    /// Returns the balance of `book`.
+    pub fn balance(&self, book: Book) -> Balance {
+        match book {
+            Book::Holdings => self.holdings,
+            Book::NativeHoldings => self.native_holdings,
+            Book::Principal => self.principal,
+            Book::Released => self.released,
+            Book::RewardFunds => self.reward_funds,
+            Book::RewardsOwed => self.rewards_owed,
+            Book::Boosted => self.boosted,
+            Book::Treasury => self.treasury,
+            Book::Gifts => self.gifts,
+            Book::Otc => self.otc,
+            Book::RentPot => self.rent_pot,
+            Book::Deposits => self.deposits,
+        }
+    }
+
+    /// Debits `debit` and credits `credit` with `amount`.
+    ///
+    /// Receiving tokens or native currency debits its asset book, paying them
+    /// out credits it. Moving
+    /// an amount between two owed books debits the source and credits the target.
+    ///
+    /// # Panics
+    ///
+    /// If a book would fall below zero, which is an accounting bug and must
+    /// revert the whole call instead of wrapping silently.
+    pub fn post(&mut self, debit: Book, credit: Book, amount: Balance) {
+        if debit == credit || amount == 0 {
+            return;
+        }
+        let debited = if debit.is_asset() {
+            self.balance(debit).checked_add(amount)
+        } else {
+            self.balance(debit).checked_sub(amount)
+        };
+        *self.book_mut(debit) = debited.expect("ledger book out of range");
+        let credited = if credit.is_asset() {
+            self.balance(credit).checked_sub(amount)
+        } else {
+            self.balance(credit).checked_add(amount)
+        };
+        *self.book_mut(credit) = credited.expect("ledger book out of range");
+        debug_assert!(self.is_balanced());
+    }
+
+    /// Whether each asset book equals the sum of the books owed in its currency.
+    pub fn is_balanced(&self) -> bool {
+        let tokens = self
+            .principal
+            .checked_add(self.released)
+            .and_then(|owed| owed.checked_add(self.treasury))
+            .and_then(|owed| owed.checked_add(self.gifts))
+            .and_then(|owed| owed.checked_add(self.otc))
+            .and_then(|owed| owed.checked_add(self.deposits));
+        let rewards = self
+            .reward_funds
+            .checked_add(self.rewards_owed)
+            .and_then(|owed| owed.checked_add(self.boosted));
+        let (tokens, rewards) = match (tokens, rewards) {
+            (Some(tokens), Some(rewards)) => (tokens, rewards),
+            _ => return false,
+        };
+        let native = self.rent_pot;
+        // The rewards are owed in one of the two currencies.
+        let in_tokens = tokens.checked_add(rewards) == Some(self.holdings)
+            && native == self.native_holdings;
+        let in_native = tokens == self.holdings
+            && native.checked_add(rewards) == Some(self.native_holdings);
+        in_tokens || in_native
+    }
+
+    fn book_mut(&mut self, book: Book) -> &mut Balance {
+        match book {
+            Book::Holdings => &mut self.holdings,
+            Book::NativeHoldings => &mut self.native_holdings,
+            Book::Principal => &mut self.principal,
+            Book::Released => &mut self.released,
+            Book::RewardFunds => &mut self.reward_funds,
+            Book::RewardsOwed => &mut self.rewards_owed,
+            Book::Boosted => &mut self.boosted,
+            Book::Treasury => &mut self.treasury,
+            Book::Gifts => &mut self.gifts,
+            Book::Otc => &mut self.otc,
+            Book::RentPot => &mut self.rent_pot,
+            Book::Deposits => &mut self.deposits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_books_balance_apart() {
+        let mut ledger = Ledger::default();
+        ledger.post(Book::Holdings, Book::Principal, 1000);
+        ledger.post(Book::NativeHoldings, Book::RentPot, 50);
+        assert_eq!(ledger.holdings, 1000);
+        assert_eq!(ledger.native_holdings, 50);
+        assert!(ledger.is_balanced());
+        ledger.post(Book::RentPot, Book::NativeHoldings, 20);
+        assert!(ledger.is_balanced());
+    }
+
+    #[test]
+    fn rewards_balance_in_one_currency() {
+        let mut ledger = Ledger::default();
+        ledger.post(Book::Holdings, Book::Principal, 1000);
+        ledger.post(Book::NativeHoldings, Book::RewardFunds, 300);
+        ledger.post(Book::RewardFunds, Book::RewardsOwed, 100);
+        assert!(ledger.is_balanced());
+        ledger.post(Book::RewardsOwed, Book::NativeHoldings, 100);
+        assert!(ledger.is_balanced());
+        ledger.native_holdings -= 1;
+        assert!(!ledger.is_balanced());
+    }
+}
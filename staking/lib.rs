@@ -1,26 +1,78 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub use self::staking::Staking;
+
 use ink_lang as ink;
 
-#[ink::contract]
+mod bounded;
+mod compact;
+#[cfg(feature = "e2e")]
+pub mod e2e;
+mod ledger;
+mod math;
+#[cfg(test)]
+mod mock;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod units;
+
+/// Prints to the debug buffer only when built with the `debug` feature,
+/// keeping the formatting machinery out of production Wasm blobs.
+macro_rules! debug_println {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug")]
+        ink_env::debug_println!($($arg)*);
+    };
+}
+
+#[ink::contract(env = staking_traits::StakingEnvironment)]
 mod staking {
     use erc20::Erc20Ref;
     use ink_env;
-    use ink_env::call::FromAccountId;
+    use ink_env::call::{FromAccountId, ToAccountId};
     use ink_prelude::{
+        format,
         // string::ToString,
+        string::String,
         vec,
         vec::Vec,
     };
+    use ink_primitives::{Key, KeyPtr};
     use ink_storage::{
-        collections::HashMap as StorageHashMap,
+        collections::{HashMap as StorageHashMap, Stash, Vec as StorageVec},
         traits::{
             // SpreadAllocate,
+            ExtKeyPtr,
             PackedLayout,
             SpreadLayout,
         },
+        Lazy,
+    };
+
+    use crate::{
+        compact::CompactStakes,
+        ledger::{Book, Ledger},
+        math::{self, INITIAL_STEP, INITIAL_UNLOCKED_PERMILLE, MULTIPLIER_BASE, UNLOCK_PRECISION},
+        units::{BlockNumberOf, TokenAmount, TokenBlocks},
     };
 
+    pub use crate::math::Rounding;
+    pub use staking_traits::{
+        AccountSummary,
+        Error,
+        GiftId,
+        OfferId,
+        ReceiptId,
+        Result,
+        Role,
+        ScheduleVersion,
+        Stake,
+        StakeId,
+        StakingEnvironment,
+        StakingView,
+    };
+    use scale::Encode;
+    #[cfg(feature = "rand-std")]
     use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
 
     /// Defines the storage of your contract.
@@ -29,274 +81,6799 @@ mod staking {
     // #[derive(SpreadAllocate)]
     #[ink(storage)]
     pub struct Staking {
-        staked: StorageHashMap<AccountId, Vec<Stake>>,
-        unstaked: StorageHashMap<AccountId, Vec<Balance>>,
+        staked: StorageHashMap<AccountId, Stakes>,
         token: Erc20Ref,
         sig_status: u128, //////////////////////////////
+        /// Withdrawable balances of payouts whose token transfer failed.
+        escrow: StorageHashMap<AccountId, Balance>,
+        /// Amounts claimed per (account, client-supplied claim id).
+        claim_ids: StorageHashMap<(AccountId, ClaimId), Balance>,
+        /// Administrator of the contract parameters.
+        owner: AccountId,
+        /// Account the ownership is being transferred to.
+        pending_owner: Option<AccountId>,
+        /// Rounding applied to the unlock math.
+        rounding: Rounding,
+        /// Double-entry books of the amounts held and owed: staked principal,
+        /// escrow, rewards and fees.
+        ledger: Ledger,
+        /// Decimals of the staked token, read at construction.
+        decimals: u8,
+        /// Minimal amount of a single stake.
+        min_stake: Balance,
+        /// Maximal amount of a single stake, `0` if unlimited.
+        max_stake: Balance,
+        /// Maximal number of accounts holding stakes, `0` if unlimited.
+        max_stakers: u32,
+        /// Accounts holding stakes, in no particular order.
+        stakers: StorageVec<AccountId>,
+        /// Position of each staker in `stakers`.
+        staker_index: StorageHashMap<AccountId, u32>,
+        /// Minimal amount of a partial claim.
+        dust_threshold: Balance,
+        /// Token replacement proposed by the owner and the time it becomes executable.
+        pending_token: Option<(AccountId, Timestamp)>,
+        /// Whether staking and claiming are suspended.
+        paused: bool,
+        /// Rewards emitted per second until `period_finish`, shared pro rata
+        /// among the reward weights.
+        reward_rate: Balance,
+        /// Length of a reward period in ms.
+        rewards_duration: Timestamp,
+        /// End of the current reward period.
+        period_finish: Timestamp,
+        /// Time up to which `reward_per_token_stored` has been accumulated.
+        last_update_time: Timestamp,
+        /// Rewards accumulated per reward weight unit, scaled by `ACC_PRECISION`.
+        reward_per_token_stored: Balance,
+        /// Utilization-driven scaling of `reward_rate`.
+        rate_model: RateModel,
+        /// Reward accounting per account.
+        reward_accounts: StorageHashMap<AccountId, RewardAccount>,
+        /// Staked principal checkpoints per account, in increasing block order.
+        checkpoints: StorageHashMap<AccountId, Vec<Checkpoint>>,
+        /// Sum of the reward weights of all accounts.
+        total_weight: Balance,
+        /// Stakes created before this block get `bonus_multiplier`.
+        bonus_end_block: BlockNumberOf,
+        /// Early-bird reward multiplier in per mille.
+        bonus_multiplier: u32,
+        /// Rewards locked back into the contract per account to boost its weight.
+        boosts: StorageHashMap<AccountId, Boost>,
+        /// Roles granted by the owner.
+        roles: StorageHashMap<(Role, AccountId), ()>,
+        /// Time the emergency mode was entered, freezing schedules and emission.
+        emergency_since: Option<Timestamp>,
+        /// Recovery setup registered per account.
+        recoveries: StorageHashMap<AccountId, Recovery>,
+        /// Ring buffer of the last `MAX_ADMIN_LOG` admin actions.
+        admin_log: StorageHashMap<u32, AdminLogEntry>,
+        /// Number of admin actions logged so far.
+        admin_log_len: u32,
+        /// Unlock schedules by version, stakes keeping the version active at creation.
+        schedules: StorageHashMap<ScheduleVersion, Schedule>,
+        /// Number of schedule versions, the latest applying to new stakes.
+        schedule_count: ScheduleVersion,
+        /// Divisor of all schedule durations, `1` outside testnet rehearsals.
+        /// Only set at instantiation, see `new_scaled`.
+        time_scale: u32,
+        /// Identifier of the next stake.
+        next_stake_id: StakeId,
+        /// Pool replacing this one once deprecated, no new stakes are accepted then.
+        successor: Option<AccountId>,
+        /// Deprecated pool allowed to migrate positions into this one.
+        predecessor: Option<AccountId>,
+        /// Whether the reward pool holds and pays the native currency instead of
+        /// the staked token.
+        native_rewards: bool,
+        /// Oracle quoting the price of a whole reward token, enabling stable-denominated
+        /// reward targeting.
+        price_oracle: Option<AccountId>,
+        /// Quoted value of the rewards to emit per second.
+        target_value: Balance,
+        /// Minimal time between two recalculations of the reward rate.
+        retarget_epoch: Timestamp,
+        /// Time of the last recalculation of the reward rate.
+        last_retarget: Timestamp,
+        /// Ring buffer of the last `MAX_HISTORY` daily checkpoints.
+        history: StorageHashMap<u32, HistoryEntry>,
+        /// Number of daily checkpoints recorded so far.
+        history_len: u32,
+        /// Rewards paid out to the accounts so far.
+        rewards_paid: Balance,
+        /// Accounts opted in to claims by anyone, with their threshold and tip.
+        claim_bots: StorageHashMap<AccountId, ClaimBot>,
+        /// Whitelisted yield strategy the token surplus can be invested into.
+        strategy: Option<AccountId>,
+        /// Tokens invested into `strategy` and not divested yet.
+        invested: Balance,
+        /// Time of the last published obligations report.
+        last_report: Timestamp,
+        /// ERC20 token rewards are paid in, the staked token if `None`.
+        reward_token: Option<AccountId>,
+        /// Number of reward token migrations so far.
+        reward_epoch: u32,
+        /// Snapshot of the rewards owed in the reward token before the last migration.
+        legacy_rewards: Option<LegacyRewards>,
+        /// Settled legacy rewards per account.
+        legacy_owed: StorageHashMap<AccountId, Balance>,
+        /// Address receiving the claims of an account, the account itself if unset.
+        payout_addresses: StorageHashMap<AccountId, AccountId>,
+        /// Proposed payout address per account and the time it can be set.
+        pending_payouts: StorageHashMap<AccountId, (AccountId, Timestamp)>,
+        /// Payout splits of the principal claims per account.
+        payout_splits: StorageHashMap<AccountId, PayoutSplit>,
+        /// Recurring pulls per (account, merchant).
+        pull_authorizations: StorageHashMap<(AccountId, AccountId), PullAuthorization>,
+        /// Granularity claims are rounded down to per account opted into donating
+        /// the remainder.
+        round_ups: StorageHashMap<AccountId, Balance>,
+        /// Donated amount per account.
+        donations: StorageHashMap<AccountId, Balance>,
+        /// Donated amount of all accounts.
+        total_donated: Balance,
+        /// Gifted stakes waiting for acceptance by their recipient.
+        gifts: StorageHashMap<GiftId, Gift>,
+        /// Identifier of the next gift.
+        next_gift_id: GiftId,
+        /// Open OTC offers of released principal.
+        otc_offers: StorageHashMap<OfferId, OtcOffer>,
+        /// Identifier of the next OTC offer.
+        next_offer_id: OfferId,
+        /// Whether contracts other than the allowlisted integrators are rejected
+        /// as callers of the entry points.
+        eoa_only: bool,
+        /// Integrator contracts allowed to call while `eoa_only` is set.
+        integrators: StorageHashMap<AccountId, ()>,
+        /// Reward multipliers of the schedule versions offered as lock tiers.
+        tier_multipliers: StorageHashMap<ScheduleVersion, u32>,
+        /// Current emergency refund vote, `0` before the first vote.
+        refund_round: u32,
+        /// Start of the current refund vote.
+        refund_round_start: Timestamp,
+        /// Principal of the accounts that voted in the current refund vote.
+        refund_votes_total: Balance,
+        /// Refund vote and principal counted per account.
+        refund_votes: StorageHashMap<AccountId, (u32, Balance)>,
+        /// Whether stakers voted for the emergency refund, voiding all locks.
+        refund_open: bool,
+        /// Account taking over the ownership if the owner goes inactive.
+        backup_owner: Option<AccountId>,
+        /// Inactivity of the owner while paused after which the backup owner can
+        /// take over.
+        failover_interval: Timestamp,
+        /// Time of the owner's last admin action.
+        last_owner_action: Timestamp,
+        /// Block of the last admin action, including heartbeats.
+        last_admin_activity: BlockNumber,
+        /// Block of the last stake or claim of any account.
+        last_user_activity: BlockNumber,
+        /// Time the contract has been paused at.
+        paused_since: Timestamp,
+        /// Accounts staking without earning rewards, e.g. the treasury's own stake.
+        reward_excluded: StorageHashMap<AccountId, ()>,
+        /// Accounts whose storage deposit has been sponsored.
+        rent_sponsored: StorageHashMap<AccountId, ()>,
+        /// Receipts of the principal claims.
+        receipts: StorageHashMap<ReceiptId, Receipt>,
+        /// Identifier of the next claim receipt.
+        next_receipt_id: ReceiptId,
+        /// Rarely changing parameters, loaded only by the messages using them.
+        settings: Lazy<Settings>,
+        /// Tokens deposited per account, waiting for `confirm_stake`.
+        pending_deposits: StorageHashMap<AccountId, Balance>,
+        /// Entries of an account in mappings not keyed by the account alone.
+        entry_counts: StorageHashMap<AccountId, EntryCounts>,
+        /// Sibling pools listed in the registry of this pool, by identifier.
+        pools: StorageHashMap<PoolId, AccountId>,
+        /// Identifier of the next listed pool.
+        next_pool_id: PoolId,
+        /// Whether the hub paused all the pools of its registry, on top of `paused`.
+        global_paused: bool,
+        /// Voting power each account spent on the gauges, in its last voting epoch.
+        gauge_votes: StorageHashMap<AccountId, GaugeVote>,
+        /// Gauge votes per epoch and listed pool, removed once distributed.
+        gauge_tallies: StorageHashMap<(u32, PoolId), Balance>,
+        /// Gauge votes per epoch over all pools, removed once distributed.
+        gauge_totals: StorageHashMap<u32, Balance>,
     }
 
-    /// Staking data per wallet
-    ///
-    /// # Note
-    /// This struct is based on std module so that
-    /// we can embed into `staked` data described above.
-    /// Otherwise, might raise ERR.
+    /// Declares `FieldKeys`, the root storage keys of the fields of `Staking`,
+    /// from the fields listed in declaration order with their types.
+    macro_rules! field_keys {
+        ($($field:ident: $ty:ty,)*) => {
+            /// Root storage keys of the fields of `Staking`, laid out one after the
+            /// other from the root key of the contract like its storage.
+            #[allow(dead_code)]
+            struct FieldKeys {
+                $($field: Key,)*
+            }
+
+            impl FieldKeys {
+                fn new() -> Self {
+                    let mut ptr = KeyPtr::from(Key::from([0x00; 32]));
+                    Self {
+                        $($field: *ptr.next_for::<$ty>(),)*
+                    }
+                }
+            }
+        };
+    }
+
+    // Has to follow `Staking`, `storage_keys_match_the_storage` checks it.
+    field_keys! {
+    staked: StorageHashMap<AccountId, Stakes>,
+    token: Erc20Ref,
+    sig_status: u128,
+    escrow: StorageHashMap<AccountId, Balance>,
+    claim_ids: StorageHashMap<(AccountId, ClaimId), Balance>,
+    owner: AccountId,
+    pending_owner: Option<AccountId>,
+    rounding: Rounding,
+    ledger: Ledger,
+    decimals: u8,
+    min_stake: Balance,
+    max_stake: Balance,
+    max_stakers: u32,
+    stakers: StorageVec<AccountId>,
+    staker_index: StorageHashMap<AccountId, u32>,
+    dust_threshold: Balance,
+    pending_token: Option<(AccountId, Timestamp)>,
+    paused: bool,
+    reward_rate: Balance,
+    rewards_duration: Timestamp,
+    period_finish: Timestamp,
+    last_update_time: Timestamp,
+    reward_per_token_stored: Balance,
+    rate_model: RateModel,
+    reward_accounts: StorageHashMap<AccountId, RewardAccount>,
+    checkpoints: StorageHashMap<AccountId, Vec<Checkpoint>>,
+    total_weight: Balance,
+    bonus_end_block: BlockNumberOf,
+    bonus_multiplier: u32,
+    boosts: StorageHashMap<AccountId, Boost>,
+    roles: StorageHashMap<(Role, AccountId), ()>,
+    emergency_since: Option<Timestamp>,
+    recoveries: StorageHashMap<AccountId, Recovery>,
+    admin_log: StorageHashMap<u32, AdminLogEntry>,
+    admin_log_len: u32,
+    schedules: StorageHashMap<ScheduleVersion, Schedule>,
+    schedule_count: ScheduleVersion,
+    time_scale: u32,
+    next_stake_id: StakeId,
+    successor: Option<AccountId>,
+    predecessor: Option<AccountId>,
+    native_rewards: bool,
+    price_oracle: Option<AccountId>,
+    target_value: Balance,
+    retarget_epoch: Timestamp,
+    last_retarget: Timestamp,
+    history: StorageHashMap<u32, HistoryEntry>,
+    history_len: u32,
+    rewards_paid: Balance,
+    claim_bots: StorageHashMap<AccountId, ClaimBot>,
+    strategy: Option<AccountId>,
+    invested: Balance,
+    last_report: Timestamp,
+    reward_token: Option<AccountId>,
+    reward_epoch: u32,
+    legacy_rewards: Option<LegacyRewards>,
+    legacy_owed: StorageHashMap<AccountId, Balance>,
+    payout_addresses: StorageHashMap<AccountId, AccountId>,
+    pending_payouts: StorageHashMap<AccountId, (AccountId, Timestamp)>,
+    payout_splits: StorageHashMap<AccountId, PayoutSplit>,
+    pull_authorizations: StorageHashMap<(AccountId, AccountId), PullAuthorization>,
+    round_ups: StorageHashMap<AccountId, Balance>,
+    donations: StorageHashMap<AccountId, Balance>,
+    total_donated: Balance,
+    gifts: StorageHashMap<GiftId, Gift>,
+    next_gift_id: GiftId,
+    otc_offers: StorageHashMap<OfferId, OtcOffer>,
+    next_offer_id: OfferId,
+    eoa_only: bool,
+    integrators: StorageHashMap<AccountId, ()>,
+    tier_multipliers: StorageHashMap<ScheduleVersion, u32>,
+    refund_round: u32,
+    refund_round_start: Timestamp,
+    refund_votes_total: Balance,
+    refund_votes: StorageHashMap<AccountId, (u32, Balance)>,
+    refund_open: bool,
+    backup_owner: Option<AccountId>,
+    failover_interval: Timestamp,
+    last_owner_action: Timestamp,
+    last_admin_activity: BlockNumber,
+    last_user_activity: BlockNumber,
+    paused_since: Timestamp,
+    reward_excluded: StorageHashMap<AccountId, ()>,
+    rent_sponsored: StorageHashMap<AccountId, ()>,
+    receipts: StorageHashMap<ReceiptId, Receipt>,
+    next_receipt_id: ReceiptId,
+    settings: Lazy<Settings>,
+    pending_deposits: StorageHashMap<AccountId, Balance>,
+    entry_counts: StorageHashMap<AccountId, EntryCounts>,
+    pools: StorageHashMap<PoolId, AccountId>,
+    next_pool_id: PoolId,
+    global_paused: bool,
+    gauge_votes: StorageHashMap<AccountId, GaugeVote>,
+    gauge_tallies: StorageHashMap<(u32, PoolId), Balance>,
+    gauge_totals: StorageHashMap<u32, Balance>,
+    }
+
+    /// Entries of an account in the mappings keyed by claim, pull, receipt, gift
+    /// or offer, counted so that `storage_footprint` doesn't scan these mappings.
     #[derive(
         Copy,
         Clone,
         Debug,
-        Ord,
-        PartialOrd,
+        Default,
         Eq,
         PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct EntryCounts {
+        /// Claim ids used by the account.
+        pub claim_ids: u32,
+        /// Pulls the account authorized.
+        pub pulls: u32,
+        /// Receipts of the account's claims.
+        pub receipts: u32,
+        /// Open gifts funded by the account.
+        pub gifts: u32,
+        /// Open OTC offers of the account.
+        pub offers: u32,
+    }
+
+    /// Voting power an account spent on the gauges in an epoch, see `vote_gauge`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
         Default,
+        Eq,
+        PartialEq,
         PackedLayout,
         SpreadLayout,
         scale::Encode,
         scale::Decode,
     )]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Stake {
-        amount: Balance,
-        timestamp: Balance,
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct GaugeVote {
+        /// Epoch the votes were cast in.
+        pub epoch: u32,
+        /// Voting power spent in `epoch`.
+        pub used: Balance,
     }
 
-    impl Staking {
-        /// @dev    Default Initialization.
-        /// @param  address of pre-deployed ERC20 contract.
-        ///         this is available only after the deployment of ERC20 contract.
-        /// @note   Initialize the contract with pre-deployed erc20 instance address
+    /// Released principal escrowed for sale to a single counterparty, see `list_otc`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct OtcOffer {
+        pub seller: AccountId,
+        pub buyer: AccountId,
+        /// Escrowed amount of the staked token, net of the withdrawal fee.
+        pub amount: Balance,
+        /// ERC20 token the price is paid in.
+        pub price_token: AccountId,
+        pub price: Balance,
+    }
 
-        #[ink(constructor)]
-        pub fn new(_erc20_account_id: AccountId) -> Self {
-            //
-            // let address : AccountId = AccountId::decode(&mut ref_account32).unwrap_or_default();
-            let erc20_instance = Erc20Ref::from_account_id(_erc20_account_id);
-            Self {
-                staked: StorageHashMap::new(),
-                unstaked: StorageHashMap::new(),
-                token: erc20_instance,
-                sig_status: 0, ////////////////////////////
-            }
-        }
+    /// Time a recipient has to accept a gift before the funder can cancel it
+    /// (7 days in ms).
+    const GIFT_WINDOW: Timestamp = 7 * 86400_000;
 
-        /// @dev     Method #1 (WRITE)
-        /// @param   _amount:Balance
-        /// @note    register/update caller's staking data, and stake ERC20 token.
-        #[ink(message)]
-        pub fn stake(&mut self, _amount: Balance) {
-            let caller = self.env().caller();
-            let me = self.env().account_id();
-            let current_block_timestamp: Balance = self.env().block_timestamp().into();
-            if self.token.balance_of(caller) < _amount {
-                ink_env::debug_println!("{}", "Insufficient funds");
-                return;
-            }
-            // Rigister/update caller's staking data.
-            if self.staked.contains_key(&caller) {
-                let mut _staked = self.staked.get_mut(&caller).unwrap();
-                _staked.push(Stake {
-                    timestamp: current_block_timestamp,
-                    amount: _amount,
-                });
-            } else {
-                self.staked.insert(
-                    caller,
-                    vec![Stake {
-                        timestamp: current_block_timestamp,
-                        amount: _amount,
-                    }],
-                );
-            }
-            // Register/update caller's unstaking data.
-            if self.unstaked.contains_key(&caller) {
-                self.unstaked.get_mut(&caller).unwrap().push(0);
-            } else {
-                self.unstaked.insert(caller, vec![0]);
-            }
-            // Transfer ERC20 token to this contract.
+    /// Time stakers have to reach the refund quorum once a vote started
+    /// (7 days in ms).
+    const REFUND_VOTE_WINDOW: Timestamp = 7 * 86400_000;
 
-            self.transfer_with_signature(caller, me, _amount);
-        }
+    /// Percentage of the staked principal that must be exceeded by the votes
+    /// to open the emergency refund.
+    const REFUND_QUORUM_PERCENT: Balance = 66;
 
-        /// @dev       Method #2 (READ)
-        /// @param     
-        /// @note      Stake up to 5 days. Each day within 5 has 10% increament than the day before.
-        #[ink(message)]
-        pub fn get_unstakable(&self, _start: Balance) -> Balance {
-            if u128::from(self.env().block_timestamp()) < _start {
-                return 0;
-            }
-            let times: Balance = u128::from(self.env().block_timestamp()) - _start;
-            let days: Balance = times / 86400_000;
-            match days {
-                0 => 0,
-                1 => 5,
-                2 => 6,
-                3 => 7,
-                4 => 8,
-                5 => 9,
-                _ => 10,
-            }
-        }
+    /// Tokens gifted as a stake, see `gift_stake`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Gift {
+        pub funder: AccountId,
+        pub recipient: AccountId,
+        pub amount: Balance,
+        /// End of the acceptance window.
+        pub expires_at: Timestamp,
+    }
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_balance(&self, _addr: AccountId) -> Balance {
-            let mut balance: Balance = 0;
-            let length = self.staked.get(&_addr).unwrap().len();
-            (0..length).for_each(|i| {
-                let staked_time: Balance = self.staked.get(&_addr).unwrap()[i].timestamp;
-                let staked_amount: Balance = self.staked.get(&_addr).unwrap()[i].amount;
-                balance = balance + self.get_unstakable(staked_time) * staked_amount / 10
-                    - self.unstaked.get(&_addr).unwrap()[i];
-            });
-            return balance;
-        }
+    /// Receipt of a principal claim, see `receipt`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Receipt {
+        /// Account whose principal has been claimed.
+        pub account: AccountId,
+        /// Amount paid out, net of the withdrawal fee.
+        pub amount: Balance,
+        /// Withdrawal fee withheld.
+        pub fee: Balance,
+        /// Time of the claim.
+        pub timestamp: Timestamp,
+        /// Block of the claim.
+        pub block: BlockNumber,
+    }
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_erc20_totalsupply(&self) -> Balance {
-            return self.token.total_supply();
-        }
+    /// Delay between proposing and setting a payout address (2 days in ms).
+    const PAYOUT_TIMELOCK: Timestamp = 2 * 86400_000;
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_erc20_balance(&self, _addr: AccountId) -> Balance {
-            return self.token.balance_of(_addr);
-        }
+    /// Recurring pull of a merchant from the matured balance of an account, see
+    /// `authorize_pull`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct PullAuthorization {
+        /// Amount pulled per period.
+        pub amount: Balance,
+        /// Length of a period in ms.
+        pub period: Timestamp,
+        /// Time from which the next pull is possible.
+        pub next_pull: Timestamp,
+    }
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_staked_timestamp(&self, _addr: AccountId, _index: Balance) -> Balance {
-            return self.staked.get(&_addr).unwrap()
-                [TryInto::<usize>::try_into(_index).ok().unwrap()]
-            .timestamp;
-        }
+    /// Maximal number of beneficiaries of a payout split.
+    const MAX_SPLIT_SHARES: usize = 8;
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_staked_amount(&self, _addr: AccountId, _index: Balance) -> Balance {
-            return self.staked.get(&_addr).unwrap()
-                [TryInto::<usize>::try_into(_index).ok().unwrap()]
-            .amount;
-        }
+    /// Split of the principal claims of an account, see `set_split`.
+    #[derive(
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct PayoutSplit {
+        /// Beneficiaries and their shares in per mille, summing up to 1000.
+        pub shares: Vec<(AccountId, u32)>,
+        /// Time from which `shares` apply.
+        pub effective_at: Timestamp,
+        /// Shares applying until `effective_at`, empty without split.
+        pub previous: Vec<(AccountId, u32)>,
+    }
 
-        /// @dev     Method #3 (READ)
-        /// @param   addr: AccountId
-        /// @return  Total balance of _addr's ERC20 token.
-        #[ink(message)]
-        pub fn get_sig_status(&self) -> u128 {
-            self.sig_status
-        }
+    /// Rewards accrued before a reward token migration, claimable in the old
+    /// reward token until `claim_deadline`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct LegacyRewards {
+        /// Old reward token, the staked token if `None`.
+        pub token: Option<AccountId>,
+        /// Final value of the old accumulator.
+        pub reward_per_token: Balance,
+        /// Time of the migration.
+        pub migrated_at: Timestamp,
+        pub claim_deadline: Timestamp,
+        /// Legacy rewards not claimed yet.
+        pub owed: Balance,
+    }
 
-        /// @dev     Method #4 (WRITE)
-        /// @param   _amount: Balance
-        /// @note    TL;DR : "Inline comment will help you."
-        #[ink(message)]
-        pub fn claim(&mut self, _amount: Balance) {
-            let caller = self.env().caller();
-            let me = self.env().account_id();
-            if self.get_balance(caller) < _amount {
-                ink_env::debug_println!("{}", "Exceeds current unstakable");
-                return;
-            }
-            let mut unstakable: Balance;
-            let mut length = self.staked.get(&caller).unwrap().len();
-            let _claim_amount = _amount;
-            let mut amount = _amount.clone();
-            let mut i = 0;
+    /// Maximal number of fee discount tiers.
+    const MAX_DISCOUNT_TIERS: usize = 16;
 
-            // Looping through storage, sum up unstakable balance and update storage.
-            // Finally transfer ERC20 token to caller.
-            loop {
-                if !(i < length && amount > 0) {
-                    break;
-                }
-                unstakable = (self
-                    .get_unstakable(self.staked.get(&caller).unwrap()[i].timestamp)
-                    * self.staked.get(&caller).unwrap()[i].amount)
-                    / 10
-                    - self.unstaked.get(&caller).unwrap()[i];
-                if unstakable > amount {
-                    self.unstaked.get_mut(&caller).unwrap()[i] += amount;
-                    amount = 0;
-                } else {
-                    self.unstaked.get_mut(&caller).unwrap()[i] += unstakable;
-                    if self.staked.get(&caller).unwrap()[i].amount
-                        == self.unstaked.get(&caller).unwrap()[i]
-                    {
-                        length -= 1;
-                        self.staked.get_mut(&caller).unwrap().remove(i);
-                        self.unstaked.get_mut(&caller).unwrap().remove(i);
-                    } else {
-                        i += 1;
-                    }
-                    amount -= unstakable;
-                }
-            }
-            self.transfer_with_signature(me, caller, _claim_amount);
-        }
+    /// Upper bound of the tip paid to claim bots.
+    const MAX_TIP_BPS: u32 = 100;
 
-        /// @dev     Method #5 (WRITE)
-        /// @note    unstake all tokens.
-        ///          This method is similar to claim()
-        #[ink(message)]
-        pub fn claim_all(&mut self) {
-            let caller = self.env().caller();
-            let me = self.env().account_id();
-            let balance: Balance = self.get_balance(caller);
-            if balance <= 0 {
-                ink_env::debug_println!("{}", "No token to be staked");
-                return;
-            }
-            let mut i = 0;
-            let mut _length = self.staked.get(&caller).unwrap().len();
-            let mut unstakable: Balance;
-            loop {
-                if i >= _length {
-                    break;
-                }
-                unstakable = (self
-                    .get_unstakable(self.staked.get(&caller).unwrap()[i].timestamp)
-                    * self.staked.get(&caller).unwrap()[i].amount)
-                    / 10
-                    - self.unstaked.get(&caller).unwrap()[i];
-                self.unstaked.get_mut(&caller).unwrap()[i] += unstakable;
-                if self.staked.get(&caller).unwrap()[i].amount
-                    == self.unstaked.get(&caller).unwrap()[i]
-                {
-                    _length -= 1;
-                    self.staked.get_mut(&caller).unwrap().remove(i);
-                    self.unstaked.get_mut(&caller).unwrap().remove(i);
-                } else {
-                    i += 1;
-                }
-            }
-            self.transfer_with_signature(me, caller, balance);
-        }
+    /// Opt-in of an account to claims by anyone, see `claim_for_with_tip`.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct ClaimBot {
+        /// Unstakable balance from which anyone may claim.
+        pub threshold: Balance,
+        /// Tip paid to the claimer in basis points of the claimed amount.
+        pub tip_bps: u32,
+    }
 
-        // EIP-2612: Digital Signature Algorithm
-        // This makes Transfer with signature of owner.
-        fn transfer_with_signature(
-            &mut self,
-            from: AccountId,
-            to: AccountId,
+    /// Number of daily checkpoints kept in `history`.
+    const MAX_HISTORY: u32 = 365;
+
+    /// Length of a history checkpoint period (1 day in ms).
+    const HISTORY_PERIOD: Timestamp = 86400_000;
+
+    /// Daily checkpoint of the pool, taken at the first update of the day.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct HistoryEntry {
+        /// Day since the Unix epoch.
+        pub day: u32,
+        pub total_staked: Balance,
+        /// Rewards paid out up to then.
+        pub rewards_paid: Balance,
+    }
+
+    /// Number of admin actions kept in `admin_log`.
+    const MAX_ADMIN_LOG: u32 = 256;
+
+    /// Admin actions recorded in the audit trail.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum AdminAction {
+        SetRounding,
+        SetTreasury,
+        TransferOwnership,
+        AcceptOwnership,
+        SetLimits,
+        ProposeToken,
+        SetToken,
+        SetPaused,
+        SetRewardsDuration,
+        FundRewards,
+        EnterEmergency,
+        SetMaxShare,
+        SetWithdrawalFee,
+        GrantRole,
+        RevokeRole,
+        SetRewardRateBounds,
+        SetRateModel,
+        AddSchedule,
+        Deprecate,
+        SetPredecessor,
+        SetNativeRewards,
+        FundNativeRewards,
+        SetPriceOracle,
+        SetStrategy,
+        InvestSurplus,
+        Divest,
+        BuybackAndBurn,
+        Pause,
+        RotateRole,
+        SetDiscountTiers,
+        MigrateRewardToken,
+        SetEoaOnly,
+        SetIntegrator,
+        SetRouter,
+        SetTierMultiplier,
+        SetBackupOwner,
+        AssumeOwnership,
+        SetRewardExcluded,
+        FundRentPot,
+        WithdrawRentPot,
+        SetRentSponsorship,
+        SetRejectEvents,
+        Heartbeat,
+        SetDonationFund,
+        SetMaxStakers,
+        RegisterPool,
+        UnregisterPool,
+        SetHub,
+        SetGlobalPaused,
+        DistributeGauges,
+    }
+
+    /// Entry of the admin audit trail.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct AdminLogEntry {
+        /// Sequence number of the action.
+        pub id: u32,
+        pub action: AdminAction,
+        pub actor: AccountId,
+        /// Blake2x256 hash of the SCALE encoded parameters.
+        pub params_hash: Hash,
+        pub block: BlockNumber,
+    }
+
+    /// Minimal challenge delay of an account recovery (3 days in ms).
+    const MIN_RECOVERY_DELAY: Timestamp = 3 * 86400_000;
+
+    /// Minimal owner inactivity before the backup owner can take over
+    /// (30 days in ms).
+    const MIN_FAILOVER_INTERVAL: Timestamp = 30 * 86400_000;
+
+    /// Guardian allowed to move an account's entries after a challenge delay.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Recovery {
+        /// Guardian account or social recovery contract.
+        guardian: AccountId,
+        /// Challenge delay in ms during which the account can cancel a recovery.
+        delay: Timestamp,
+        /// Initiated recovery: new account and the time it becomes executable.
+        pending: Option<(AccountId, Timestamp)>,
+    }
+
+    /// Upper bound of the boost bonus in per mille.
+    const MAX_BOOST_PERMILLE: u32 = 500;
+
+    /// Time a boost stays locked after the last top-up (7 days in ms).
+    const BOOST_COOLDOWN: Timestamp = 7 * 86400_000;
+
+    /// Rewards locked into the boost vault.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Boost {
+        amount: Balance,
+        locked_at: Timestamp,
+    }
+
+    /// Upper bound of `withdrawal_fee_bps`.
+    const MAX_FEE_BPS: u32 = 1000;
+
+    /// Maximal number of stake entries a message touches, keeping every
+    /// message within the block limits.
+    const MAX_ITERATIONS: usize = 64;
+
+    /// Maximal number of open stakes per account, so that every message
+    /// iterating over them stays within `MAX_ITERATIONS`.
+    const MAX_STAKES: usize = MAX_ITERATIONS;
+
+    // Loops over the stakes of an account rely on the capacity for their bound.
+    const _: () = assert!(MAX_STAKES <= MAX_ITERATIONS);
+
+    /// Open stakes of an account, stored compactly.
+    type Stakes = CompactStakes<MAX_STAKES>;
+
+    /// Maximal number of steps of a schedule, e.g. hourly steps over a month.
+    const MAX_SCHEDULE_STEPS: usize = 744;
+
+    /// Maximal number of steps returned by `unlock_timeline`.
+    const MAX_TIMELINE_STEPS: usize = 64;
+
+    /// Unlock schedule of a stake.
+    #[derive(
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Schedule {
+        /// Length of a step in ms.
+        pub step: Timestamp,
+        /// Parts of `UNLOCK_PRECISION` unlocked after each number of full steps,
+        /// the last entry applying from then on.
+        pub unlocked: Vec<u32>,
+    }
+
+    /// Maximal amount of whole tokens handed out by one `faucet` call.
+    #[cfg(feature = "demo")]
+    const DEMO_FAUCET_LIMIT: Balance = 1000;
+
+    impl Schedule {
+        /// Schedule active at deployment: half unlocks after the first step, then
+        /// each step within 5 steps unlocks 10% more than the step before.
+        fn initial() -> Self {
+            Schedule {
+                step: INITIAL_STEP,
+                unlocked: INITIAL_UNLOCKED_PERMILLE
+                    .iter()
+                    .map(|&permille| math::from_permille(permille))
+                    .collect(),
+            }
+        }
+
+        /// Whether the schedule never decreases and fully unlocks in the end.
+        fn is_valid(&self) -> bool {
+            self.step > 0
+                && self.unlocked.len() <= MAX_SCHEDULE_STEPS + 1
+                && self.unlocked.last() == Some(&UNLOCK_PRECISION)
+                && self.unlocked.windows(2).all(|pair| pair[0] <= pair[1])
+        }
+
+        /// Age in ms from which a stake is fully unstakable.
+        pub fn maturity(&self) -> Balance {
+            Balance::from(self.step) * self.unlocked.len().saturating_sub(1) as Balance
+        }
+
+        /// Returns the parts of `UNLOCK_PRECISION` unlocked at `age` ms.
+        fn unlocked_at(&self, age: Balance) -> Balance {
+            math::unlocked_fraction(self.step, &self.unlocked, age)
+        }
+    }
+
+    /// Worst-case cost of a message as reported by `weights`.
+    ///
+    /// Counts exclude the contract root, which every message reads and every
+    /// writing message writes once.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct MessageWeight {
+        /// Selector of the message.
+        pub selector: [u8; 4],
+        /// Maximal number of stake entries iterated.
+        pub max_entries: u32,
+        /// Maximal number of mapping entries read.
+        pub storage_reads: u32,
+        /// Maximal number of mapping entries written.
+        pub storage_writes: u32,
+        /// Maximal number of calls to other contracts.
+        pub calls: u32,
+    }
+
+    /// Mapping entries read and written when settling the rewards of an account:
+    /// reward account, boost, legacy rewards, checkpoints, refund vote, daily
+    /// history and reward exclusion (read only).
+    const SETTLE_READS: u32 = 7;
+    const SETTLE_WRITES: u32 = 6;
+
+    /// Calls to the token per transfer made by this contract, see
+    /// `transfer_with_signature`: nonce and transfer.
+    const TRANSFER_CALLS: u32 = 2;
+
+    /// Mapping entries deleted by `close_account`.
+    const CLOSED_ENTRIES: u32 = 8;
+
+    /// Stake as returned by `stakes_flat`: (id, amount, released, start in unix ms,
+    /// multiplier in per mille, schedule version).
+    pub type FlatStake = (u64, u128, u128, u64, u32, u32);
+
+    /// Position as returned by `account_summary_flat`: (staked, unstakable,
+    /// pending rewards, escrow, boost, number of stakes).
+    pub type FlatSummary = (u128, u128, u128, u128, u128, u32);
+
+    /// Obligations as returned by `obligations_flat`: (principal, rewards accrued,
+    /// escrow, fees, token balance, invested, shortfall, time in unix ms).
+    pub type FlatObligations = (u128, u128, u128, u128, u128, u128, u128, u64);
+
+    /// Outcome of a claim as previewed by `preview_claim`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ClaimPreview {
+        /// Released principal.
+        pub amount: Balance,
+        /// Withdrawal fee withheld from `amount`.
+        pub fee: Balance,
+        /// Amount transferred to the claimer.
+        pub net: Balance,
+    }
+
+    /// Staked principal of an account from `block` on.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Checkpoint {
+        block: BlockNumberOf,
+        balance: TokenAmount,
+        /// Sum of `balance * blocks` over all blocks before `block`.
+        cumulative: TokenBlocks,
+    }
+
+    /// Reward accounting of a single account.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RewardAccount {
+        /// Staked principal.
+        staked: Balance,
+        /// Principal scaled by the multipliers of its stakes.
+        weight: Balance,
+        /// `weight * reward_per_token_stored` already accounted for.
+        reward_debt: Balance,
+        /// Rewards settled but not claimed yet.
+        pending: Balance,
+        /// Block of the last interaction.
+        last_update: BlockNumber,
+        /// Reward token migrations accounted for.
+        reward_epoch: u32,
+    }
+
+    /// Utilization-driven scaling of the reward rate.
+    ///
+    /// The rate is scaled by `target_tvl / total_staked`, clamped to
+    /// [`min_permille`, `max_permille`], so that incentives increase below the
+    /// target and taper above it. Disabled while `target_tvl` is zero.
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RateModel {
+        pub target_tvl: Balance,
+        pub min_permille: u32,
+        pub max_permille: u32,
+    }
+
+    /// Raw reward accounting of an account, returned by `audit_rewards`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RewardAudit {
+        /// Accumulator as of `last_update_time`, scaled by `ACC_PRECISION`.
+        pub reward_per_token_stored: Balance,
+        /// Accumulator including the rewards emitted since `last_update_time`.
+        pub reward_per_token: Balance,
+        /// Time of the last accumulator update.
+        pub last_update_time: Timestamp,
+        /// Sum of the reward weights of all accounts.
+        pub total_weight: Balance,
+        /// Reward weight of the account.
+        pub weight: Balance,
+        /// `weight * reward_per_token_stored` already accounted for.
+        pub reward_debt: Balance,
+        /// Rewards settled at the last interaction.
+        pub settled: Balance,
+        /// Rewards the account could claim now, as returned by `pending_rewards`.
+        pub pending: Balance,
+        /// Block of the last interaction.
+        pub last_update: BlockNumber,
+    }
+
+    /// Rarely changing parameters, kept behind a `Lazy` so that the common
+    /// messages don't load them.
+    #[derive(
+        Clone,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        PackedLayout,
+        SpreadLayout,
+        scale::Encode,
+        scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Settings {
+        /// Receiver of the token surplus (rounding dust, stray transfers).
+        pub treasury: AccountId,
+        /// Withdrawal fee in basis points charged on a stake claimed right away,
+        /// decaying linearly to zero at maturity.
+        pub withdrawal_fee_bps: u32,
+        /// Ascending staked principal thresholds of the fee discount tiers.
+        pub discount_tiers: Vec<Balance>,
+        /// Maximal share of `total_staked` per account in per mille, `0` if unlimited.
+        pub max_share_permille: u32,
+        /// Lower bound of `reward_rate` of a new reward period.
+        pub min_reward_rate: Balance,
+        /// Upper bound of `reward_rate` of a new reward period, `0` if unbounded.
+        pub max_reward_rate: Balance,
+        /// Token balance below which new stakers get their storage deposit sponsored.
+        pub rent_max_balance: Balance,
+        /// Native currency sent to each sponsored staker, `0` if disabled.
+        pub rent_amount: Balance,
+        /// Swap router claims can be zapped out to the native currency through, and
+        /// the staked token bought back with.
+        pub router: Option<AccountId>,
+        /// Whether rejected stakes and claims emit `OperationRejected`.
+        pub reject_events: bool,
+        /// Fund receiving the round-up donations, `None` if disabled.
+        pub donation_fund: Option<AccountId>,
+        /// Pool registry allowed to claim for the stakers with `claim_all_for`
+        /// and to pause this pool with `apply_global_pause`.
+        pub hub: Option<AccountId>,
+    }
+
+    /// All contract parameters, returned by `get_config`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Config {
+        pub token: AccountId,
+        pub token_decimals: u8,
+        pub owner: AccountId,
+        pub treasury: AccountId,
+        /// Age in ms from which a new stake is fully unstakable.
+        pub maturity: Balance,
+        pub rounding: Rounding,
+        pub withdrawal_fee_bps: u32,
+        pub min_stake: Balance,
+        pub max_stake: Balance,
+        pub dust_threshold: Balance,
+        pub max_share_permille: u32,
+        pub bonus_end_block: BlockNumber,
+        pub bonus_multiplier: u32,
+        pub rewards_duration: Timestamp,
+        pub rate_model: RateModel,
+        /// Pool replacing this one, `None` unless deprecated.
+        pub successor: Option<AccountId>,
+        /// Whether rewards are paid in the native currency.
+        pub native_rewards: bool,
+        /// Whether only the allowlisted integrator contracts may call the entry points.
+        pub eoa_only: bool,
+        /// Swap router used by `zap_out_native` and `buyback_and_burn`.
+        pub router: Option<AccountId>,
+        pub paused: bool,
+        /// Whether the hub paused all the pools of its registry.
+        pub global_paused: bool,
+        pub emergency_since: Option<Timestamp>,
+        /// Whether stakers voted for the emergency refund.
+        pub refund_open: bool,
+    }
+
+    /// Raw per-account entries returned by `dump_state`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct AccountDump {
+        pub account: AccountId,
+        pub staked: Vec<Stake>,
+        pub rewards: RewardAccount,
+        pub escrow: Balance,
+        pub boost: Balance,
+    }
+
+    /// Snapshot of the contract state for monitoring bots.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct HealthReport {
+        /// Whether staking and claiming are suspended.
+        pub paused: bool,
+        /// Token balance held by the contract.
+        pub token_balance: Balance,
+        /// Staked principal and escrow owed to the accounts.
+        pub obligations: Balance,
+        /// `token_balance` per mille of `obligations`, `None` without obligations.
+        pub solvency_permille: Option<Balance>,
+        /// Number of proposed admin actions waiting for execution: token change and
+        /// ownership transfer.
+        pub pending_admin_actions: u32,
+        /// End of the current reward period.
+        pub reward_period_finish: Timestamp,
+        /// Time of the last reward accumulator update.
+        pub last_reward_update: Timestamp,
+        /// Whether the holdings of each currency equal the books owed in it.
+        pub ledger_balanced: bool,
+    }
+
+    /// Proof-of-reserve style comparison of the amounts owed with the token
+    /// balances, returned by `obligations_report`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ObligationsReport {
+        /// Staked principal owed to the stakers.
+        pub principal: Balance,
+        /// Emitted rewards not paid out yet, including locked boosts.
+        pub rewards_accrued: Balance,
+        /// Whether rewards are owed in the native currency instead of the token.
+        pub native_rewards: bool,
+        /// Payouts held back after failed transfers.
+        pub escrow: Balance,
+        /// Withdrawal fees owed to the treasury.
+        pub fees: Balance,
+        /// Token balance held by the contract.
+        pub token_balance: Balance,
+        /// Tokens invested into the yield strategy.
+        pub invested: Balance,
+        /// Amount by which the token balance falls short of the token obligations.
+        pub shortfall: Balance,
+        /// Time of the report.
+        pub timestamp: Timestamp,
+    }
+
+    /// Minimal time between two published obligations reports (1 hour in ms).
+    const REPORT_INTERVAL: Timestamp = 3600_000;
+
+    /// Decimals assumed for tokens not exposing `decimals()`.
+    const DEFAULT_DECIMALS: u8 = 0;
+
+    /// Delay between proposing and executing an admin action (2 days in ms).
+    const ADMIN_TIMELOCK: Timestamp = 2 * 86400_000;
+
+    /// Client-supplied identifier making a claim idempotent.
+    pub type ClaimId = u128;
+
+    /// Selectors of `stake`, `claim` and `claim_all`, those of `stake_v2`,
+    /// `claim_v2` and `claim_all_v2`. The selectors of their own names stay with
+    /// the legacy messages taking the original arguments.
+    const STAKE_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("stake_v2");
+    const CLAIM_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("claim_v2");
+    const CLAIM_ALL_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("claim_all_v2");
+
+    /// Identifier of a pool listed in the registry.
+    pub type PoolId = u32;
+
+    /// Maximal number of pools ever listed in the registry, bounding the loop of
+    /// `claim_all_pools`.
+    const MAX_POOLS: PoolId = 16;
+
+    /// Length of a gauge voting epoch in blocks, a week of 6 s blocks.
+    const GAUGE_EPOCH: BlockNumber = 100_800;
+
+    /// Event emitted when a failed payout is credited to the escrow.
+    #[ink(event)]
+    pub struct EscrowCredited {
+        /// Account the payout was owed to.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount credited to the escrow.
+        amount: Balance,
+    }
+
+    /// Event emitted when the owner proposes to replace the token.
+    #[ink(event)]
+    pub struct TokenProposed {
+        /// Proposed token.
+        #[ink(topic)]
+        token: AccountId,
+        /// Time the replacement becomes executable.
+        eta: Timestamp,
+    }
+
+    /// Event emitted when an account proposes a new payout address.
+    #[ink(event)]
+    pub struct PayoutAddressProposed {
+        /// Account whose claims are redirected.
+        #[ink(topic)]
+        account: AccountId,
+        /// Proposed payout address.
+        payout_address: AccountId,
+        /// Time the payout address can be set.
+        eta: Timestamp,
+    }
+
+    /// Event emitted when an account's payout address has been changed.
+    #[ink(event)]
+    pub struct PayoutAddressChanged {
+        /// Account whose claims are redirected.
+        #[ink(topic)]
+        account: AccountId,
+        /// Address receiving the claims from now on.
+        payout_address: AccountId,
+    }
+
+    /// Event emitted when an account authorizes or revokes a merchant's pulls.
+    #[ink(event)]
+    pub struct PullAuthorized {
+        /// Account paying the merchant.
+        #[ink(topic)]
+        account: AccountId,
+        /// Merchant allowed to pull.
+        #[ink(topic)]
+        merchant: AccountId,
+        /// Amount per period, `0` once revoked.
+        amount: Balance,
+        /// Length of a period in ms.
+        period: Timestamp,
+    }
+
+    /// Event emitted when a merchant pulled from an account's matured balance.
+    #[ink(event)]
+    pub struct Pulled {
+        /// Paying account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Merchant receiving the amount.
+        #[ink(topic)]
+        merchant: AccountId,
+        /// Amount pulled.
+        amount: Balance,
+    }
+
+    /// Event emitted when the remainder of a rounded down claim is donated.
+    #[ink(event)]
+    pub struct Donated {
+        /// Donating account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Fund receiving the donation.
+        fund: AccountId,
+        /// Donated amount.
+        amount: Balance,
+    }
+
+    /// Event emitted when an account changed the split of its claims.
+    #[ink(event)]
+    pub struct SplitChanged {
+        /// Account whose claims are split.
+        #[ink(topic)]
+        account: AccountId,
+        /// Beneficiaries and their shares in per mille, empty to remove the split.
+        shares: Vec<(AccountId, u32)>,
+        /// Time from which the split applies.
+        effective_at: Timestamp,
+    }
+
+    /// Event emitted when the token has been replaced.
+    #[ink(event)]
+    pub struct TokenChanged {
+        /// Replaced token.
+        #[ink(topic)]
+        old_token: AccountId,
+        /// Token staked from now on.
+        #[ink(topic)]
+        new_token: AccountId,
+    }
+
+    /// Event emitted when the ownership has been transferred.
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        /// Former owner.
+        #[ink(topic)]
+        previous_owner: AccountId,
+        /// Owner from now on.
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    /// Event emitted when the owner pauses or unpauses the contract.
+    #[ink(event)]
+    pub struct PausedChanged {
+        /// Whether staking and claiming are suspended now.
+        paused: bool,
+    }
+
+    /// Event emitted when the hub pauses or resumes all the pools of its registry.
+    #[ink(event)]
+    pub struct GlobalPauseChanged {
+        /// Whether staking and claiming are suspended in every listed pool now.
+        paused: bool,
+    }
+
+    /// Event emitted when a staker votes for a listed pool.
+    #[ink(event)]
+    pub struct GaugeVoted {
+        #[ink(topic)]
+        voter: AccountId,
+        /// Pool voted for.
+        pool_id: PoolId,
+        /// Epoch the vote counts in.
+        epoch: u32,
+        /// Voting power given to the pool.
+        weight: Balance,
+    }
+
+    /// Event emitted when the emission of an epoch is split across the pools.
+    #[ink(event)]
+    pub struct GaugesDistributed {
+        /// Distributed epoch.
+        epoch: u32,
+        /// Emission paid to the pools, net of the rounding remainder.
+        amount: Balance,
+    }
+
+    /// Event emitted when rewards are added to the reward pool.
+    #[ink(event)]
+    pub struct RewardsFunded {
+        /// Account that added the rewards.
+        #[ink(topic)]
+        funder: AccountId,
+        /// Amount added to the reward pool.
+        amount: Balance,
+    }
+
+    /// Event emitted when an account claims its rewards.
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        /// Account whose rewards were paid out.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount paid out.
+        amount: Balance,
+    }
+
+    /// Event emitted when an account locks rewards into the boost vault.
+    #[ink(event)]
+    pub struct BoostLocked {
+        /// Account locking its rewards.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount locked.
+        amount: Balance,
+    }
+
+    /// Event emitted when an account unlocks its boost.
+    #[ink(event)]
+    pub struct BoostUnlocked {
+        /// Account unlocking its boost.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount credited back to the pending rewards.
+        amount: Balance,
+    }
+
+    /// Event emitted by `publish_report`.
+    #[ink(event)]
+    pub struct ObligationsReported {
+        /// Published report.
+        report: ObligationsReport,
+    }
+
+    /// Event emitted when the owner migrates the reward token.
+    #[ink(event)]
+    pub struct RewardTokenMigrated {
+        /// Old reward token, the staked token if `None`.
+        old_token: Option<AccountId>,
+        /// New reward token, the staked token if `None`.
+        new_token: Option<AccountId>,
+        /// End of the grace period to claim the legacy rewards.
+        claim_deadline: Timestamp,
+    }
+
+    /// Event emitted when the staked token has been bought back to be burned.
+    #[ink(event)]
+    pub struct BoughtBack {
+        /// Native currency swapped through the router.
+        native_in: Balance,
+        /// Amount of the staked token bought.
+        bought: Balance,
+    }
+
+    /// Event emitted when collected fees are burned.
+    #[ink(event)]
+    pub struct FeesBurned {
+        /// Amount of collected fees and bought back tokens burned.
+        amount: Balance,
+        /// Total supply of the staked token after the burn.
+        total_supply: Balance,
+    }
+
+    /// Event emitted when the owner grants or revokes a role.
+    #[ink(event)]
+    pub struct RoleChanged {
+        /// Account whose role changed.
+        #[ink(topic)]
+        account: AccountId,
+        /// Granted or revoked role.
+        role: Role,
+        /// Whether the role was granted or revoked.
+        granted: bool,
+    }
+
+    /// Event emitted when a reward injector adds rewards.
+    #[ink(event)]
+    pub struct RewardNotified {
+        /// Reward injector that added the rewards.
+        #[ink(topic)]
+        injector: AccountId,
+        /// Amount added to the reward pool.
+        amount: Balance,
+        /// Rewards emitted per second in the new period.
+        reward_rate: Balance,
+        /// End of the new period.
+        period_finish: Timestamp,
+    }
+
+    /// Event emitted when the funded rewards ran out before the end of the
+    /// reward period and the accrual was suspended.
+    #[ink(event)]
+    pub struct RewardsExhausted {
+        /// Rewards accrued since the last update.
+        accrued: Balance,
+        /// Funded rewards left to cover them.
+        available: Balance,
+    }
+
+    /// Event emitted when the owner deprecates the pool.
+    #[ink(event)]
+    pub struct Deprecated {
+        /// Pool replacing this one.
+        #[ink(topic)]
+        successor: AccountId,
+    }
+
+    /// Event emitted when a position moved into the successor pool.
+    #[ink(event)]
+    pub struct PositionMigrated {
+        /// Owner of the position.
+        #[ink(topic)]
+        account: AccountId,
+        /// Identifier of the position in this pool.
+        stake_id: StakeId,
+        /// Principal transferred to the successor.
+        amount: Balance,
+        /// Identifier of the position in the successor pool.
+        successor_stake_id: StakeId,
+    }
+
+    /// Event emitted when the reward rate was recalculated from the oracle price.
+    #[ink(event)]
+    pub struct RewardRateRecalculated {
+        /// Quoted price of a whole reward token.
+        price: Balance,
+        /// Rewards emitted per second from now on.
+        reward_rate: Balance,
+        /// End of the reward period at this rate.
+        period_finish: Timestamp,
+    }
+
+    /// Event emitted when the owner enters the emergency mode.
+    #[ink(event)]
+    pub struct EmergencyEntered {
+        /// Time schedules and emission are frozen at.
+        frozen_at: Timestamp,
+    }
+
+    /// Event emitted when the stakers' votes opened the emergency refund.
+    #[ink(event)]
+    pub struct RefundOpened {
+        /// Principal of the accounts that voted.
+        votes: Balance,
+        /// Staked principal when the quorum was reached.
+        total_staked: Balance,
+    }
+
+    /// Event emitted when a guardian initiates the recovery of an account.
+    #[ink(event)]
+    pub struct RecoveryInitiated {
+        /// Account being recovered.
+        #[ink(topic)]
+        account: AccountId,
+        /// Account receiving the entries.
+        #[ink(topic)]
+        new_account: AccountId,
+        /// Time the recovery can be executed.
+        eta: Timestamp,
+    }
+
+    /// Event emitted when an account's entries have been moved to a new account.
+    #[ink(event)]
+    pub struct AccountRecovered {
+        /// Recovered account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Account now holding the entries.
+        #[ink(topic)]
+        new_account: AccountId,
+    }
+
+    /// Event emitted when a stake is gifted.
+    #[ink(event)]
+    pub struct GiftCreated {
+        /// Identifier of the gift.
+        gift_id: GiftId,
+        /// Account that funded the gift.
+        #[ink(topic)]
+        funder: AccountId,
+        /// Account that can accept the gift.
+        #[ink(topic)]
+        recipient: AccountId,
+        /// Gifted amount.
+        amount: Balance,
+        /// End of the acceptance window.
+        expires_at: Timestamp,
+    }
+
+    /// Event emitted when a gift has been accepted and turned into a stake.
+    #[ink(event)]
+    pub struct GiftAccepted {
+        /// Identifier of the gift.
+        gift_id: GiftId,
+        /// Identifier of the created stake.
+        stake_id: StakeId,
+    }
+
+    /// Event emitted when an expired gift has been cancelled by its funder.
+    #[ink(event)]
+    pub struct GiftCancelled {
+        /// Identifier of the gift.
+        gift_id: GiftId,
+    }
+
+    /// Event emitted when a staker lists released principal for sale.
+    #[ink(event)]
+    pub struct OtcListed {
+        /// Identifier of the offer.
+        offer_id: OfferId,
+        /// Account selling the principal.
+        #[ink(topic)]
+        seller: AccountId,
+        /// Only account allowed to buy.
+        #[ink(topic)]
+        buyer: AccountId,
+        /// Escrowed amount of the staked token.
+        amount: Balance,
+        /// ERC20 token the price is paid in.
+        price_token: AccountId,
+        /// Agreed price.
+        price: Balance,
+    }
+
+    /// Event emitted when an OTC offer has been settled.
+    #[ink(event)]
+    pub struct OtcSettled {
+        /// Identifier of the offer.
+        offer_id: OfferId,
+    }
+
+    /// Event emitted when the seller withdrew an OTC offer.
+    #[ink(event)]
+    pub struct OtcCancelled {
+        /// Identifier of the offer.
+        offer_id: OfferId,
+    }
+
+    /// Event emitted when a claim has been swapped to the native currency.
+    #[ink(event)]
+    pub struct ZappedOut {
+        /// Claiming account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Tokens swapped, net of the withdrawal fee.
+        amount: Balance,
+        /// Native currency paid out.
+        native_out: Balance,
+    }
+
+    /// Event emitted when principal has been claimed.
+    #[ink(event)]
+    pub struct Claimed {
+        /// Account whose principal has been claimed.
+        #[ink(topic)]
+        account: AccountId,
+        /// Receipt of the claim.
+        #[ink(topic)]
+        receipt_id: ReceiptId,
+        /// Amount paid out, net of the withdrawal fee.
+        amount: Balance,
+        /// Withdrawal fee withheld.
+        fee: Balance,
+    }
+
+    /// Event emitted when an account deleted its entries after a full exit.
+    #[ink(event)]
+    pub struct AccountClosed {
+        /// Closed account.
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// Event emitted when the storage deposit of a new staker has been sponsored.
+    #[ink(event)]
+    pub struct RentSponsored {
+        /// Sponsored staker.
+        #[ink(topic)]
+        account: AccountId,
+        /// Native currency sent to the staker.
+        amount: Balance,
+    }
+
+    /// Event emitted when a stake or claim is rejected, if enabled by the owner.
+    /// Best-effort telemetry, e.g. for spikes of allowance or UI problems.
+    #[ink(event)]
+    pub struct OperationRejected {
+        /// Selector of the rejected message.
+        #[ink(topic)]
+        selector: [u8; 4],
+        /// Error returned to the caller.
+        reason: Error,
+    }
+
+    /// Event emitted when a stake moved to a longer lock tier.
+    #[ink(event)]
+    pub struct TierChanged {
+        /// Owner of the stake.
+        #[ink(topic)]
+        account: AccountId,
+        /// Identifier of the stake.
+        stake_id: StakeId,
+        /// Schedule version of the new tier.
+        tier: ScheduleVersion,
+        /// Reward multiplier of the stake from now on, in per mille.
+        multiplier: u32,
+    }
+
+    /// Event emitted when tokens are deposited for a later `confirm_stake`.
+    #[ink(event)]
+    pub struct Deposited {
+        /// Depositing account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount deposited.
+        amount: Balance,
+    }
+
+    /// Event emitted when a pending deposit is refunded.
+    #[ink(event)]
+    pub struct DepositRefunded {
+        /// Refunded account.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount refunded.
+        amount: Balance,
+    }
+
+    /// Event emitted when an escrowed balance is withdrawn.
+    #[ink(event)]
+    pub struct EscrowWithdrawn {
+        /// Account withdrawing its escrow.
+        #[ink(topic)]
+        account: AccountId,
+        /// Amount withdrawn.
+        amount: Balance,
+    }
+
+    /// Upper bound of the early-bird multiplier (10x).
+    const MAX_MULTIPLIER: u32 = 10 * MULTIPLIER_BASE;
+
+    impl Staking {
+        /// @dev    Default Initialization.
+        /// @param  address of pre-deployed ERC20 contract.
+        ///         this is available only after the deployment of ERC20 contract.
+        /// @param  _bonus_end_block: stakes created before this block are early birds.
+        /// @param  _bonus_multiplier: early-bird reward multiplier in per mille.
+        /// @param  _withdrawal_fee_bps: withdrawal fee of a fresh stake in basis points.
+        /// @param  _rewards_duration: length of a reward period in ms.
+        /// @note   Initialize the contract with pre-deployed erc20 instance address.
+        ///         Instantiation reverts on nonsensical parameters.
+
+        #[ink(constructor)]
+        pub fn new(
+            _erc20_account_id: AccountId,
+            _bonus_end_block: BlockNumber,
+            _bonus_multiplier: u32,
+            _withdrawal_fee_bps: u32,
+            _rewards_duration: Timestamp,
+        ) -> Self {
+            //
+            // let address : AccountId = AccountId::decode(&mut ref_account32).unwrap_or_default();
+            if let Err(_error) = Self::validate_params(
+                _bonus_end_block,
+                _bonus_multiplier,
+                _withdrawal_fee_bps,
+                _rewards_duration,
+                Self::env().block_number(),
+            ) {
+                // The rejected parameter is only printed by debug builds, a static
+                // message keeps `core::fmt` out of production Wasm blobs.
+                debug_println!("invalid constructor parameters: {:?}", _error);
+                panic!("invalid constructor parameters")
+            }
+            assert!(
+                Self::is_erc20(_erc20_account_id),
+                "token account is not a callable ERC20 contract"
+            );
+            let erc20_instance = Erc20Ref::from_account_id(_erc20_account_id);
+            let caller = Self::env().caller();
+            let decimals = Self::read_decimals(_erc20_account_id);
+            let mut schedules = StorageHashMap::new();
+            schedules.insert(0, Schedule::initial());
+            Self {
+                staked: StorageHashMap::new(),
+                token: erc20_instance,
+                sig_status: 0, ////////////////////////////
+                escrow: StorageHashMap::new(),
+                claim_ids: StorageHashMap::new(),
+                owner: caller,
+                pending_owner: None,
+                rounding: Rounding::default(),
+                ledger: Ledger::default(),
+                decimals,
+                min_stake: 0,
+                max_stake: 0,
+                max_stakers: 0,
+                stakers: StorageVec::new(),
+                staker_index: StorageHashMap::new(),
+                dust_threshold: 0,
+                pending_token: None,
+                paused: false,
+                reward_rate: 0,
+                rewards_duration: _rewards_duration,
+                period_finish: 0,
+                last_update_time: 0,
+                reward_per_token_stored: 0,
+                rate_model: Default::default(),
+                reward_accounts: StorageHashMap::new(),
+                checkpoints: StorageHashMap::new(),
+                total_weight: 0,
+                bonus_end_block: BlockNumberOf(_bonus_end_block),
+                bonus_multiplier: _bonus_multiplier,
+                boosts: StorageHashMap::new(),
+                roles: StorageHashMap::new(),
+                emergency_since: None,
+                recoveries: StorageHashMap::new(),
+                admin_log: StorageHashMap::new(),
+                admin_log_len: 0,
+                schedules,
+                schedule_count: 1,
+                time_scale: 1,
+                next_stake_id: 0,
+                successor: None,
+                predecessor: None,
+                native_rewards: false,
+                price_oracle: None,
+                target_value: 0,
+                retarget_epoch: 0,
+                last_retarget: 0,
+                history: StorageHashMap::new(),
+                history_len: 0,
+                rewards_paid: 0,
+                claim_bots: StorageHashMap::new(),
+                strategy: None,
+                invested: 0,
+                last_report: 0,
+                reward_token: None,
+                reward_epoch: 0,
+                legacy_rewards: None,
+                legacy_owed: StorageHashMap::new(),
+                payout_addresses: StorageHashMap::new(),
+                payout_splits: StorageHashMap::new(),
+                pull_authorizations: StorageHashMap::new(),
+                round_ups: StorageHashMap::new(),
+                donations: StorageHashMap::new(),
+                total_donated: 0,
+                pending_payouts: StorageHashMap::new(),
+                gifts: StorageHashMap::new(),
+                next_gift_id: 0,
+                otc_offers: StorageHashMap::new(),
+                next_offer_id: 0,
+                eoa_only: false,
+                integrators: StorageHashMap::new(),
+                tier_multipliers: StorageHashMap::new(),
+                refund_round: 0,
+                refund_round_start: 0,
+                refund_votes_total: 0,
+                refund_votes: StorageHashMap::new(),
+                refund_open: false,
+                backup_owner: None,
+                failover_interval: 0,
+                last_owner_action: Self::env().block_timestamp(),
+                last_admin_activity: Self::env().block_number(),
+                last_user_activity: 0,
+                paused_since: 0,
+                reward_excluded: StorageHashMap::new(),
+                rent_sponsored: StorageHashMap::new(),
+                receipts: StorageHashMap::new(),
+                next_receipt_id: 0,
+                settings: Lazy::new(Settings {
+                    treasury: caller,
+                    withdrawal_fee_bps: _withdrawal_fee_bps,
+                    discount_tiers: Vec::new(),
+                    max_share_permille: 0,
+                    min_reward_rate: 0,
+                    max_reward_rate: 0,
+                    rent_max_balance: 0,
+                    rent_amount: 0,
+                    router: None,
+                    reject_events: false,
+                    donation_fund: None,
+                    hub: None,
+                }),
+                pending_deposits: StorageHashMap::new(),
+                entry_counts: StorageHashMap::new(),
+                pools: StorageHashMap::new(),
+                next_pool_id: 0,
+                global_paused: false,
+                gauge_votes: StorageHashMap::new(),
+                gauge_tallies: StorageHashMap::new(),
+                gauge_totals: StorageHashMap::new(),
+            }
+        }
+
+        /// @dev    Testnet initialization with accelerated schedules.
+        /// @param  _time_scale: divisor of all schedule durations, e.g. 1440 to turn
+        ///         days into minutes.
+        /// @param  _production: whether the target chain is a production chain.
+        /// @note   Same as `new` otherwise. Reverts on production chains unless
+        ///         _time_scale is 1, the scale can't be changed afterwards.
+        #[ink(constructor)]
+        pub fn new_scaled(
+            _erc20_account_id: AccountId,
+            _bonus_end_block: BlockNumber,
+            _bonus_multiplier: u32,
+            _withdrawal_fee_bps: u32,
+            _rewards_duration: Timestamp,
+            _time_scale: u32,
+            _production: bool,
+        ) -> Self {
+            assert!(_time_scale > 0, "time scale must be positive");
+            assert!(
+                !_production || _time_scale == 1,
+                "time scale refused on a production chain"
+            );
+            let mut contract = Self::new(
+                _erc20_account_id,
+                _bonus_end_block,
+                _bonus_multiplier,
+                _withdrawal_fee_bps,
+                _rewards_duration,
+            );
+            contract.time_scale = _time_scale;
+            contract
+        }
+
+        /// @dev    Demo pool initialization (feature `demo`).
+        /// @param  _token_code_hash: code hash of the uploaded ERC20 contract.
+        /// @param  _rewards_duration: length of a reward period in ms.
+        /// @note   Instantiates a fresh ERC20 token as the staked token, minted by
+        ///         anyone through `faucet`, without early-bird bonus or withdrawal fee.
+        #[cfg(feature = "demo")]
+        #[ink(constructor)]
+        pub fn new_demo(_token_code_hash: Hash, _rewards_duration: Timestamp) -> Self {
+            let token = Erc20Ref::new(0)
+                .endowment(0)
+                .code_hash(_token_code_hash)
+                .salt_bytes(Self::env().account_id().encode())
+                .instantiate()
+                .unwrap_or_else(|_| panic!("failed to instantiate the faucet token"));
+            Self::new(token.to_account_id(), 0, MULTIPLIER_BASE, 0, _rewards_duration)
+        }
+
+        /// @dev     Method #1 (WRITE)
+        /// @param   _amount:Balance
+        /// @param   _deadline: nothing is staked if executed after this timestamp.
+        /// @return  Identifier of the created stake.
+        /// @note    register/update caller's staking data, and stake ERC20 token.
+        #[ink(message, selector = 0x3F1D8140)]
+        pub fn stake(&mut self, _amount: Balance, _deadline: Option<Timestamp>) -> Result<StakeId> {
+            let result = self.stake_impl(_amount, _deadline);
+            self.report_rejection(STAKE_SELECTOR, result)
+        }
+
+        /// Body of `stake`.
+        fn stake_impl(
+            &mut self,
+            _amount: Balance,
+            _deadline: Option<Timestamp>,
+        ) -> Result<StakeId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            let current_block_timestamp: Balance = self.env().block_timestamp().into();
+            self.ensure_deadline(_deadline)?;
+            self.ensure_stakeable(caller, _amount)?;
+            let balance = self.token_balance_of(caller)?;
+            if balance < _amount {
+                return Err(Error::InsufficientBalance {
+                    balance,
+                    required: _amount,
+                });
+            }
+            let multiplier = self.new_stake_multiplier();
+            let new_staker = !self.staked.contains_key(&caller);
+            // Transfer ERC20 token to this contract before crediting the stake.
+            self.transfer_with_signature(caller, me, _amount)?;
+            let stake_id = self.push_stake(caller, _amount, 0, current_block_timestamp, multiplier);
+            if new_staker {
+                self.sponsor_rent(caller, _amount, balance);
+            }
+            Ok(stake_id)
+        }
+
+        /// @dev     Method #1-2 (WRITE)
+        /// @param   _amount:Balance
+        /// @param   _deadline: deadline of the permit, nothing is staked after it.
+        /// @param   _signature: signature of the token's `permit` approving this
+        ///          contract to spend _amount on behalf of the caller.
+        /// @return  Identifier of the created stake.
+        /// @note    approves and stakes in a single transaction, see `permit` of the
+        ///          token contract for what to sign.
+        #[ink(message)]
+        pub fn stake_with_permit(
+            &mut self,
+            _amount: Balance,
+            _deadline: Timestamp,
+            _signature: [u8; 65],
+        ) -> Result<StakeId> {
+            let result = self.stake_with_permit_impl(_amount, _deadline, _signature);
+            self.report_rejection(ink_lang::selector_bytes!("stake_with_permit"), result)
+        }
+
+        /// Body of `stake_with_permit`.
+        fn stake_with_permit_impl(
+            &mut self,
+            _amount: Balance,
+            _deadline: Timestamp,
+            _signature: [u8; 65],
+        ) -> Result<StakeId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            let current_block_timestamp: Balance = self.env().block_timestamp().into();
+            self.ensure_deadline(Some(_deadline))?;
+            self.ensure_stakeable(caller, _amount)?;
+            let balance = self.token_balance_of(caller)?;
+            if balance < _amount {
+                return Err(Error::InsufficientBalance {
+                    balance,
+                    required: _amount,
+                });
+            }
+            let multiplier = self.new_stake_multiplier();
+            let new_staker = !self.staked.contains_key(&caller);
+            let token = self.get_token();
+            let permit = (caller, me, _amount, _deadline, _signature);
+            Self::token_op(token, ink_lang::selector_bytes!("permit"), permit, _amount)?;
+            let transfer = (caller, me, _amount);
+            Self::token_op(token, ink_lang::selector_bytes!("transfer_from"), transfer, _amount)?;
+            let stake_id = self.push_stake(caller, _amount, 0, current_block_timestamp, multiplier);
+            if new_staker {
+                self.sponsor_rent(caller, _amount, balance);
+            }
+            Ok(stake_id)
+        }
+
+        /// @dev     Method #1-1 (WRITE)
+        /// @param   _from: account the tokens have been transferred from.
+        /// @param   _value: transferred tokens.
+        /// @param   _data: must be empty.
+        /// @note    token contract only, called by its `transfer_and_call` once _value
+        ///          has been transferred to this contract. Stakes the received tokens
+        ///          for _from without a prior approval. Stakes for other accounts go
+        ///          through `gift_stake`, so that nobody gets locked stakes pushed onto
+        ///          them. The token contract returns the tokens if this rejects them.
+        #[ink(message)]
+        pub fn on_token_received(
+            &mut self,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<()> {
+            if self.env().caller() != self.get_token() {
+                return Err(Error::InvalidToken);
+            }
+            if !_data.is_empty() {
+                return Err(Error::InvalidPayload);
+            }
+            if _value == 0 {
+                return Err(Error::BelowMinimum {
+                    amount: _value,
+                    minimum: 1,
+                });
+            }
+            self.ensure_caller_allowed(_from)?;
+            self.ensure_stakeable(_from, _value)?;
+            let timestamp = self.env().block_timestamp().into();
+            let multiplier = self.new_stake_multiplier();
+            // The tokens are already held, the token contract awaits this call and
+            // can't be called back.
+            self.push_stake(_from, _value, 0, timestamp, multiplier);
+            Ok(())
+        }
+
+        /// @dev     Method #1-3 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    first phase of a two-phase stake. Pulls _amount of caller's tokens
+        ///          into a pending deposit, staked by `confirm_stake` or refunded by
+        ///          `cancel_pending`. Nothing is locked until then.
+        #[ink(message)]
+        pub fn deposit(&mut self, _amount: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            self.ensure_not_emergency()?;
+            if _amount == 0 {
+                return Err(Error::BelowMinimum {
+                    amount: _amount,
+                    minimum: 1,
+                });
+            }
+            let me = self.env().account_id();
+            self.transfer_with_signature(caller, me, _amount)?;
+            let pending = self.pending_deposit_of(caller);
+            self.pending_deposits.insert(caller, pending + _amount);
+            self.ledger.post(Book::Holdings, Book::Deposits, _amount);
+            self.env().emit_event(Deposited {
+                account: caller,
+                amount: _amount,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #1-4 (WRITE)
+        /// @param   _amount: part of caller's pending deposit to stake.
+        /// @return  Identifier of the created stake.
+        /// @note    second phase of a two-phase stake. Locks _amount of the pending
+        ///          deposit like `stake`, without another token transfer.
+        #[ink(message)]
+        pub fn confirm_stake(&mut self, _amount: Balance) -> Result<StakeId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let pending = self.pending_deposit_of(caller);
+            if _amount > pending {
+                return Err(Error::InsufficientBalance {
+                    balance: pending,
+                    required: _amount,
+                });
+            }
+            self.ensure_stakeable(caller, _amount)?;
+            let timestamp = self.env().block_timestamp().into();
+            let multiplier = self.new_stake_multiplier();
+            if pending == _amount {
+                self.pending_deposits.take(&caller);
+            } else {
+                self.pending_deposits.insert(caller, pending - _amount);
+            }
+            // `push_stake` credits the principal out of the holdings again.
+            self.ledger.post(Book::Deposits, Book::Holdings, _amount);
+            Ok(self.push_stake(caller, _amount, 0, timestamp, multiplier))
+        }
+
+        /// @dev     Method #1-5 (WRITE)
+        /// @return  Refunded amount.
+        /// @note    refunds caller's whole pending deposit. The deposit is only
+        ///          cleared after the transfer went through.
+        #[ink(message)]
+        pub fn cancel_pending(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let amount = self.pending_deposit_of(caller);
+            if amount == 0 {
+                return Err(Error::NoPendingDeposit);
+            }
+            let me = self.env().account_id();
+            self.transfer_with_signature(me, caller, amount)?;
+            self.pending_deposits.take(&caller);
+            self.ledger.post(Book::Deposits, Book::Holdings, amount);
+            self.env().emit_event(DepositRefunded {
+                account: caller,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// @dev     Method #1-6 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Tokens deposited by _addr and not staked or refunded yet.
+        #[ink(message)]
+        pub fn pending_deposit_of(&self, _addr: AccountId) -> Balance {
+            self.pending_deposits.get(&_addr).copied().unwrap_or(0)
+        }
+
+        /// @dev     Method #1-7 (WRITE)
+        /// @param   _amount: Balance
+        /// @param   _deadline: nothing is staked if executed after this timestamp.
+        /// @return  Identifier of the created stake.
+        /// @note    pulls _amount with the token's `transfer_from`, after caller
+        ///          approved this contract with the token's `approve`. A short
+        ///          allowance is reported with the allowance to add.
+        #[ink(message)]
+        pub fn stake_from_allowance(
+            &mut self,
+            _amount: Balance,
+            _deadline: Option<Timestamp>,
+        ) -> Result<StakeId> {
+            let result = self.stake_from_allowance_impl(_amount, _deadline);
+            self.report_rejection(ink_lang::selector_bytes!("stake_from_allowance"), result)
+        }
+
+        /// Body of `stake_from_allowance`.
+        fn stake_from_allowance_impl(
+            &mut self,
+            _amount: Balance,
+            _deadline: Option<Timestamp>,
+        ) -> Result<StakeId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            let current_block_timestamp: Balance = self.env().block_timestamp().into();
+            self.ensure_deadline(_deadline)?;
+            self.ensure_stakeable(caller, _amount)?;
+            let balance = self.token_balance_of(caller)?;
+            if balance < _amount {
+                return Err(Error::InsufficientBalance {
+                    balance,
+                    required: _amount,
+                });
+            }
+            let token = self.get_token();
+            let allowance: Balance =
+                Self::call_token(token, ink_lang::selector_bytes!("allowance"), (caller, me))?;
+            if allowance < _amount {
+                return Err(Error::InsufficientAllowance {
+                    allowance,
+                    missing: _amount - allowance,
+                });
+            }
+            let multiplier = self.new_stake_multiplier();
+            let new_staker = !self.staked.contains_key(&caller);
+            let transfer = (caller, me, _amount);
+            Self::token_op(token, ink_lang::selector_bytes!("transfer_from"), transfer, _amount)?;
+            let stake_id = self.push_stake(caller, _amount, 0, current_block_timestamp, multiplier);
+            if new_staker {
+                self.sponsor_rent(caller, _amount, balance);
+            }
+            Ok(stake_id)
+        }
+
+        /// Rejects a new stake of `amount` for `account` unless staking is open and
+        /// the amount fits the limits.
+        fn ensure_stakeable(&self, account: AccountId, amount: Balance) -> Result<()> {
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            self.ensure_not_emergency()?;
+            if let Some(successor) = self.successor {
+                return Err(Error::Deprecated { successor });
+            }
+            if amount < self.min_stake {
+                return Err(Error::BelowMinimum {
+                    amount,
+                    minimum: self.min_stake,
+                });
+            }
+            if self.max_stake > 0 && amount > self.max_stake {
+                return Err(Error::AboveMaximum {
+                    amount,
+                    maximum: self.max_stake,
+                });
+            }
+            let headroom = self.max_stake_headroom(account);
+            if amount > headroom {
+                return Err(Error::AboveMaximum {
+                    amount,
+                    maximum: headroom,
+                });
+            }
+            self.ensure_stake_capacity(account)
+        }
+
+        /// Returns the reward multiplier of a stake created now.
+        fn new_stake_multiplier(&self) -> u32 {
+            // Early birds keep the bonus multiplier for the whole life of the stake.
+            if BlockNumberOf(self.env().block_number()) < self.bonus_end_block {
+                self.bonus_multiplier
+            } else {
+                MULTIPLIER_BASE
+            }
+        }
+
+        /// @dev       Method #2 (READ)
+        /// @param     _start: stake timestamp in ms.
+        /// @return    Unstakable part of a stake from _start under the latest schedule,
+        ///            in parts of `unlock_precision`.
+        #[ink(message)]
+        pub fn get_unstakable(&self, _start: Balance) -> Balance {
+            self.unlocked_fraction(self.schedule_count - 1, _start)
+        }
+
+        /// @dev     Method #2-5 (READ)
+        /// @return  Denominator of the unlocked fractions of the schedules: 1000 (per
+        ///          mille), or 1000000 (ppm) when built with the `unlock-ppm` feature.
+        #[ink(message)]
+        pub fn unlock_precision(&self) -> u32 {
+            UNLOCK_PRECISION
+        }
+
+        /// @dev     Method #2-7 (READ)
+        /// @return  Divisor of all schedule durations set at instantiation, `1`
+        ///          unless deployed with `new_scaled` on a testnet.
+        #[ink(message)]
+        pub fn time_scale(&self) -> u32 {
+            self.time_scale
+        }
+
+        /// @dev     Method #2-1 (WRITE)
+        /// @param   _step: length of a step in ms.
+        /// @param   _unlocked: parts of `unlock_precision` unlocked after each number
+        ///          of full steps.
+        /// @note    owner only. New stakes use the added schedule, existing stakes
+        ///          keep the schedule active at their creation.
+        #[ink(message)]
+        pub fn add_schedule(
+            &mut self,
+            _step: Timestamp,
+            _unlocked: Vec<u32>,
+        ) -> Result<ScheduleVersion> {
+            self.ensure_owner()?;
+            let schedule = Schedule {
+                step: _step,
+                unlocked: _unlocked,
+            };
+            if !schedule.is_valid() {
+                return Err(Error::InvalidSchedule);
+            }
+            Ok(self.insert_schedule(schedule))
+        }
+
+        /// @dev     Method #2-6 (WRITE)
+        /// @param   _duration: time in ms until a new stake is fully unstakable.
+        /// @param   _step: step period in ms, e.g. an hour, a day or a week.
+        /// @note    owner only. Adds a schedule unlocking an equal part after each
+        ///          _step until _duration, which _step must divide into at most
+        ///          `MAX_SCHEDULE_STEPS` steps. New stakes use the added schedule.
+        #[ink(message)]
+        pub fn add_linear_schedule(
+            &mut self,
+            _duration: Timestamp,
+            _step: Timestamp,
+        ) -> Result<ScheduleVersion> {
+            self.ensure_owner()?;
+            if _step == 0
+                || _duration < _step
+                || _duration % _step != 0
+                || _duration / _step > MAX_SCHEDULE_STEPS as Timestamp
+            {
+                return Err(Error::InvalidStepPeriod {
+                    step: _step,
+                    duration: _duration,
+                });
+            }
+            let steps = Balance::from(_duration / _step);
+            let unlocked = (0..=steps)
+                .map(|k| {
+                    math::mul_div(k, UNLOCK_PRECISION.into(), steps, Rounding::Floor) as u32
+                })
+                .collect();
+            Ok(self.insert_schedule(Schedule {
+                step: _step,
+                unlocked,
+            }))
+        }
+
+        /// Adds the validated `schedule` as the one of new stakes.
+        fn insert_schedule(&mut self, schedule: Schedule) -> ScheduleVersion {
+            let version = self.schedule_count;
+            self.log_admin(AdminAction::AddSchedule, &schedule);
+            self.schedules.insert(version, schedule);
+            self.schedule_count += 1;
+            version
+        }
+
+        /// @dev     Method #2-2 (READ)
+        /// @param   _version: ScheduleVersion
+        /// @return  Unlock schedule _version, `None` if it doesn't exist.
+        #[ink(message)]
+        pub fn get_schedule(&self, _version: ScheduleVersion) -> Option<Schedule> {
+            self.schedules.get(&_version).cloned()
+        }
+
+        /// @dev     Method #2-4 (READ)
+        /// @param   _schedule: candidate unlock schedule.
+        /// @param   _amount: staked amount.
+        /// @param   _elapsed: age of the stake in ms.
+        /// @return  Amount _schedule would unlock, so that a proposed schedule can be
+        ///          checked before it's added.
+        #[ink(message)]
+        pub fn simulate_schedule(
+            &self,
+            _schedule: Schedule,
+            _amount: Balance,
+            _elapsed: Timestamp,
+        ) -> Result<Balance> {
+            if !_schedule.is_valid() {
+                return Err(Error::InvalidSchedule);
+            }
+            Ok(math::unlocked_amount(
+                _schedule.unlocked_at(self.schedule_age(_elapsed.into())),
+                _amount,
+                self.rounding,
+            ))
+        }
+
+        /// @dev     Method #2-3 (READ)
+        /// @param   _addr: AccountId
+        /// @param   _stake_id: StakeId
+        /// @return  Schedule version of _addr's stake _stake_id, `None` if it doesn't exist.
+        #[ink(message)]
+        pub fn schedule_of(
+            &self,
+            _addr: AccountId,
+            _stake_id: StakeId,
+        ) -> Option<ScheduleVersion> {
+            self.staked
+                .get(&_addr)?
+                .iter()
+                .find(|stake| stake.id == _stake_id)
+                .map(|stake| stake.schedule)
+        }
+
+        /// @dev     Method #2-8 (READ)
+        /// @param   _addr: AccountId
+        /// @param   _stake_id: StakeId
+        /// @return  JSON metadata of _addr's stake _stake_id as a data URI, for
+        ///          wallets and marketplaces to render the position, `None` if it
+        ///          doesn't exist.
+        /// @note    Amounts are in the smallest token unit, times in ms and the
+        ///          multiplier in per mille.
+        #[ink(message)]
+        pub fn token_uri(&self, _addr: AccountId, _stake_id: StakeId) -> Option<String> {
+            let stake = self
+                .staked
+                .get(&_addr)?
+                .iter()
+                .find(|stake| stake.id == _stake_id)?;
+            let unlock = self.schedules.get(&stake.schedule).map_or(stake.timestamp, |schedule| {
+                let last = schedule.unlocked.len().saturating_sub(1) as Balance;
+                stake.timestamp + self.real_duration(last * Balance::from(schedule.step))
+            });
+            Some(format!(
+                "data:application/json,{{\"name\":\"Stake #{}\",\"amount\":{},\"released\":{},\
+                 \"start\":{},\"unlock\":{},\"multiplier\":{}}}",
+                stake.id, stake.amount, stake.released, stake.timestamp, unlock, stake.multiplier
+            ))
+        }
+
+        /// Returns the parts of `UNLOCK_PRECISION` of a stake from `start` unlocked by
+        /// now under schedule `version`.
+        fn unlocked_fraction(&self, version: ScheduleVersion, start: Balance) -> Balance {
+            let now = Balance::from(self.now());
+            if now < start {
+                return 0;
+            }
+            self.schedules
+                .get(&version)
+                .map_or(UNLOCK_PRECISION.into(), |schedule| {
+                    schedule.unlocked_at(self.schedule_age(now - start))
+                })
+        }
+
+        /// Returns `age` ms of real time as schedule time, see `time_scale`.
+        fn schedule_age(&self, age: Balance) -> Balance {
+            age.saturating_mul(self.time_scale.into())
+        }
+
+        /// Returns the real time in ms after which `duration` ms of schedule time
+        /// have passed, see `time_scale`.
+        fn real_duration(&self, duration: Balance) -> Balance {
+            math::mul_div(duration, 1, self.time_scale.into(), Rounding::Ceil)
+        }
+
+        /// Returns the maturity of schedule `version` in real time.
+        fn maturity_of_version(&self, version: ScheduleVersion) -> Balance {
+            self.schedules
+                .get(&version)
+                .map_or(0, |schedule| self.real_duration(schedule.maturity()))
+        }
+
+        /// Returns the maturity of the schedule of `stake`.
+        fn maturity_of(&self, stake: &Stake) -> Balance {
+            self.maturity_of_version(stake.schedule)
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Total balance of _addr's ERC20 token, `0` without stakes.
+        #[ink(message)]
+        pub fn get_balance(&self, _addr: AccountId) -> Balance {
+            self.unstakable_of(_addr)
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Total supply of the ERC20 token, `0` if it can't be called.
+        #[ink(message)]
+        pub fn get_erc20_totalsupply(&self) -> Balance {
+            self.token_total_supply().unwrap_or_default()
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Total balance of _addr's ERC20 token, `0` if it can't be called.
+        #[ink(message)]
+        pub fn get_erc20_balance(&self, _addr: AccountId) -> Balance {
+            self.token_balance_of(_addr).unwrap_or_default()
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Start of _addr's stake at _index, `0` if there is none.
+        #[ink(message)]
+        pub fn get_staked_timestamp(&self, _addr: AccountId, _index: Balance) -> Balance {
+            self.stake_at(_addr, _index).map_or(0, |stake| stake.timestamp)
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Amount of _addr's stake at _index, `0` if there is none.
+        #[ink(message)]
+        pub fn get_staked_amount(&self, _addr: AccountId, _index: Balance) -> Balance {
+            self.stake_at(_addr, _index).map_or(0, |stake| stake.amount)
+        }
+
+        /// Returns the stake of `account` at `index`, `None` for accounts that
+        /// fully exited or an index out of range.
+        fn stake_at(&self, account: AccountId, index: Balance) -> Option<&Stake> {
+            let index = usize::try_from(index).ok()?;
+            self.staked.get(&account).and_then(|stakes| stakes.get(index))
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Total balance of _addr's ERC20 token.
+        #[ink(message)]
+        pub fn get_sig_status(&self) -> u128 {
+            self.sig_status
+        }
+
+        /// @dev     Method #4 (WRITE)
+        /// @param   _amount: Balance
+        /// @param   _min_expected_out: nothing is claimed if less would be paid out,
+        ///          e.g. after a fee change.
+        /// @param   _deadline: nothing is claimed if executed after this timestamp.
+        /// @return  Claimed amount net of the withdrawal fee.
+        /// @note    TL;DR : "Inline comment will help you."
+        #[ink(message, selector = 0x08B82517)]
+        pub fn claim(
+            &mut self,
+            _amount: Balance,
+            _min_expected_out: Option<Balance>,
+            _deadline: Option<Timestamp>,
+        ) -> Result<Balance> {
+            let caller = self.env().caller();
+            let result = self
+                .ensure_deadline(_deadline)
+                .and_then(|_| self.claim_impl(caller, _amount, _min_expected_out));
+            self.report_rejection(CLAIM_SELECTOR, result)
+        }
+
+        /// @dev     Method #4-1 (WRITE)
+        /// @param   _amount: Balance
+        /// @param   _claim_id: client-supplied identifier of this claim.
+        /// @return  Claimed amount.
+        /// @note    Idempotent claim for integrators retrying transactions.
+        ///          Repeating a claim with an already-used `_claim_id` changes nothing
+        ///          and returns the amount recorded by the original claim.
+        #[ink(message)]
+        pub fn claim_with_id(&mut self, _amount: Balance, _claim_id: ClaimId) -> Result<Balance> {
+            let result = self.claim_with_id_impl(_amount, _claim_id);
+            self.report_rejection(ink_lang::selector_bytes!("claim_with_id"), result)
+        }
+
+        /// Body of `claim_with_id`.
+        fn claim_with_id_impl(&mut self, _amount: Balance, _claim_id: ClaimId) -> Result<Balance> {
+            let caller = self.env().caller();
+            if let Some(claimed) = self.claim_ids.get(&(caller, _claim_id)) {
+                return Ok(*claimed);
+            }
+            let claimed = self.claim_impl(caller, _amount, None)?;
+            self.claim_ids.insert((caller, _claim_id), claimed);
+            self.count_entries(caller, |counts| counts.claim_ids += 1);
+            Ok(claimed)
+        }
+
+        /// @dev     Method #4-2 (READ)
+        /// @param   addr: AccountId
+        /// @param   _claim_id: ClaimId
+        /// @return  Amount claimed under `_claim_id`, `None` if the id is unused.
+        #[ink(message)]
+        pub fn get_claim(&self, _addr: AccountId, _claim_id: ClaimId) -> Option<Balance> {
+            self.claim_ids.get(&(_addr, _claim_id)).copied()
+        }
+
+        /// Releases `_amount` of caller's unstakable balance and pays it out
+        /// net of the withdrawal fee. Returns the amount paid out.
+        fn claim_impl(
+            &mut self,
+            caller: AccountId,
+            _amount: Balance,
+            min_expected_out: Option<Balance>,
+        ) -> Result<Balance> {
+            let net = self.release(caller, _amount, min_expected_out)?;
+            self.pay_out_claim(caller, net, _amount - net, Book::Principal);
+            Ok(net)
+        }
+
+        /// Records the receipt of a claim of `amount` net of `fee` by `account`.
+        fn issue_receipt(&mut self, account: AccountId, amount: Balance, fee: Balance) {
+            let receipt_id = self.next_receipt_id;
+            self.next_receipt_id += 1;
+            self.count_entries(account, |counts| counts.receipts += 1);
+            self.receipts.insert(
+                receipt_id,
+                Receipt {
+                    account,
+                    amount,
+                    fee,
+                    timestamp: self.env().block_timestamp(),
+                    block: self.env().block_number(),
+                },
+            );
+            self.env().emit_event(Claimed {
+                account,
+                receipt_id,
+                amount,
+                fee,
+            });
+        }
+
+        /// @dev     Method #4-6 (READ)
+        /// @param   _receipt_id: ReceiptId
+        /// @return  Receipt of the claim _receipt_id, `None` if not issued yet.
+        #[ink(message)]
+        pub fn receipt(&self, _receipt_id: ReceiptId) -> Option<Receipt> {
+            self.receipts.get(&_receipt_id).copied()
+        }
+
+        /// Releases `_amount` of caller's unstakable balance, withholding the
+        /// withdrawal fee. Returns the amount left to pay out, rejecting the
+        /// release if it is below `min_expected_out`.
+        fn release(
+            &mut self,
+            caller: AccountId,
+            _amount: Balance,
+            min_expected_out: Option<Balance>,
+        ) -> Result<Balance> {
+            if self.is_paused() && !self.refund_open {
+                return Err(Error::Paused);
+            }
+            let unstakable = self.unstakable_of(caller);
+            if unstakable < _amount {
+                debug_println!("{}", "Exceeds current unstakable");
+                return Err(Error::ExceedsUnstakable {
+                    requested: _amount,
+                    unstakable,
+                    block: self.env().block_number(),
+                });
+            }
+            if _amount < self.dust_threshold {
+                return Err(Error::BelowMinimum {
+                    amount: _amount,
+                    minimum: self.dust_threshold,
+                });
+            }
+            let fee = self.withdrawal_fee(caller, _amount);
+            if let Some(min_expected_out) = min_expected_out {
+                if _amount - fee < min_expected_out {
+                    return Err(Error::InsufficientOutput {
+                        out: _amount - fee,
+                        min_expected_out,
+                    });
+                }
+            }
+            let _claim_amount = _amount;
+            let mut amount = _amount;
+            let mut i = 0;
+
+            // Load caller's stakes once, release from them in memory and write them
+            // back once. Finally transfer ERC20 token to caller.
+            let mut stakes = self.staked.get(&caller).cloned().unwrap_or_default();
+            while i < stakes.len() && amount > 0 {
+                let stake = stakes[i];
+                let unstakable = self.unlocked_of(&stake).saturating_sub(stake.released);
+                if unstakable > amount {
+                    stakes[i].released += amount;
+                    amount = 0;
+                } else {
+                    stakes[i].released += unstakable;
+                    if stakes[i].released == stakes[i].amount {
+                        stakes.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                    amount -= unstakable;
+                }
+            }
+            self.staked.insert(caller, stakes);
+            self.last_user_activity = self.env().block_number();
+            let staked_after = self.reward_account(caller).staked - _claim_amount;
+            self.update_rewards(caller, staked_after, self.weight_of(caller));
+            // The net amount stays in the principal book until it is paid out.
+            self.ledger.post(Book::Principal, Book::Treasury, fee);
+            self.clear_if_empty(caller);
+            Ok(_claim_amount - fee)
+        }
+
+        /// @dev     Method #4-3 (WRITE)
+        /// @param   _bot: claim threshold and tip, `None` to opt out.
+        /// @note    opts caller in to claims by anyone once its unstakable balance
+        ///          reaches the threshold, tipping the claimer from the claimed amount.
+        #[ink(message)]
+        pub fn set_claim_bot(&mut self, _bot: Option<ClaimBot>) -> Result<()> {
+            let caller = self.env().caller();
+            match _bot {
+                Some(bot) => {
+                    if bot.tip_bps > MAX_TIP_BPS {
+                        return Err(Error::FeeTooHigh {
+                            fee_bps: bot.tip_bps,
+                            max_fee_bps: MAX_TIP_BPS,
+                        });
+                    }
+                    self.claim_bots.insert(caller, bot);
+                }
+                None => {
+                    self.claim_bots.take(&caller);
+                }
+            }
+            Ok(())
+        }
+
+        /// @dev     Method #4-4 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Claim threshold and tip of _addr, `None` if not opted in.
+        #[ink(message)]
+        pub fn claim_bot_of(&self, _addr: AccountId) -> Option<ClaimBot> {
+            self.claim_bots.get(&_addr).copied()
+        }
+
+        /// @dev     Method #4-5 (WRITE)
+        /// @param   _owner: AccountId
+        /// @return  Tip paid to the caller.
+        /// @note    anyone. Claims _owner's whole unstakable balance once it reaches
+        ///          _owner's threshold, paying the caller the tip out of it.
+        #[ink(message)]
+        pub fn claim_for_with_tip(&mut self, _owner: AccountId) -> Result<Balance> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            let bot = self.claim_bots.get(&_owner).copied().ok_or(Error::NotOptedIn)?;
+            let unstakable = if self.staked.contains_key(&_owner) {
+                self.get_balance(_owner)
+            } else {
+                0
+            };
+            if unstakable == 0 || unstakable < bot.threshold {
+                return Err(Error::BelowMinimum {
+                    amount: unstakable,
+                    minimum: bot.threshold,
+                });
+            }
+            let net = self.release(_owner, unstakable, None)?;
+            let tip = math::mul_div(net, bot.tip_bps.into(), 10_000, Rounding::Floor);
+            self.pay_out(me, caller, tip, Book::Principal);
+            self.pay_out_claim(_owner, net - tip, unstakable - net + tip, Book::Principal);
+            Ok(tip)
+        }
+
+        /// @dev     Method #5 (WRITE)
+        /// @param   _close_account: also close caller's account like `close_account`
+        ///          once everything has been claimed. An account still holding
+        ///          entries is left open without failing the claim.
+        /// @note    unstake all tokens.
+        ///          This method is similar to claim()
+        #[ink(message, selector = 0x78F2D905)]
+        pub fn claim_all(&mut self, _close_account: bool) -> Result<Balance> {
+            let result = self.claim_all_impl(self.env().caller(), _close_account);
+            self.report_rejection(CLAIM_ALL_SELECTOR, result)
+        }
+
+        /// Body of `claim_all` and `claim_all_for`, claiming for `caller`.
+        fn claim_all_impl(&mut self, caller: AccountId, _close_account: bool) -> Result<Balance> {
+            // Like `release`, the emergency refund lets stakers exit while paused.
+            if self.is_paused() && !self.refund_open {
+                debug_println!("{}", "Contract is paused");
+                return Err(Error::Paused);
+            }
+            let balance = self.unstakable_of(caller);
+            if balance <= 0 {
+                debug_println!("{}", "No token to be staked");
+                return Ok(0);
+            }
+            let fee = self.withdrawal_fee(caller, balance);
+            let mut i = 0;
+            // Load caller's stakes once, release them in memory and write them back once.
+            let mut stakes = self.staked.get(&caller).cloned().unwrap_or_default();
+            while i < stakes.len() {
+                let unstakable = self.unlocked_of(&stakes[i]).saturating_sub(stakes[i].released);
+                stakes[i].released += unstakable;
+                if stakes[i].released == stakes[i].amount {
+                    stakes.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+            self.staked.insert(caller, stakes);
+            self.last_user_activity = self.env().block_number();
+            let staked_after = self.reward_account(caller).staked - balance;
+            self.update_rewards(caller, staked_after, self.weight_of(caller));
+            self.ledger.post(Book::Principal, Book::Treasury, fee);
+            self.clear_if_empty(caller);
+            self.pay_out_claim(caller, balance - fee, fee, Book::Principal);
+            if _close_account {
+                let _ = self.close_account_of(caller);
+            }
+            Ok(balance - fee)
+        }
+
+        /// @dev     Method #5-7 (WRITE)
+        /// @note    deletes all entries of caller once it fully exited: no stakes,
+        ///          escrow, rewards or boost left. The storage deposit of the entries
+        ///          is refunded to caller. Entries kept for others (checkpoints of
+        ///          historical balances, claim ids, owner-set flags) stay.
+        #[ink(message)]
+        pub fn close_account(&mut self) -> Result<()> {
+            self.close_account_of(self.env().caller())
+        }
+
+        /// Deletes the `CLOSED_ENTRIES` entries of `account`, rejecting accounts
+        /// not fully exited.
+        fn close_account_of(&mut self, account: AccountId) -> Result<()> {
+            if self.staked.contains_key(&account)
+                || self.escrow_of(account) > 0
+                || self.pending_rewards(account) > 0
+                || self.boosts.contains_key(&account)
+                || self.legacy_owed.get(&account).map_or(false, |owed| *owed > 0)
+            {
+                return Err(Error::AccountInUse);
+            }
+            self.escrow.take(&account);
+            self.reward_accounts.take(&account);
+            self.claim_bots.take(&account);
+            self.payout_addresses.take(&account);
+            self.pending_payouts.take(&account);
+            self.recoveries.take(&account);
+            self.legacy_owed.take(&account);
+            self.refund_votes.take(&account);
+            self.env().emit_event(AccountClosed { account });
+            Ok(())
+        }
+
+        /// Returns the unstakable balance of `account`, `0` without stakes.
+        fn unstakable_of(&self, account: AccountId) -> Balance {
+            self.staked
+                .get(&account)
+                .into_iter()
+                .flatten()
+                .map(|stake| self.unlocked_of(stake).saturating_sub(stake.released))
+                .sum()
+        }
+
+        /// @dev     Method #5-4 (WRITE)
+        /// @param   _payout_address: AccountId
+        /// @note    proposes the address receiving caller's claims, settable with
+        ///          `set_payout_address` once `PAYOUT_TIMELOCK` elapsed.
+        #[ink(message)]
+        pub fn propose_payout_address(&mut self, _payout_address: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            let eta = self.env().block_timestamp() + PAYOUT_TIMELOCK;
+            self.pending_payouts.insert(caller, (_payout_address, eta));
+            self.env().emit_event(PayoutAddressProposed {
+                account: caller,
+                payout_address: _payout_address,
+                eta,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #5-5 (WRITE)
+        /// @param   _payout_address: AccountId proposed by `propose_payout_address`.
+        /// @note    used by all claim paths of caller from then on.
+        #[ink(message)]
+        pub fn set_payout_address(&mut self, _payout_address: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            match self.pending_payouts.get(&caller) {
+                Some(&(payout_address, eta)) if payout_address == _payout_address => {
+                    let now = self.env().block_timestamp();
+                    if now < eta {
+                        return Err(Error::TimelockActive { now, eta });
+                    }
+                }
+                _ => return Err(Error::NotProposed),
+            }
+            self.pending_payouts.take(&caller);
+            if _payout_address == caller {
+                self.payout_addresses.take(&caller);
+            } else {
+                self.payout_addresses.insert(caller, _payout_address);
+            }
+            self.env().emit_event(PayoutAddressChanged {
+                account: caller,
+                payout_address: _payout_address,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #5-6 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Address receiving _addr's claims.
+        #[ink(message)]
+        pub fn payout_address_of(&self, _addr: AccountId) -> AccountId {
+            self.payout_addresses.get(&_addr).copied().unwrap_or(_addr)
+        }
+
+        /// @dev     Method #5-8 (WRITE)
+        /// @param   _shares: beneficiaries and their shares in per mille, summing up
+        ///          to 1000, at most `MAX_SPLIT_SHARES`. Empty to remove the split.
+        /// @note    splits caller's principal claims from `PAYOUT_TIMELOCK` on, like a
+        ///          payout address change. Caller's own share goes to its payout address.
+        #[ink(message)]
+        pub fn set_split(&mut self, _shares: Vec<(AccountId, u32)>) -> Result<()> {
+            let caller = self.env().caller();
+            let total = _shares
+                .iter()
+                .try_fold(0u32, |total, &(_, permille)| total.checked_add(permille));
+            if _shares.len() > MAX_SPLIT_SHARES
+                || _shares.iter().any(|&(_, permille)| permille == 0 || permille > 1000)
+                || (!_shares.is_empty() && total != Some(1000))
+            {
+                return Err(Error::InvalidSplit);
+            }
+            let effective_at = self.env().block_timestamp() + PAYOUT_TIMELOCK;
+            let previous = self.active_split(caller);
+            if _shares.is_empty() && previous.is_empty() {
+                self.payout_splits.take(&caller);
+            } else {
+                self.payout_splits.insert(
+                    caller,
+                    PayoutSplit {
+                        shares: _shares.clone(),
+                        effective_at,
+                        previous,
+                    },
+                );
+            }
+            self.env().emit_event(SplitChanged {
+                account: caller,
+                shares: _shares,
+                effective_at,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #5-9 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Split of _addr's claims, `None` if all go to its payout address.
+        #[ink(message)]
+        pub fn split_of(&self, _addr: AccountId) -> Option<PayoutSplit> {
+            self.payout_splits.get(&_addr).cloned()
+        }
+
+        /// Returns the shares of `account`'s claims applying now, empty without split.
+        fn active_split(&self, account: AccountId) -> Vec<(AccountId, u32)> {
+            match self.payout_splits.get(&account) {
+                Some(split) if self.env().block_timestamp() >= split.effective_at => {
+                    split.shares.clone()
+                }
+                Some(split) => split.previous.clone(),
+                None => Vec::new(),
+            }
+        }
+
+        /// Pays `amount` claimed by `account` out of `book` along its split, after
+        /// its round-up donation. The payout address of `account` receives its own
+        /// share, the rounding dust goes to the last beneficiary.
+        fn pay_out_claim(
+            &mut self,
+            account: AccountId,
+            amount: Balance,
+            fee: Balance,
+            book: Book,
+        ) {
+            let own = self.pay_out_shares(account, amount, fee, book);
+            if own > 0 {
+                let me = self.env().account_id();
+                let payout = self.payout_address_of(account);
+                self.pay_out(me, payout, own, book);
+            }
+        }
+
+        /// Pays the round-up donation and the shares of the other beneficiaries of
+        /// `amount` claimed by `account` net of `fee`, and returns the share left to
+        /// `account`. Every claim paid to its owner is receipted here.
+        fn pay_out_shares(
+            &mut self,
+            account: AccountId,
+            amount: Balance,
+            fee: Balance,
+            book: Book,
+        ) -> Balance {
+            self.issue_receipt(account, amount, fee);
+            let amount = amount - self.donate_round_up(account, amount, book);
+            let me = self.env().account_id();
+            let shares = self.active_split(account);
+            let mut remaining = amount;
+            let mut own = 0;
+            for (i, &(beneficiary, permille)) in shares.iter().enumerate() {
+                let share = if i + 1 == shares.len() {
+                    remaining
+                } else {
+                    math::mul_div(amount, permille.min(1000).into(), 1000, Rounding::Floor)
+                        .min(remaining)
+                };
+                remaining = remaining.saturating_sub(share);
+                if beneficiary == account {
+                    own += share;
+                } else if share > 0 {
+                    self.pay_out(me, beneficiary, share, book);
+                }
+            }
+            own + remaining
+        }
+
+        /// @dev     Method #5-1 (WRITE)
+        /// @note    drop caller's fully released entries and clear emptied mappings,
+        ///          so that the storage deposit of these entries is refunded.
+        #[ink(message)]
+        pub fn consolidate(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if let Some(staked) = self.staked.get_mut(&caller) {
+                let mut i = 0;
+                while i < staked.len() {
+                    if staked[i].released == staked[i].amount {
+                        staked.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            self.clear_if_empty(caller);
+            Ok(())
+        }
+
+        /// @dev     Method #5-3 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Future unlock steps of _addr as (time in ms, unlocked amount),
+        ///          aggregated across all stakes, the earliest `MAX_TIMELINE_STEPS`.
+        #[ink(message)]
+        pub fn unlock_timeline(&self, _addr: AccountId) -> Vec<(Timestamp, Balance)> {
+            let now = Balance::from(self.now());
+            let mut timeline: Vec<(Timestamp, Balance)> = Vec::new();
+            for stake in self.staked.get(&_addr).into_iter().flatten() {
+                let schedule = match self.schedules.get(&stake.schedule) {
+                    Some(schedule) => schedule,
+                    None => continue,
+                };
+                let step = Balance::from(schedule.step);
+                let last = schedule.unlocked.len().saturating_sub(1);
+                let age = self.schedule_age(now.saturating_sub(stake.timestamp));
+                let current = (age / step).min(last as Balance);
+                let unlocked = |k: usize| {
+                    math::unlocked_amount(schedule.unlocked[k].into(), stake.amount, self.rounding)
+                };
+                for k in (current as usize + 1)..=last {
+                    let amount = unlocked(k).saturating_sub(unlocked(k - 1));
+                    if amount > 0 {
+                        let time = stake.timestamp + self.real_duration(k as Balance * step);
+                        timeline.push((time as Timestamp, amount));
+                    }
+                }
+            }
+            timeline.sort_unstable();
+            let mut aggregated: Vec<(Timestamp, Balance)> = Vec::new();
+            for (time, amount) in timeline {
+                match aggregated.last_mut() {
+                    Some(last) if last.0 == time => last.1 += amount,
+                    _ if aggregated.len() == MAX_TIMELINE_STEPS => break,
+                    _ => aggregated.push((time, amount)),
+                }
+            }
+            aggregated
+        }
+
+        /// @dev     Method #5-2 (READ)
+        /// @param   addr: AccountId
+        /// @return  Encoded size in bytes of the storage entries held for _addr.
+        /// @note    The storage deposit charged for _addr is proportional to it.
+        ///          Entries set by the owner, e.g. roles, aren't counted.
+        #[ink(message)]
+        pub fn storage_footprint(&self, _addr: AccountId) -> u32 {
+            fn size<V: Encode>(value: Option<&V>) -> usize {
+                value.map_or(0, Encode::encoded_size)
+            }
+            let addr = &_addr;
+            let keyed = size(self.staked.get(addr))
+                + size(self.escrow.get(addr))
+                + size(self.reward_accounts.get(addr))
+                + size(self.checkpoints.get(addr))
+                + size(self.boosts.get(addr))
+                + size(self.recoveries.get(addr))
+                + size(self.claim_bots.get(addr))
+                + size(self.legacy_owed.get(addr))
+                + size(self.payout_addresses.get(addr))
+                + size(self.pending_payouts.get(addr))
+                + size(self.payout_splits.get(addr))
+                + size(self.round_ups.get(addr))
+                + size(self.donations.get(addr))
+                + size(self.refund_votes.get(addr))
+                + size(self.rent_sponsored.get(addr))
+                + size(self.pending_deposits.get(addr))
+                + size(self.entry_counts.get(addr))
+                + size(self.gauge_votes.get(addr))
+                + size(self.staker_index.get(addr))
+                + self.staker_index.get(addr).map_or(0, |_| addr.encoded_size());
+            // The entries not keyed by the account alone have a fixed size.
+            let counts = self.entry_counts.get(addr).copied().unwrap_or_default();
+            let counted = counts.claim_ids as usize * Balance::default().encoded_size()
+                + counts.pulls as usize * PullAuthorization::default().encoded_size()
+                + counts.receipts as usize * Receipt::default().encoded_size()
+                + counts.gifts as usize * Gift::default().encoded_size()
+                + counts.offers as usize * OtcOffer::default().encoded_size();
+            (keyed + counted) as u32
+        }
+
+        /// @dev     Method #5-10 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Storage keys of the entries held for _addr in the mappings keyed
+        ///          by the account alone, in field order, for light clients proving
+        ///          them against the state root without calling the contract.
+        /// @note    Only the keys of existing entries are returned.
+        #[ink(message)]
+        pub fn storage_keys_of(&self, _addr: AccountId) -> Vec<[u8; 32]> {
+            let fields = FieldKeys::new();
+            let addr = &_addr;
+            [
+                (fields.staked, self.staked.contains_key(addr)),
+                (fields.escrow, self.escrow.contains_key(addr)),
+                (fields.staker_index, self.staker_index.contains_key(addr)),
+                (fields.reward_accounts, self.reward_accounts.contains_key(addr)),
+                (fields.checkpoints, self.checkpoints.contains_key(addr)),
+                (fields.boosts, self.boosts.contains_key(addr)),
+                (fields.recoveries, self.recoveries.contains_key(addr)),
+                (fields.claim_bots, self.claim_bots.contains_key(addr)),
+                (fields.legacy_owed, self.legacy_owed.contains_key(addr)),
+                (fields.payout_addresses, self.payout_addresses.contains_key(addr)),
+                (fields.pending_payouts, self.pending_payouts.contains_key(addr)),
+                (fields.payout_splits, self.payout_splits.contains_key(addr)),
+                (fields.round_ups, self.round_ups.contains_key(addr)),
+                (fields.donations, self.donations.contains_key(addr)),
+                (fields.integrators, self.integrators.contains_key(addr)),
+                (fields.refund_votes, self.refund_votes.contains_key(addr)),
+                (fields.reward_excluded, self.reward_excluded.contains_key(addr)),
+                (fields.rent_sponsored, self.rent_sponsored.contains_key(addr)),
+                (fields.pending_deposits, self.pending_deposits.contains_key(addr)),
+                (fields.entry_counts, self.entry_counts.contains_key(addr)),
+                (fields.gauge_votes, self.gauge_votes.contains_key(addr)),
+            ]
+            .iter()
+            .filter(|(_, kept)| *kept)
+            .map(|(root, _)| Self::entry_key(root, addr))
+            .collect()
+        }
+
+        /// Returns the storage key of the entry of `account` in the mapping stored
+        /// at `root`. The values of a mapping follow the stash of its keys and are
+        /// stored at the Blake2x256 hash of the prefixed root and key.
+        fn entry_key(root: &Key, account: &AccountId) -> [u8; 32] {
+            use ink_env::hash::{Blake2x256, HashOutput};
+            let mut ptr = KeyPtr::from(*root);
+            ptr.next_for::<Stash<AccountId>>();
+            let values = *ptr.advance_by(0);
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            let prefixed = (b"ink hashmap", &values, account);
+            ink_env::hash_encoded::<Blake2x256, _>(&prefixed, &mut output);
+            output
+        }
+
+        /// Applies `update` to the entry counts of `account`.
+        fn count_entries(&mut self, account: AccountId, update: impl FnOnce(&mut EntryCounts)) {
+            let mut counts = self.entry_counts.get(&account).copied().unwrap_or_default();
+            update(&mut counts);
+            self.entry_counts.insert(account, counts);
+        }
+
+        /// Adds a stake of `amount` from `timestamp` to `account` under the latest
+        /// schedule and updates the reward accounting. `released` of it has been
+        /// claimed already, e.g. in the predecessor pool.
+        fn push_stake(
+            &mut self,
+            account: AccountId,
+            amount: Balance,
+            released: Balance,
+            timestamp: Balance,
+            multiplier: u32,
+        ) -> StakeId {
+            let id = self.next_stake_id;
+            self.next_stake_id += 1;
+            let new_stake = Stake {
+                timestamp,
+                amount,
+                multiplier,
+                id,
+                schedule: self.schedule_count - 1,
+                released,
+            };
+            // Rigister/update account's staking data.
+            if !self.staked.contains_key(&account) {
+                self.staked.insert(account, Stakes::default());
+                self.add_staker(account);
+            }
+            self.staked
+                .get_mut(&account)
+                .unwrap()
+                .try_push(new_stake)
+                .expect("stake capacity is checked before any state change");
+            let staked_after = self.reward_account(account).staked + new_stake.remaining();
+            self.update_rewards(account, staked_after, self.weight_of(account));
+            self.ledger.post(Book::Holdings, Book::Principal, new_stake.remaining());
+            self.last_user_activity = self.env().block_number();
+            id
+        }
+
+        /// Rejects adding a stake to `account` once it holds `MAX_STAKES`.
+        fn ensure_stake_capacity(&self, account: AccountId) -> Result<()> {
+            match self.staked.get(&account) {
+                Some(staked) if staked.is_full() => Err(Error::TooManyStakes {
+                    maximum: Stakes::CAPACITY as u32,
+                }),
+                Some(_) => Ok(()),
+                None if self.max_stakers > 0 && self.stakers.len() >= self.max_stakers => {
+                    Err(Error::StakerCapReached {
+                        maximum: self.max_stakers,
+                    })
+                }
+                None => Ok(()),
+            }
+        }
+
+        /// Removes the entries of `account` once it has no stake left.
+        fn clear_if_empty(&mut self, account: AccountId) {
+            if self.staked.get(&account).map_or(false, |v| v.is_empty()) {
+                self.staked.take(&account);
+                self.remove_staker(account);
+            }
+        }
+
+        /// Appends `account` to the index of the stakers.
+        fn add_staker(&mut self, account: AccountId) {
+            self.staker_index.insert(account, self.stakers.len());
+            self.stakers.push(account);
+        }
+
+        /// Removes `account` from the index of the stakers, moving the last staker
+        /// into its position.
+        fn remove_staker(&mut self, account: AccountId) {
+            let index = match self.staker_index.take(&account) {
+                Some(index) => index,
+                None => return,
+            };
+            self.stakers.swap_remove_drop(index);
+            if let Some(&moved) = self.stakers.get(index) {
+                self.staker_index.insert(moved, index);
+            }
+        }
+
+        /// @dev     Method #6 (WRITE)
+        /// @note    withdraw caller's escrowed balance.
+        ///          The escrow is only cleared after the transfer went through.
+        #[ink(message)]
+        pub fn withdraw_escrow(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let me = self.env().account_id();
+            let amount = self.escrow_of(caller);
+            if amount == 0 {
+                return Err(Error::EmptyEscrow);
+            }
+            self.transfer_with_signature(me, caller, amount)?;
+            self.escrow.take(&caller);
+            self.ledger.post(Book::Released, Book::Holdings, amount);
+            self.env().emit_event(EscrowWithdrawn {
+                account: caller,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// @dev     Method #7 (READ)
+        /// @param   addr: AccountId
+        /// @return  Escrowed balance of _addr waiting for `withdraw_escrow`.
+        #[ink(message)]
+        pub fn escrow_of(&self, _addr: AccountId) -> Balance {
+            *self.escrow.get(&_addr).unwrap_or(&0)
+        }
+
+        /// @dev     Method #8 (WRITE)
+        /// @param   _rounding: Rounding
+        /// @note    owner only. Sets the rounding of the unlock math.
+        #[ink(message)]
+        pub fn set_rounding(&mut self, _rounding: Rounding) -> Result<()> {
+            self.ensure_owner()?;
+            self.rounding = _rounding;
+            self.log_admin(AdminAction::SetRounding, &_rounding);
+            Ok(())
+        }
+
+        /// @dev     Method #9 (READ)
+        /// @return  Rounding of the unlock math.
+        #[ink(message)]
+        pub fn get_rounding(&self) -> Rounding {
+            self.rounding
+        }
+
+        /// @dev     Method #10 (WRITE)
+        /// @param   _treasury: AccountId
+        /// @note    owner only. Sets the receiver of swept surplus.
+        #[ink(message)]
+        pub fn set_treasury(&mut self, _treasury: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.treasury = _treasury;
+            self.log_admin(AdminAction::SetTreasury, &_treasury);
+            Ok(())
+        }
+
+        /// Returns the liquid tokens held above the obligations and the funded
+        /// token rewards.
+        fn surplus(&self) -> Balance {
+            let reward_funds = if self.rewards_in_staked_token() {
+                self.ledger.reward_funds
+            } else {
+                0
+            };
+            // A token that can't be called has no surplus to sweep.
+            self.token_balance_of(self.env().account_id())
+                .unwrap_or_default()
+                .saturating_sub(self.obligations() + reward_funds)
+        }
+
+        /// @dev     Method #11-1 (WRITE)
+        /// @param   _strategy: yield strategy contract, `None` to unregister.
+        /// @note    owner only. Only possible once everything has been divested.
+        ///          The strategy must expose `deposit(amount)` for tokens transferred
+        ///          to it and `withdraw(amount)` transferring them back to the caller,
+        ///          both returning `Result<(), E>` with a fieldless error `E`.
+        #[ink(message)]
+        pub fn set_strategy(&mut self, _strategy: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            if self.invested != 0 {
+                return Err(Error::StrategyInUse {
+                    invested: self.invested,
+                });
+            }
+            self.strategy = _strategy;
+            self.log_admin(AdminAction::SetStrategy, &_strategy);
+            Ok(())
+        }
+
+        /// @dev     Method #11-2 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    owner only. Moves _amount of the surplus into the strategy, the
+        ///          obligations staying covered by the liquid balance. Reverts if
+        ///          the strategy rejects the deposit.
+        #[ink(message)]
+        pub fn invest_surplus(&mut self, _amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let strategy = self.strategy.ok_or(Error::NoStrategy)?;
+            if _amount == 0 || _amount > self.surplus() {
+                return Err(Error::NoSurplus);
+            }
+            let me = self.env().account_id();
+            self.transfer_with_signature(me, strategy, _amount)?;
+            let deposit = ink_lang::selector_bytes!("deposit");
+            // Trap instead of returning an error so that the transfer is reverted.
+            if !Self::strategy_op(strategy, deposit, _amount) {
+                panic!("strategy rejected the deposit");
+            }
+            self.invested += _amount;
+            self.log_admin(AdminAction::InvestSurplus, &_amount);
+            Ok(())
+        }
+
+        /// @dev     Method #11-3 (WRITE)
+        /// @param   _amount: Balance
+        /// @return  Tokens received back from the strategy.
+        /// @note    owner only. Gains above the invested amount count as surplus.
+        #[ink(message)]
+        pub fn divest(&mut self, _amount: Balance) -> Result<Balance> {
+            self.ensure_owner()?;
+            let strategy = self.strategy.ok_or(Error::NoStrategy)?;
+            let me = self.env().account_id();
+            let before = self.token_balance_of(me)?;
+            let withdraw = ink_lang::selector_bytes!("withdraw");
+            if !Self::strategy_op(strategy, withdraw, _amount) {
+                return Err(Error::StrategyFailed);
+            }
+            let received = self.token_balance_of(me)?.saturating_sub(before);
+            self.invested = self.invested.saturating_sub(received);
+            self.log_admin(AdminAction::Divest, &_amount);
+            Ok(received)
+        }
+
+        /// @dev     Method #11 (WRITE)
+        /// @return  Swept amount.
+        /// @note    Transfers the tokens held above the obligations (staked principal and
+        ///          escrow), e.g. accumulated rounding dust, to the treasury.
+        #[ink(message)]
+        pub fn sweep_dust(&mut self) -> Result<Balance> {
+            let me = self.env().account_id();
+            let surplus = self.surplus();
+            if surplus == 0 {
+                return Err(Error::NoSurplus);
+            }
+            let treasury = self.settings.treasury;
+            self.transfer_with_signature(me, treasury, surplus)?;
+            Ok(surplus)
+        }
+
+        /// @dev     Method #12 (READ)
+        /// @return  Owner of the contract.
+        #[ink(message)]
+        pub fn get_owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// @dev     Method #12-1 (WRITE)
+        /// @param   _new_owner: AccountId, e.g. a multisig contract.
+        /// @note    owner only. The ownership moves once _new_owner calls
+        ///          `accept_ownership`, proving it can call admin messages.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, _new_owner: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.pending_owner = Some(_new_owner);
+            self.log_admin(AdminAction::TransferOwnership, &_new_owner);
+            Ok(())
+        }
+
+        /// @dev     Method #12-2 (WRITE)
+        /// @note    pending owner only.
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if self.pending_owner != Some(caller) {
+                return Err(Error::NotOwner);
+            }
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: caller,
+            });
+            self.log_admin(AdminAction::AcceptOwnership, &previous_owner);
+            Ok(())
+        }
+
+        /// @dev     Method #12-3 (READ)
+        /// @return  Whether the owner is a contract, e.g. a multisig.
+        #[ink(message)]
+        pub fn owner_is_contract(&self) -> bool {
+            self.env().is_contract(&self.owner)
+        }
+
+        /// @dev     Method #12-4 (WRITE)
+        /// @param   _backup_owner: account taking over if the owner goes inactive, e.g.
+        ///          a governance contract, `None` to disable the failover.
+        /// @param   _interval: owner inactivity in ms, at least `MIN_FAILOVER_INTERVAL`.
+        /// @note    owner only. The backup owner can take over with `assume_ownership`
+        ///          once the contract has been paused and the owner performed no admin
+        ///          action for _interval.
+        #[ink(message)]
+        pub fn set_backup_owner(
+            &mut self,
+            _backup_owner: Option<AccountId>,
+            _interval: Timestamp,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if _backup_owner.is_some() && _interval < MIN_FAILOVER_INTERVAL {
+                return Err(Error::FailoverIntervalTooShort {
+                    interval: _interval,
+                    min_interval: MIN_FAILOVER_INTERVAL,
+                });
+            }
+            self.backup_owner = _backup_owner;
+            self.failover_interval = _interval;
+            self.log_admin(AdminAction::SetBackupOwner, &(_backup_owner, _interval));
+            Ok(())
+        }
+
+        /// @dev     Method #12-5 (WRITE)
+        /// @note    backup owner only. Takes over the ownership of an inactive owner,
+        ///          see `failover_at`. The backup owner has to be designated again
+        ///          by the new owner.
+        #[ink(message)]
+        pub fn assume_ownership(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if self.backup_owner != Some(caller) {
+                return Err(Error::NotOwner);
+            }
+            let failover_at = self.failover_at();
+            match failover_at {
+                Some(failover_at) if self.env().block_timestamp() >= failover_at => {}
+                _ => return Err(Error::OwnerActive { failover_at }),
+            }
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.backup_owner = None;
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: caller,
+            });
+            self.log_admin(AdminAction::AssumeOwnership, &previous_owner);
+            Ok(())
+        }
+
+        /// @dev     Method #12-6 (READ)
+        /// @return  Time from which the backup owner can take over, `None` while not
+        ///          paused or without backup owner. Moves on with each admin action
+        ///          of the owner.
+        #[ink(message)]
+        pub fn failover_at(&self) -> Option<Timestamp> {
+            if !self.paused || self.backup_owner.is_none() {
+                return None;
+            }
+            Some(self.paused_since.max(self.last_owner_action) + self.failover_interval)
+        }
+
+        /// @dev     Method #12-7 (WRITE)
+        /// @note    owner only. Proves the owner is alive without changing anything
+        ///          else, moving on `failover_at` and the last admin activity.
+        #[ink(message)]
+        pub fn heartbeat(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.log_admin(AdminAction::Heartbeat, &());
+            Ok(())
+        }
+
+        /// @dev     Method #12-8 (READ)
+        /// @return  (block of the last admin action, block of the last stake or
+        ///          claim), for dead-man switches and alerting.
+        #[ink(message)]
+        pub fn last_activity(&self) -> (BlockNumber, BlockNumber) {
+            (self.last_admin_activity, self.last_user_activity)
+        }
+
+        /// @dev     Method #12 (READ)
+        /// @return  Receiver of swept surplus.
+        #[ink(message)]
+        pub fn get_treasury(&self) -> AccountId {
+            self.settings.treasury
+        }
+
+        /// @dev     Method #12 (READ)
+        /// @return  Outstanding staked principal over all accounts.
+        #[ink(message)]
+        pub fn get_total_staked(&self) -> Balance {
+            self.ledger.principal
+        }
+
+        /// @dev     Method #13 (WRITE)
+        /// @param   _min_stake: minimal stake in whole tokens.
+        /// @param   _max_stake: maximal stake in whole tokens, `0` if unlimited.
+        /// @param   _dust_threshold: minimal partial claim in whole tokens.
+        /// @note    owner only. Limits are converted with the token decimals.
+        #[ink(message)]
+        pub fn set_limits(
+            &mut self,
+            _min_stake: Balance,
+            _max_stake: Balance,
+            _dust_threshold: Balance,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.min_stake = self.to_units(_min_stake)?;
+            self.max_stake = self.to_units(_max_stake)?;
+            self.dust_threshold = self.to_units(_dust_threshold)?;
+            self.log_admin(AdminAction::SetLimits, &(_min_stake, _max_stake, _dust_threshold));
+            Ok(())
+        }
+
+        /// @dev     Method #14 (READ)
+        /// @return  (min stake, max stake, dust threshold) in token units.
+        #[ink(message)]
+        pub fn get_limits(&self) -> (Balance, Balance, Balance) {
+            (self.min_stake, self.max_stake, self.dust_threshold)
+        }
+
+        /// @dev     Method #15 (READ)
+        /// @return  Decimals of the staked token.
+        #[ink(message)]
+        pub fn token_decimals(&self) -> u8 {
+            self.decimals
+        }
+
+        /// @dev     Method #16 (WRITE)
+        /// @param   _new_token: AccountId of the replacement ERC20 contract.
+        /// @note    owner only. Starts the timelock of `set_token`.
+        #[ink(message)]
+        pub fn propose_token(&mut self, _new_token: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            if !Self::is_erc20(_new_token) {
+                return Err(Error::InvalidToken);
+            }
+            let eta = self.env().block_timestamp() + ADMIN_TIMELOCK;
+            self.pending_token = Some((_new_token, eta));
+            self.env().emit_event(TokenProposed {
+                token: _new_token,
+                eta,
+            });
+            self.log_admin(AdminAction::ProposeToken, &_new_token);
+            Ok(())
+        }
+
+        /// @dev     Method #17 (WRITE)
+        /// @param   _new_token: AccountId proposed by `propose_token`.
+        /// @note    owner only. Replaces the staked token once the timelock elapsed,
+        ///          and only while no stake or escrow is outstanding.
+        #[ink(message)]
+        pub fn set_token(&mut self, _new_token: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            match self.pending_token {
+                Some((token, eta)) if token == _new_token => {
+                    let now = self.env().block_timestamp();
+                    if now < eta {
+                        return Err(Error::TimelockActive { now, eta });
+                    }
+                }
+                _ => return Err(Error::NotProposed),
+            }
+            if self.obligations() + self.ledger.reward_funds != 0 {
+                return Err(Error::OutstandingBalances {
+                    staked: self.ledger.principal,
+                    escrowed: self.ledger.released,
+                    rewards: self.ledger.rewards_owed + self.ledger.reward_funds,
+                });
+            }
+            let old_token = self.get_token();
+            self.token = Erc20Ref::from_account_id(_new_token);
+            self.decimals = Self::read_decimals(_new_token);
+            self.pending_token = None;
+            self.env().emit_event(TokenChanged {
+                old_token,
+                new_token: _new_token,
+            });
+            self.log_admin(AdminAction::SetToken, &_new_token);
+            Ok(())
+        }
+
+        /// @dev     Method #18 (READ)
+        /// @return  AccountId of the staked ERC20 contract.
+        #[ink(message)]
+        pub fn get_token(&self) -> AccountId {
+            self.token.to_account_id()
+        }
+
+        /// @dev     Method #18 (READ)
+        /// @return  Proposed replacement token and the time it becomes executable.
+        #[ink(message)]
+        pub fn get_pending_token(&self) -> Option<(AccountId, Timestamp)> {
+            self.pending_token
+        }
+
+        /// @dev     Method #19 (WRITE)
+        /// @param   _paused: bool
+        /// @note    owner only. Suspends or resumes staking and claiming.
+        #[ink(message)]
+        pub fn set_paused(&mut self, _paused: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if _paused && !self.paused {
+                self.paused_since = self.env().block_timestamp();
+            }
+            self.paused = _paused;
+            self.env().emit_event(PausedChanged { paused: _paused });
+            self.log_admin(AdminAction::SetPaused, &_paused);
+            Ok(())
+        }
+
+        /// @dev     Method #19-3 (WRITE)
+        /// @note    owner and guardians only. Suspends staking and claiming, only
+        ///          the owner can resume them with `set_paused`.
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.ensure_owner()
+                .or_else(|_| self.ensure_role(Role::Guardian))?;
+            if !self.paused {
+                self.paused_since = self.env().block_timestamp();
+            }
+            self.paused = true;
+            self.env().emit_event(PausedChanged { paused: true });
+            self.log_admin(AdminAction::Pause, &());
+            Ok(())
+        }
+
+        /// @dev     Method #19-1 (READ)
+        /// @param   _offset: index of the first account.
+        /// @param   _limit: maximal number of accounts.
+        /// @return  Raw entries of the stakers for incident forensics and
+        ///          off-chain reconciliation.
+        /// @note    owner only, and only while paused.
+        #[ink(message)]
+        pub fn dump_state(&self, _offset: u32, _limit: u32) -> Result<Vec<AccountDump>> {
+            self.ensure_owner()?;
+            if !self.paused {
+                return Err(Error::NotPaused);
+            }
+            let end = _offset.saturating_add(_limit).min(self.stakers.len());
+            Ok((_offset..end)
+                .filter_map(|index| self.stakers.get(index))
+                .map(|&account| AccountDump {
+                    account,
+                    staked: self.staked.get(&account).map_or(Vec::new(), |staked| staked.to_vec()),
+                    rewards: self.reward_account(account),
+                    escrow: self.escrow_of(account),
+                    boost: self.boost_of(account).0,
+                })
+                .collect())
+        }
+
+        /// @dev     Method #19-2 (READ)
+        /// @return  All contract parameters in one struct, so that deployments
+        ///          can be verified with a single query.
+        #[ink(message)]
+        pub fn get_config(&self) -> Config {
+            Config {
+                token: self.get_token(),
+                token_decimals: self.decimals,
+                owner: self.owner,
+                treasury: self.settings.treasury,
+                maturity: self.maturity_of_version(self.schedule_count - 1),
+                rounding: self.rounding,
+                withdrawal_fee_bps: self.settings.withdrawal_fee_bps,
+                min_stake: self.min_stake,
+                max_stake: self.max_stake,
+                dust_threshold: self.dust_threshold,
+                max_share_permille: self.settings.max_share_permille,
+                bonus_end_block: self.bonus_end_block.0,
+                bonus_multiplier: self.bonus_multiplier,
+                rewards_duration: self.rewards_duration,
+                rate_model: self.rate_model,
+                successor: self.successor,
+                native_rewards: self.native_rewards,
+                eoa_only: self.eoa_only,
+                router: self.settings.router,
+                paused: self.paused,
+                global_paused: self.global_paused,
+                emergency_since: self.emergency_since,
+                refund_open: self.refund_open,
+            }
+        }
+
+        /// @dev     Method #20 (READ)
+        /// @return  Health report for uptime monitors.
+        #[ink(message)]
+        pub fn health(&self) -> HealthReport {
+            let token_balance = self
+                .token_balance_of(self.env().account_id())
+                .unwrap_or_default();
+            let obligations = self.obligations();
+            HealthReport {
+                paused: self.is_paused(),
+                token_balance,
+                obligations,
+                solvency_permille: token_balance
+                    .checked_mul(1000)
+                    .and_then(|scaled| scaled.checked_div(obligations)),
+                pending_admin_actions: self.pending_token.iter().count() as u32
+                    + self.pending_owner.iter().count() as u32,
+                reward_period_finish: self.period_finish,
+                last_reward_update: self.last_update_time,
+                ledger_balanced: self.ledger.is_balanced(),
+            }
+        }
+
+        /// @dev     Method #20-1 (READ)
+        /// @return  Amounts owed to the accounts compared to the token balance held.
+        #[ink(message)]
+        pub fn obligations_report(&self) -> ObligationsReport {
+            let token_balance = self
+                .token_balance_of(self.env().account_id())
+                .unwrap_or_default();
+            ObligationsReport {
+                principal: self.ledger.principal,
+                rewards_accrued: self.ledger.rewards_owed
+                    + self.emitted_since_update()
+                    + self.ledger.boosted,
+                native_rewards: self.native_rewards,
+                escrow: self.ledger.released,
+                fees: self.ledger.treasury,
+                token_balance,
+                invested: self.invested,
+                shortfall: self.obligations().saturating_sub(token_balance),
+                timestamp: self.env().block_timestamp(),
+            }
+        }
+
+        /// @dev     Method #20-2 (WRITE)
+        /// @note    anyone, at most once per `REPORT_INTERVAL`. Emits the obligations
+        ///          report for transparency dashboards.
+        #[ink(message)]
+        pub fn publish_report(&mut self) -> Result<()> {
+            let now = self.env().block_timestamp();
+            let next_report = self.last_report + REPORT_INTERVAL;
+            if self.last_report != 0 && now < next_report {
+                return Err(Error::ReportTooEarly { next_report });
+            }
+            self.last_report = now;
+            let report = self.obligations_report();
+            self.env().emit_event(ObligationsReported { report });
+            Ok(())
+        }
+
+        /// @dev     Method #20-3 (READ)
+        /// @return  Balances of the internal double-entry books.
+        #[ink(message)]
+        pub fn get_ledger(&self) -> Ledger {
+            self.ledger
+        }
+
+        /// @dev     Method #21 (WRITE)
+        /// @param   _rewards_duration: length of the next reward periods in ms.
+        /// @note    owner only. Only possible once the current period finished.
+        #[ink(message)]
+        pub fn set_rewards_duration(&mut self, _rewards_duration: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            if _rewards_duration == 0 {
+                return Err(Error::ZeroDuration);
+            }
+            if self.env().block_timestamp() < self.period_finish {
+                return Err(Error::RewardPeriodActive {
+                    period_finish: self.period_finish,
+                });
+            }
+            self.rewards_duration = _rewards_duration;
+            self.log_admin(AdminAction::SetRewardsDuration, &_rewards_duration);
+            Ok(())
+        }
+
+        /// @dev     Method #22 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    owner only. Pulls _amount of caller's ERC20 token into the reward pool
+        ///          and starts a new reward period.
+        #[ink(message)]
+        pub fn fund_rewards(&mut self, _amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_not_emergency()?;
+            self.ensure_reward_currency(false)?;
+            let caller = self.env().caller();
+            self.ensure_reward_rate(_amount)?;
+            self.pull_reward(caller, _amount)?;
+            self.start_reward_period(_amount);
+            self.env().emit_event(RewardsFunded {
+                funder: caller,
+                amount: _amount,
+            });
+            self.log_admin(AdminAction::FundRewards, &_amount);
+            Ok(())
+        }
+
+        /// @dev     Method #22-1 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    reward injectors only. Pulls _amount of injector's ERC20 token
+        ///          into the reward pool and starts a new reward period, rolling
+        ///          the rewards not emitted yet into it.
+        #[ink(message)]
+        pub fn notify_reward(&mut self, _amount: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::RewardInjector)?;
+            self.ensure_not_emergency()?;
+            self.ensure_reward_currency(false)?;
+            self.ensure_reward_rate(_amount)?;
+            self.pull_reward(caller, _amount)?;
+            self.start_reward_period(_amount);
+            self.env().emit_event(RewardNotified {
+                injector: caller,
+                amount: _amount,
+                reward_rate: self.reward_rate,
+                period_finish: self.period_finish,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #22-2 (WRITE)
+        /// @param   _native_rewards: bool
+        /// @note    owner only. Switches the reward pool between the staked token and
+        ///          the native currency. Only possible without outstanding rewards.
+        #[ink(message)]
+        pub fn set_native_rewards(&mut self, _native_rewards: bool) -> Result<()> {
+            self.ensure_owner()?;
+            let rewards =
+                self.ledger.reward_funds + self.ledger.rewards_owed + self.ledger.boosted;
+            if rewards != 0 {
+                return Err(Error::OutstandingBalances {
+                    staked: self.ledger.principal,
+                    escrowed: self.ledger.released,
+                    rewards,
+                });
+            }
+            self.native_rewards = _native_rewards;
+            self.log_admin(AdminAction::SetNativeRewards, &_native_rewards);
+            Ok(())
+        }
+
+        /// @dev     Method #22-3 (WRITE, payable)
+        /// @note    owner and reward injectors only. Adds the transferred native
+        ///          currency to the reward pool and starts a new reward period.
+        ///          The value is refunded if the funding is rejected.
+        #[ink(message, payable)]
+        pub fn fund_native_rewards(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            let amount = self.env().transferred_value();
+            let checked = self
+                .ensure_owner()
+                .or_else(|_| self.ensure_role(Role::RewardInjector))
+                .and_then(|_| self.ensure_not_emergency())
+                .and_then(|_| self.ensure_reward_currency(true));
+            if let Err(error) = checked.and_then(|_| self.ensure_reward_rate(amount)) {
+                // Returning an error doesn't revert the transfer of the value.
+                self.env()
+                    .transfer(caller, amount)
+                    .map_err(|_| Error::TransferFailed { amount })?;
+                return Err(error);
+            }
+            self.start_reward_period(amount);
+            self.env().emit_event(RewardsFunded {
+                funder: caller,
+                amount,
+            });
+            self.log_admin(AdminAction::FundNativeRewards, &amount);
+            Ok(())
+        }
+
+        /// Rejects funding the reward pool in the currency it doesn't hold.
+        fn ensure_reward_currency(&self, native_rewards: bool) -> Result<()> {
+            if self.native_rewards != native_rewards {
+                return Err(Error::WrongRewardCurrency {
+                    native_rewards: self.native_rewards,
+                });
+            }
+            Ok(())
+        }
+
+        /// @dev     Method #23 (WRITE)
+        /// @param   _min_expected_out: nothing is claimed if less would be paid out.
+        /// @return  Claimed rewards.
+        /// @note    pay out caller's accrued rewards.
+        #[ink(message)]
+        pub fn claim_rewards(&mut self, _min_expected_out: Option<Balance>) -> Result<Balance> {
+            let result = self.claim_rewards_impl(_min_expected_out);
+            self.report_rejection(ink_lang::selector_bytes!("claim_rewards"), result)
+        }
+
+        /// Body of `claim_rewards`.
+        fn claim_rewards_impl(&mut self, _min_expected_out: Option<Balance>) -> Result<Balance> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            let info = self.reward_account(caller);
+            self.update_rewards(caller, info.staked, info.weight);
+            let mut account = self.reward_account(caller);
+            let amount = account.pending;
+            if amount == 0 {
+                return Err(Error::NoRewards);
+            }
+            if let Some(min_expected_out) = _min_expected_out {
+                if amount < min_expected_out {
+                    return Err(Error::InsufficientOutput {
+                        out: amount,
+                        min_expected_out,
+                    });
+                }
+            }
+            let payout = self.payout_address_of(caller);
+            self.pay_reward(
+                self.native_rewards,
+                self.reward_token,
+                payout,
+                amount,
+                Book::RewardsOwed,
+            )?;
+            account.pending = 0;
+            self.reward_accounts.insert(caller, account);
+            self.rewards_paid += amount;
+            self.last_user_activity = self.env().block_number();
+            self.env().emit_event(RewardsClaimed {
+                account: caller,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// @dev     Method #24 (READ)
+        /// @param   addr: AccountId
+        /// @return  Rewards _addr could claim now.
+        #[ink(message)]
+        pub fn pending_rewards(&self, _addr: AccountId) -> Balance {
+            let (account, _) = self.split_legacy(self.reward_account(_addr));
+            math::pending(
+                account.pending,
+                account.weight,
+                self.reward_per_token(),
+                account.reward_debt,
+            )
+        }
+
+        /// @dev     Method #24-1 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Raw accumulator values and _addr's reward accounting, to check
+        ///          `pending_rewards` against off-chain calculations.
+        #[ink(message)]
+        pub fn audit_rewards(&self, _addr: AccountId) -> RewardAudit {
+            let account = self.reward_account(_addr);
+            RewardAudit {
+                reward_per_token_stored: self.reward_per_token_stored,
+                reward_per_token: self.reward_per_token(),
+                last_update_time: self.last_update_time,
+                total_weight: self.total_weight,
+                weight: account.weight,
+                reward_debt: account.reward_debt,
+                settled: account.pending,
+                pending: self.pending_rewards(_addr),
+                last_update: account.last_update,
+            }
+        }
+
+        /// @dev     Method #25 (READ)
+        /// @return  (rewards emitted per second, end of the reward period, period length).
+        #[ink(message)]
+        pub fn get_reward_rate(&self) -> (Balance, Timestamp, Timestamp) {
+            (self.reward_rate, self.period_finish, self.rewards_duration)
+        }
+
+        /// @dev     Method #25 (READ)
+        /// @return  Rewards accumulated per reward weight unit, scaled by 10^12.
+        #[ink(message)]
+        pub fn reward_per_token(&self) -> Balance {
+            math::reward_per_token(
+                self.reward_per_token_stored,
+                self.emitted_since_update(),
+                self.total_weight,
+            )
+        }
+
+        /// @dev     Method #24-2 (WRITE)
+        /// @param   _new_token: new ERC20 reward token, the staked token if `None`.
+        /// @param   _grace_period: time in ms to claim the legacy rewards.
+        /// @note    owner only, once the reward period finished. Finalizes the old
+        ///          accumulator, snapshots the owed rewards as legacy rewards and
+        ///          continues the accrual in _new_token. Unemitted rewards and legacy
+        ///          rewards of a previous migration left unclaimed go to the treasury.
+        #[ink(message)]
+        pub fn migrate_reward_token(
+            &mut self,
+            _new_token: Option<AccountId>,
+            _grace_period: Timestamp,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_reward_currency(false)?;
+            let now = self.env().block_timestamp();
+            if now < self.period_finish {
+                return Err(Error::RewardPeriodActive {
+                    period_finish: self.period_finish,
+                });
+            }
+            if let Some(legacy) = self.legacy_rewards {
+                if now <= legacy.claim_deadline {
+                    return Err(Error::LegacyRewardsActive {
+                        claim_deadline: legacy.claim_deadline,
+                    });
+                }
+            }
+            if !_new_token.map_or(true, Self::is_erc20) {
+                return Err(Error::InvalidToken);
+            }
+            self.update_pool();
+            let expired = self.legacy_rewards;
+            let old_token = self.reward_token;
+            let unemitted = self.ledger.reward_funds;
+            // Paid before the snapshot so a failed payout leaves the old
+            // reward token in place.
+            let treasury = self.settings.treasury;
+            if let Some(expired) = expired.filter(|expired| expired.owed > 0) {
+                self.pay_reward(false, expired.token, treasury, expired.owed, Book::Holdings)?;
+                self.legacy_rewards = None;
+            }
+            if unemitted > 0 {
+                self.pay_reward(false, old_token, treasury, unemitted, Book::RewardFunds)?;
+            }
+            let claim_deadline = now + _grace_period;
+            self.legacy_rewards = Some(LegacyRewards {
+                token: old_token,
+                reward_per_token: self.reward_per_token_stored,
+                migrated_at: now,
+                claim_deadline,
+                owed: self.ledger.rewards_owed + self.ledger.boosted,
+            });
+            // The owed rewards leave the books for the legacy snapshot.
+            let (owed, boosted) = (self.ledger.rewards_owed, self.ledger.boosted);
+            let holdings = self.reward_holdings();
+            self.ledger.post(Book::RewardsOwed, holdings, owed);
+            self.ledger.post(Book::Boosted, holdings, boosted);
+            self.reward_epoch += 1;
+            self.reward_token = _new_token;
+            self.env().emit_event(RewardTokenMigrated {
+                old_token,
+                new_token: _new_token,
+                claim_deadline,
+            });
+            self.log_admin(
+                AdminAction::MigrateRewardToken,
+                &(_new_token, _grace_period),
+            );
+            Ok(())
+        }
+
+        /// @dev     Method #24-3 (WRITE)
+        /// @return  Legacy rewards paid out in the old reward token.
+        /// @note    only until the end of the grace period.
+        #[ink(message)]
+        pub fn claim_legacy_rewards(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            let legacy = self.legacy_rewards.ok_or(Error::NoRewards)?;
+            if self.env().block_timestamp() > legacy.claim_deadline {
+                return Err(Error::GracePeriodOver {
+                    claim_deadline: legacy.claim_deadline,
+                });
+            }
+            let info = self.reward_account(caller);
+            self.update_rewards(caller, info.staked, self.weight_of(caller));
+            let amount = self.legacy_owed.get(&caller).copied().unwrap_or(0);
+            if amount == 0 {
+                return Err(Error::NoRewards);
+            }
+            let payout = self.payout_address_of(caller);
+            self.pay_reward(false, legacy.token, payout, amount, Book::Holdings)?;
+            self.legacy_owed.take(&caller);
+            self.legacy_rewards = Some(LegacyRewards {
+                owed: legacy.owed - amount,
+                ..legacy
+            });
+            Ok(amount)
+        }
+
+        /// @dev     Method #24-4 (READ)
+        /// @param   _addr: AccountId
+        /// @return  (legacy rewards _addr can claim, old reward token, end of the
+        ///          grace period), `None` without migration.
+        #[ink(message)]
+        pub fn legacy_rewards_of(
+            &self,
+            _addr: AccountId,
+        ) -> Option<(Balance, Option<AccountId>, Timestamp)> {
+            let legacy = self.legacy_rewards?;
+            let (_, unsettled) = self.split_legacy(self.reward_account(_addr));
+            let boost = self.legacy_boost(_addr).map_or(0, |boost| boost.amount);
+            let owed = self.legacy_owed.get(&_addr).copied().unwrap_or(0);
+            Some((owed + unsettled + boost, legacy.token, legacy.claim_deadline))
+        }
+
+        /// @dev     Method #24-5 (READ)
+        /// @return  ERC20 token rewards are paid in, the staked token if `None`.
+        #[ink(message)]
+        pub fn get_reward_token(&self) -> Option<AccountId> {
+            self.reward_token
+        }
+
+        /// Splits the rewards `info` accrued before the last reward token
+        /// migration off. Returns the updated accounting and the split amount.
+        fn split_legacy(&self, mut info: RewardAccount) -> (RewardAccount, Balance) {
+            let mut legacy = 0;
+            if info.reward_epoch < self.reward_epoch {
+                if let Some(snapshot) = self.legacy_rewards {
+                    let debt = math::accumulated(info.weight, snapshot.reward_per_token);
+                    legacy = info.pending + debt.saturating_sub(info.reward_debt);
+                    info.pending = 0;
+                    info.reward_debt = debt;
+                }
+                info.reward_epoch = self.reward_epoch;
+            }
+            (info, legacy)
+        }
+
+        /// Returns the boost of `account` locked before the last reward token
+        /// migration, owed in the old reward token.
+        fn legacy_boost(&self, account: AccountId) -> Option<Boost> {
+            let migrated_at = self.legacy_rewards?.migrated_at;
+            self.boosts
+                .get(&account)
+                .copied()
+                .filter(|boost| boost.locked_at < migrated_at)
+        }
+
+        /// Pulls `amount` of the reward token from `from` into the reward pool.
+        fn pull_reward(&mut self, from: AccountId, amount: Balance) -> Result<()> {
+            let me = self.env().account_id();
+            match self.reward_token {
+                None => self.transfer_with_signature(from, me, amount),
+                Some(token) => Self::token_op(
+                    token,
+                    ink_lang::selector_bytes!("transfer_from"),
+                    (from, me, amount),
+                    amount,
+                ),
+            }
+        }
+
+        /// Pays `amount` of rewards owed in `book` to `to` in the native currency or
+        /// in `token`, the staked token if `None`. Staked token payouts are escrowed
+        /// on failure. Rewards kept outside the books are paid from `Book::Holdings`.
+        fn pay_reward(
+            &mut self,
+            native: bool,
+            token: Option<AccountId>,
+            to: AccountId,
+            amount: Balance,
+            book: Book,
+        ) -> Result<()> {
+            if native {
+                self.env()
+                    .transfer(to, amount)
+                    .map_err(|_| Error::TransferFailed { amount })?;
+            } else {
+                match token {
+                    None => {
+                        self.pay_out(self.env().account_id(), to, amount, book);
+                        return Ok(());
+                    }
+                    Some(token) => Self::token_op(
+                        token,
+                        ink_lang::selector_bytes!("transfer"),
+                        (to, amount),
+                        amount,
+                    )?,
+                }
+            }
+            let holdings = if native { Book::NativeHoldings } else { Book::Holdings };
+            self.ledger.post(book, holdings, amount);
+            Ok(())
+        }
+
+        /// Returns the asset book holding the rewards.
+        fn reward_holdings(&self) -> Book {
+            if self.native_rewards {
+                Book::NativeHoldings
+            } else {
+                Book::Holdings
+            }
+        }
+
+        /// Returns the reward accounting of `account`.
+        fn reward_account(&self, account: AccountId) -> RewardAccount {
+            self.reward_accounts.get(&account).copied().unwrap_or_default()
+        }
+
+        /// @dev     Method #41 (WRITE)
+        /// @note    owner only. Irreversibly enters the emergency mode: unlock schedules,
+        ///          fees and reward emission stay at their current values, staking is
+        ///          disabled and only the amounts unstakable now can be withdrawn.
+        #[ink(message)]
+        pub fn enter_emergency(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_not_emergency()?;
+            self.update_pool();
+            let frozen_at = self.env().block_timestamp();
+            self.emergency_since = Some(frozen_at);
+            self.env().emit_event(EmergencyEntered { frozen_at });
+            self.log_admin(AdminAction::EnterEmergency, &frozen_at);
+            Ok(())
+        }
+
+        /// @dev     Method #41-1 (WRITE)
+        /// @return  Whether the vote opened the emergency refund.
+        /// @note    stakers only. Votes for the emergency refund with caller's staked
+        ///          principal, which keeps counting as it changes until the vote ends.
+        ///          Once the votes exceed `REFUND_QUORUM_PERCENT` of the staked
+        ///          principal within `REFUND_VOTE_WINDOW` of the first vote, all locks
+        ///          are voided, the contract enters the emergency mode and everyone
+        ///          can withdraw their whole principal without fee, even while paused.
+        ///          A vote not reaching the quorum in time expires, the next vote
+        ///          starting a new one.
+        #[ink(message)]
+        pub fn vote_refund(&mut self) -> Result<bool> {
+            let caller = self.env().caller();
+            if self.refund_open {
+                return Err(Error::RefundOpen);
+            }
+            let staked = self.reward_account(caller).staked;
+            if staked == 0 {
+                return Err(Error::NotStaker);
+            }
+            let now = self.env().block_timestamp();
+            if self.refund_round == 0 || now > self.refund_round_start + REFUND_VOTE_WINDOW {
+                self.refund_round += 1;
+                self.refund_round_start = now;
+                self.refund_votes_total = 0;
+            }
+            let voted_round = self.refund_votes.get(&caller).map(|&(round, _)| round);
+            if voted_round == Some(self.refund_round) {
+                return Err(Error::AlreadyVoted);
+            }
+            self.refund_votes.insert(caller, (self.refund_round, staked));
+            self.refund_votes_total += staked;
+            let total_staked = self.ledger.principal;
+            if self.refund_votes_total * 100 <= total_staked * REFUND_QUORUM_PERCENT {
+                return Ok(false);
+            }
+            self.refund_open = true;
+            if self.emergency_since.is_none() {
+                self.update_pool();
+                self.emergency_since = Some(now);
+                self.env().emit_event(EmergencyEntered { frozen_at: now });
+            }
+            self.env().emit_event(RefundOpened {
+                votes: self.refund_votes_total,
+                total_staked,
+            });
+            Ok(true)
+        }
+
+        /// @dev     Method #41-2 (READ)
+        /// @return  (principal voted in the current refund vote, end of the vote,
+        ///          whether the refund is open).
+        #[ink(message)]
+        pub fn refund_vote(&self) -> (Balance, Timestamp, bool) {
+            let end = self.refund_round_start + REFUND_VOTE_WINDOW;
+            let votes = if self.refund_round == 0 || self.env().block_timestamp() > end {
+                0
+            } else {
+                self.refund_votes_total
+            };
+            (votes, end, self.refund_open)
+        }
+
+        /// Updates the principal counted for the refund vote of `account` if it
+        /// voted in the current vote.
+        fn sync_refund_vote(&mut self, account: AccountId, staked: Balance) {
+            if self.refund_open {
+                return;
+            }
+            if let Some(&(round, counted)) = self.refund_votes.get(&account) {
+                if round == self.refund_round {
+                    self.refund_votes_total = self.refund_votes_total - counted + staked;
+                    self.refund_votes.insert(account, (round, staked));
+                }
+            }
+        }
+
+        /// @dev     Method #42 (READ)
+        /// @return  Time the emergency mode was entered, `None` if not in emergency.
+        #[ink(message)]
+        pub fn emergency_since(&self) -> Option<Timestamp> {
+            self.emergency_since
+        }
+
+        /// @dev     Method #43 (WRITE)
+        /// @param   _guardian: guardian account or social recovery contract.
+        /// @param   _delay: challenge delay in ms.
+        /// @note    register caller's recovery setup, replacing any previous one.
+        #[ink(message)]
+        pub fn set_recovery(&mut self, _guardian: AccountId, _delay: Timestamp) -> Result<()> {
+            if _delay < MIN_RECOVERY_DELAY {
+                return Err(Error::RecoveryDelayTooShort {
+                    delay: _delay,
+                    min_delay: MIN_RECOVERY_DELAY,
+                });
+            }
+            self.recoveries.insert(
+                self.env().caller(),
+                Recovery {
+                    guardian: _guardian,
+                    delay: _delay,
+                    pending: None,
+                },
+            );
+            Ok(())
+        }
+
+        /// @dev     Method #44 (WRITE)
+        /// @param   _account: AccountId whose key has been lost.
+        /// @param   _new_account: AccountId receiving the entries.
+        /// @note    guardian only. Starts the challenge delay.
+        #[ink(message)]
+        pub fn initiate_recovery(
+            &mut self,
+            _account: AccountId,
+            _new_account: AccountId,
+        ) -> Result<()> {
+            let mut recovery = self.ensure_guardian(_account)?;
+            let eta = self.env().block_timestamp() + recovery.delay;
+            recovery.pending = Some((_new_account, eta));
+            self.recoveries.insert(_account, recovery);
+            self.env().emit_event(RecoveryInitiated {
+                account: _account,
+                new_account: _new_account,
+                eta,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #45 (WRITE)
+        /// @note    cancel a recovery of caller's account initiated by the guardian.
+        #[ink(message)]
+        pub fn cancel_recovery(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            let recovery = self.recoveries.get_mut(&caller).ok_or(Error::NotProposed)?;
+            recovery.pending = None;
+            Ok(())
+        }
+
+        /// @dev     Method #46 (WRITE)
+        /// @param   _account: AccountId whose key has been lost.
+        /// @note    guardian only. Moves the stakes, rewards, boost and escrow of
+        ///          _account to the new account once the challenge delay elapsed.
+        #[ink(message)]
+        pub fn execute_recovery(&mut self, _account: AccountId) -> Result<()> {
+            let recovery = self.ensure_guardian(_account)?;
+            let (new_account, eta) = recovery.pending.ok_or(Error::NotProposed)?;
+            let now = self.env().block_timestamp();
+            if now < eta {
+                return Err(Error::TimelockActive { now, eta });
+            }
+            if self.staked.contains_key(&new_account) || self.boosts.contains_key(&new_account) {
+                return Err(Error::AccountInUse);
+            }
+            // Settle the rewards of the lost account before moving its weight.
+            self.update_rewards(_account, 0, 0);
+            let pending = self
+                .reward_accounts
+                .take(&_account)
+                .map_or(0, |info| info.pending);
+            if let Some(staked) = self.staked.take(&_account) {
+                self.staked.insert(new_account, staked);
+                self.remove_staker(_account);
+                self.add_staker(new_account);
+            }
+            if let Some(boost) = self.boosts.take(&_account) {
+                self.boosts.insert(new_account, boost);
+            }
+            if let Some(escrowed) = self.escrow.take(&_account) {
+                let total = self.escrow_of(new_account) + escrowed;
+                self.escrow.insert(new_account, total);
+            }
+            // Payout addresses set with the lost key are not trusted.
+            self.payout_addresses.take(&_account);
+            self.pending_payouts.take(&_account);
+            if let Some(owed) = self.legacy_owed.take(&_account) {
+                let total = self.legacy_owed.get(&new_account).copied().unwrap_or(0) + owed;
+                self.legacy_owed.insert(new_account, total);
+            }
+            let staked = self
+                .staked
+                .get(&new_account)
+                .map_or(0, |staked| staked.iter().map(Stake::remaining).sum());
+            self.update_rewards(new_account, staked, self.weight_of(new_account));
+            let mut info = self.reward_account(new_account);
+            info.pending += pending;
+            self.reward_accounts.insert(new_account, info);
+            self.recoveries.take(&_account);
+            self.env().emit_event(AccountRecovered {
+                account: _account,
+                new_account,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #47 (READ)
+        /// @param   addr: AccountId
+        /// @return  Recovery setup of _addr.
+        #[ink(message)]
+        pub fn recovery_of(&self, _addr: AccountId) -> Option<Recovery> {
+            self.recoveries.get(&_addr).copied()
+        }
+
+        fn ensure_guardian(&self, account: AccountId) -> Result<Recovery> {
+            match self.recoveries.get(&account) {
+                Some(recovery) if recovery.guardian == self.env().caller() => Ok(*recovery),
+                _ => Err(Error::NotGuardian),
+            }
+        }
+
+        fn ensure_not_emergency(&self) -> Result<()> {
+            if self.emergency_since.is_some() {
+                return Err(Error::Emergency);
+            }
+            Ok(())
+        }
+
+        /// Rejects a call that lingered in the pool past the caller's `deadline`.
+        fn ensure_deadline(&self, deadline: Option<Timestamp>) -> Result<()> {
+            let now = self.env().block_timestamp();
+            match deadline {
+                Some(deadline) if now > deadline => Err(Error::DeadlineExpired { deadline, now }),
+                _ => Ok(()),
+            }
+        }
+
+        /// Returns the time schedules are evaluated at: the current block
+        /// timestamp, frozen when the emergency mode was entered.
+        fn now(&self) -> Timestamp {
+            let now = self.env().block_timestamp();
+            self.emergency_since.map_or(now, |frozen_at| now.min(frozen_at))
+        }
+
+        /// Returns the end of the accrual window: now, or the end of the period.
+        fn last_time_reward_applicable(&self) -> Timestamp {
+            self.now().min(self.period_finish)
+        }
+
+        /// Returns the rewards accrued since `last_update_time`, funded or not.
+        ///
+        /// # Note
+        ///
+        /// Stake changes update the pool before `total_staked` changes, so the
+        /// utilization since the last update is the current one.
+        fn accrued_since_update(&self) -> Balance {
+            let elapsed = self
+                .last_time_reward_applicable()
+                .saturating_sub(self.last_update_time);
+            math::mul_div(self.effective_reward_rate(), elapsed.into(), 1000, Rounding::Floor)
+        }
+
+        /// Returns the rewards emitted since `last_update_time`, capped at the
+        /// funded rewards.
+        fn emitted_since_update(&self) -> Balance {
+            self.accrued_since_update().min(self.ledger.reward_funds)
+        }
+
+        /// @dev     Method #25-3 (WRITE)
+        /// @param   _target_tvl: staked principal at which the base rate applies, `0` to disable.
+        /// @param   _min_permille: lower bound of the scaling in per mille.
+        /// @param   _max_permille: upper bound of the scaling in per mille.
+        /// @note    owner only. Rewards accrued so far use the previous model.
+        #[ink(message)]
+        pub fn set_rate_model(
+            &mut self,
+            _target_tvl: Balance,
+            _min_permille: u32,
+            _max_permille: u32,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if _min_permille > _max_permille {
+                return Err(Error::InvalidRateModel {
+                    min_permille: _min_permille,
+                    max_permille: _max_permille,
+                });
+            }
+            self.update_pool();
+            self.rate_model = RateModel {
+                target_tvl: _target_tvl,
+                min_permille: _min_permille,
+                max_permille: _max_permille,
+            };
+            self.log_admin(
+                AdminAction::SetRateModel,
+                &(_target_tvl, _min_permille, _max_permille),
+            );
+            Ok(())
+        }
+
+        /// @dev     Method #25-4 (READ)
+        /// @return  Rewards emitted per second at the current utilization.
+        #[ink(message)]
+        pub fn effective_reward_rate(&self) -> Balance {
+            let model = self.rate_model;
+            if model.target_tvl == 0 {
+                return self.reward_rate;
+            }
+            let principal = self.ledger.principal;
+            let permille = if principal == 0 {
+                model.max_permille.into()
+            } else {
+                math::mul_div(model.target_tvl, 1000, principal, Rounding::Floor)
+                    .clamp(model.min_permille.into(), model.max_permille.into())
+            };
+            math::mul_div(self.reward_rate, permille, 1000, Rounding::Floor)
+        }
+
+        /// @dev     Method #25-1 (WRITE)
+        /// @param   _min_reward_rate: Balance
+        /// @param   _max_reward_rate: Balance, `0` if unbounded.
+        /// @note    owner only. Rejects reward periods emitting outside the band,
+        ///          preventing fat-finger emissions draining the reward pool.
+        #[ink(message)]
+        pub fn set_reward_rate_bounds(
+            &mut self,
+            _min_reward_rate: Balance,
+            _max_reward_rate: Balance,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.min_reward_rate = _min_reward_rate;
+            self.settings.max_reward_rate = _max_reward_rate;
+            self.log_admin(
+                AdminAction::SetRewardRateBounds,
+                &(_min_reward_rate, _max_reward_rate),
+            );
+            Ok(())
+        }
+
+        /// @dev     Method #25-2 (READ)
+        /// @return  (min reward rate, max reward rate), `0` max if unbounded.
+        #[ink(message)]
+        pub fn get_reward_rate_bounds(&self) -> (Balance, Balance) {
+            (self.settings.min_reward_rate, self.settings.max_reward_rate)
+        }
+
+        /// @dev     Method #25-5 (WRITE)
+        /// @param   _price_oracle: contract exposing `price()`, the quoted value of a whole
+        ///          reward token, `None` to disable the targeting.
+        /// @param   _target_value: quoted value of the rewards to emit per second.
+        /// @param   _retarget_epoch: minimal time between two recalculations in ms.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_price_oracle(
+            &mut self,
+            _price_oracle: Option<AccountId>,
+            _target_value: Balance,
+            _retarget_epoch: Timestamp,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.price_oracle = _price_oracle;
+            self.target_value = _target_value;
+            self.retarget_epoch = _retarget_epoch;
+            self.log_admin(
+                AdminAction::SetPriceOracle,
+                &(_price_oracle, _target_value, _retarget_epoch),
+            );
+            Ok(())
+        }
+
+        /// @dev     Method #25-6 (WRITE)
+        /// @return  The recalculated reward rate.
+        /// @note    anyone, once per epoch. Sets the reward rate so that the rewards
+        ///          emitted per second approximate the target value at the oracle
+        ///          price, within the owner-set bounds. The funded rewards last
+        ///          shorter or longer accordingly.
+        #[ink(message)]
+        pub fn retarget_reward_rate(&mut self) -> Result<Balance> {
+            let oracle = self.price_oracle.ok_or(Error::NoOracle)?;
+            self.ensure_not_emergency()?;
+            let now = self.env().block_timestamp();
+            let next_retarget = self.last_retarget + self.retarget_epoch;
+            if self.last_retarget != 0 && now < next_retarget {
+                return Err(Error::EpochNotElapsed { next_retarget });
+            }
+            if now >= self.period_finish {
+                return Err(Error::NoRewards);
+            }
+            let price = ink_lang::selector_bytes!("price");
+            let price = Self::call_contract::<_, Balance>(oracle, price, (), 0)
+                .ok()
+                .filter(|&price| price > 0)
+                .ok_or(Error::OracleFailed)?;
+            let mut reward_rate =
+                math::mul_div(self.target_value, self.to_units(1)?, price, Rounding::Floor)
+                    .max(self.settings.min_reward_rate);
+            if self.settings.max_reward_rate > 0 {
+                reward_rate = reward_rate.min(self.settings.max_reward_rate);
+            }
+            self.update_pool();
+            self.reward_rate = reward_rate;
+            self.period_finish = if reward_rate == 0 {
+                now
+            } else {
+                let funds = self.ledger.reward_funds;
+                let duration = math::mul_div(funds, 1000, reward_rate, Rounding::Floor);
+                now + duration as Timestamp
+            };
+            self.last_retarget = now;
+            self.env().emit_event(RewardRateRecalculated {
+                price,
+                reward_rate,
+                period_finish: self.period_finish,
+            });
+            Ok(reward_rate)
+        }
+
+        /// Returns the reward rate of a period emitting `funds` over `rewards_duration`.
+        fn period_reward_rate(&self, funds: Balance) -> Balance {
+            math::mul_div(funds, 1000, self.rewards_duration.into(), Rounding::Floor)
+        }
+
+        /// Rejects adding `amount` if the new reward period would emit outside
+        /// the owner-set band.
+        fn ensure_reward_rate(&mut self, amount: Balance) -> Result<()> {
+            self.update_pool();
+            let reward_rate = self.period_reward_rate(self.ledger.reward_funds + amount);
+            let (min_reward_rate, max_reward_rate) = self.get_reward_rate_bounds();
+            if reward_rate < min_reward_rate
+                || (max_reward_rate > 0 && reward_rate > max_reward_rate)
+            {
+                return Err(Error::RewardRateOutOfBounds {
+                    reward_rate,
+                    min_reward_rate,
+                    max_reward_rate,
+                });
+            }
+            Ok(())
+        }
+
+        /// Starts a new reward period of `rewards_duration` emitting `amount`
+        /// plus the rewards of the current period not emitted yet.
+        fn start_reward_period(&mut self, amount: Balance) {
+            self.update_pool();
+            let now = self.env().block_timestamp();
+            self.ledger.post(self.reward_holdings(), Book::RewardFunds, amount);
+            self.reward_rate = self.period_reward_rate(self.ledger.reward_funds);
+            self.last_update_time = now;
+            self.period_finish = now + self.rewards_duration;
+        }
+
+        /// Accumulates the rewards emitted since `last_update_time`.
+        ///
+        /// # Note
+        ///
+        /// Rewards are accrued lazily on interaction, so the cost is O(1)
+        /// regardless of the number of stakers. Rewards emitted while nothing is
+        /// staked stay in `reward_funds` and roll into the next period.
+        fn update_pool(&mut self) {
+            if self.total_weight > 0 {
+                let accrued = self.accrued_since_update();
+                let reward = accrued.min(self.ledger.reward_funds);
+                self.reward_per_token_stored = self.reward_per_token();
+                self.ledger.post(Book::RewardFunds, Book::RewardsOwed, reward);
+                if accrued > reward {
+                    // Suspend the accrual until the next funding instead of
+                    // owing rewards the contract can't pay.
+                    self.env().emit_event(RewardsExhausted {
+                        accrued,
+                        available: reward,
+                    });
+                    self.reward_rate = 0;
+                    self.period_finish = self.last_time_reward_applicable();
+                }
+            }
+            self.last_update_time = self.last_time_reward_applicable();
+            self.record_history();
+        }
+
+        /// Takes the daily checkpoint unless already taken today.
+        fn record_history(&mut self) {
+            let day = (self.env().block_timestamp() / HISTORY_PERIOD) as u32;
+            if self.history_len > 0 {
+                let last = (self.history_len - 1) % MAX_HISTORY;
+                if self.history.get(&last).map_or(false, |entry| entry.day >= day) {
+                    return;
+                }
+            }
+            self.history.insert(
+                self.history_len % MAX_HISTORY,
+                HistoryEntry {
+                    day,
+                    total_staked: self.ledger.principal,
+                    rewards_paid: self.rewards_paid,
+                },
+            );
+            self.history_len += 1;
+        }
+
+        /// @dev     Method #25-7 (READ)
+        /// @param   _offset: index of the first checkpoint among the kept ones.
+        /// @param   _limit: maximal number of checkpoints.
+        /// @return  Kept daily checkpoints of the staked principal and the paid
+        ///          rewards, oldest first.
+        #[ink(message)]
+        pub fn history(&self, _offset: u32, _limit: u32) -> Vec<HistoryEntry> {
+            let first = self.history_len.saturating_sub(MAX_HISTORY);
+            let start = first.saturating_add(_offset);
+            let end = start.saturating_add(_limit).min(self.history_len);
+            (start..end)
+                .filter_map(|id| self.history.get(&(id % MAX_HISTORY)).copied())
+                .collect()
+        }
+
+        /// Settles the rewards of `account` and sets its principal to `staked`
+        /// and its reward weight to `weight`.
+        fn update_rewards(&mut self, account: AccountId, staked: Balance, weight: Balance) {
+            self.update_pool();
+            let (mut info, mut legacy) = self.split_legacy(self.reward_account(account));
+            if let Some(boost) = self.legacy_boost(account) {
+                self.boosts.take(&account);
+                legacy += boost.amount;
+            }
+            if legacy > 0 {
+                let owed = self.legacy_owed.get(&account).copied().unwrap_or(0);
+                self.legacy_owed.insert(account, owed + legacy);
+            }
+            info.pending = math::pending(
+                info.pending,
+                info.weight,
+                self.reward_per_token_stored,
+                info.reward_debt,
+            );
+            if info.staked != staked {
+                self.write_checkpoint(account, staked);
+                self.sync_refund_vote(account, staked);
+            }
+            self.total_weight = self.total_weight - info.weight + weight;
+            info.staked = staked;
+            info.weight = weight;
+            info.reward_debt = math::accumulated(weight, self.reward_per_token_stored);
+            info.last_update = self.env().block_number();
+            self.reward_accounts.insert(account, info);
+        }
+
+        /// @dev     Method #25-8 (WRITE)
+        /// @param   _thresholds: ascending staked principal thresholds, tier `n`
+        ///          starting at the `n`-th threshold.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_discount_tiers(&mut self, _thresholds: Vec<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            if _thresholds.len() > MAX_DISCOUNT_TIERS
+                || _thresholds.windows(2).any(|pair| pair[0] >= pair[1])
+            {
+                return Err(Error::InvalidDiscountTiers);
+            }
+            self.log_admin(AdminAction::SetDiscountTiers, &_thresholds);
+            self.settings.discount_tiers = _thresholds;
+            Ok(())
+        }
+
+        /// @dev     Method #25-9 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Fee discount tier of _addr's staked principal, `0` below the
+        ///          first threshold.
+        /// @note    meant to be called by partner dApps granting fee discounts.
+        #[ink(message)]
+        pub fn discount_tier_of(&self, _addr: AccountId) -> u8 {
+            let staked = self.reward_account(_addr).staked;
+            self.settings.discount_tiers
+                .iter()
+                .take_while(|&&threshold| threshold <= staked)
+                .count() as u8
+        }
+
+        /// @dev     Method #25-10 (READ)
+        /// @param   _addrs: Vec<AccountId>
+        /// @return  Fee discount tiers of _addrs, in the same order.
+        #[ink(message)]
+        pub fn discount_tiers_of(&self, _addrs: Vec<AccountId>) -> Vec<u8> {
+            _addrs
+                .into_iter()
+                .map(|addr| self.discount_tier_of(addr))
+                .collect()
+        }
+
+        /// @dev     Method #26 (READ)
+        /// @param   addr: AccountId
+        /// @param   _from_block: BlockNumber
+        /// @param   _to_block: BlockNumber
+        /// @return  Time-weighted average of _addr's staked principal over
+        ///          [_from_block, _to_block), `to_block` capped at the current block.
+        #[ink(message)]
+        pub fn twab(
+            &self,
+            _addr: AccountId,
+            _from_block: BlockNumber,
+            _to_block: BlockNumber,
+        ) -> Balance {
+            let from_block = BlockNumberOf(_from_block);
+            let to_block = BlockNumberOf(_to_block.min(self.env().block_number()));
+            if to_block <= from_block {
+                return 0;
+            }
+            let checkpoints = match self.checkpoints.get(&_addr) {
+                Some(checkpoints) => checkpoints,
+                None => return 0,
+            };
+            let total = Self::cumulative_at(checkpoints, to_block)
+                - Self::cumulative_at(checkpoints, from_block);
+            (total / to_block.since(from_block)).0
+        }
+
+        /// @dev     Method #36 (READ)
+        /// @param   addr: AccountId
+        /// @param   _min: Balance
+        /// @return  Whether _addr currently stakes at least _min.
+        /// @note    Cheap check meant for cross-contract calls.
+        #[ink(message)]
+        pub fn has_min_stake(&self, _addr: AccountId, _min: Balance) -> bool {
+            self.reward_account(_addr).staked >= _min
+        }
+
+        /// @dev     Method #37 (READ)
+        /// @param   addr: AccountId
+        /// @param   _block: BlockNumber
+        /// @return  Whether _addr has been staking without interruption since _block.
+        /// @note    Cheap check meant for cross-contract calls.
+        #[ink(message)]
+        pub fn staked_since(&self, _addr: AccountId, _block: BlockNumber) -> bool {
+            let checkpoints = match self.checkpoints.get(&_addr) {
+                Some(checkpoints) => checkpoints,
+                None => return false,
+            };
+            let index = checkpoints.partition_point(|c| c.block <= BlockNumberOf(_block));
+            index > 0 && checkpoints[index - 1..].iter().all(|c| c.balance.0 > 0)
+        }
+
+        /// Returns the sum of `balance * blocks` over all blocks before `block`.
+        fn cumulative_at(checkpoints: &[Checkpoint], block: BlockNumberOf) -> TokenBlocks {
+            let index = checkpoints.partition_point(|c| c.block <= block);
+            if index == 0 {
+                return TokenBlocks::default();
+            }
+            let last = &checkpoints[index - 1];
+            last.cumulative + last.balance * block.since(last.block)
+        }
+
+        /// Records that `account` holds `balance` of principal from the current block on.
+        fn write_checkpoint(&mut self, account: AccountId, balance: Balance) {
+            let block = BlockNumberOf(self.env().block_number());
+            let balance = TokenAmount(balance);
+            let checkpoints = match self.checkpoints.get_mut(&account) {
+                Some(checkpoints) => checkpoints,
+                None => {
+                    self.checkpoints.insert(account, Vec::new());
+                    self.checkpoints.get_mut(&account).unwrap()
+                }
+            };
+            match checkpoints.last_mut() {
+                Some(last) if last.block == block => last.balance = balance,
+                Some(last) => {
+                    let cumulative = last.cumulative + last.balance * block.since(last.block);
+                    checkpoints.push(Checkpoint {
+                        block,
+                        balance,
+                        cumulative,
+                    });
+                }
+                None => checkpoints.push(Checkpoint {
+                    block,
+                    balance,
+                    cumulative: TokenBlocks::default(),
+                }),
+            }
+        }
+
+        /// Returns the reward weight of `account`: the remaining principal of
+        /// each stake scaled by its multiplier, then by the boost bonus.
+        fn weight_of(&self, account: AccountId) -> Balance {
+            if self.reward_excluded.contains_key(&account) {
+                return 0;
+            }
+            let staked = match self.staked.get(&account) {
+                Some(staked) => staked,
+                None => return 0,
+            };
+            let mut principal = 0;
+            let mut weight = 0;
+            for stake in staked.iter() {
+                principal += stake.remaining();
+                weight += math::weight(stake.remaining(), stake.multiplier);
+            }
+            let bonus = self.boost_bonus(self.boost_of(account).0, principal);
+            math::weight(weight, MULTIPLIER_BASE + bonus)
+        }
+
+        /// Returns the boost bonus in per mille of `boosted` rewards locked
+        /// against `principal`, capped at `MAX_BOOST_PERMILLE`.
+        fn boost_bonus(&self, boosted: Balance, principal: Balance) -> u32 {
+            math::boost_bonus(boosted, principal, MAX_BOOST_PERMILLE)
+        }
+
+        /// @dev     Method #33 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    lock _amount of caller's pending rewards into the boost vault.
+        ///          Each lock restarts the cooldown of the whole boost.
+        #[ink(message)]
+        pub fn lock_boost(&mut self, _amount: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            self.ensure_not_emergency()?;
+            let info = self.reward_account(caller);
+            self.update_rewards(caller, info.staked, info.weight);
+            let mut info = self.reward_account(caller);
+            if _amount == 0 || info.pending < _amount {
+                return Err(Error::NoRewards);
+            }
+            info.pending -= _amount;
+            self.reward_accounts.insert(caller, info);
+            self.ledger.post(Book::RewardsOwed, Book::Boosted, _amount);
+            let boosted = self.boost_of(caller).0 + _amount;
+            self.boosts.insert(
+                caller,
+                Boost {
+                    amount: boosted,
+                    locked_at: self.env().block_timestamp(),
+                },
+            );
+            self.update_rewards(caller, info.staked, self.weight_of(caller));
+            self.env().emit_event(BoostLocked {
+                account: caller,
+                amount: _amount,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #34 (WRITE)
+        /// @return  Unlocked amount, credited back to caller's pending rewards.
+        #[ink(message)]
+        pub fn unlock_boost(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let boost = match self.current_boost(caller) {
+                Some(boost) => boost,
+                None => return Err(Error::NoRewards),
+            };
+            let unlock_at = boost.locked_at + BOOST_COOLDOWN;
+            if self.env().block_timestamp() < unlock_at {
+                return Err(Error::BoostLocked { unlock_at });
+            }
+            self.boosts.take(&caller);
+            self.ledger.post(Book::Boosted, Book::RewardsOwed, boost.amount);
+            let info = self.reward_account(caller);
+            self.update_rewards(caller, info.staked, self.weight_of(caller));
+            let mut info = self.reward_account(caller);
+            info.pending += boost.amount;
+            self.reward_accounts.insert(caller, info);
+            self.env().emit_event(BoostUnlocked {
+                account: caller,
+                amount: boost.amount,
+            });
+            Ok(boost.amount)
+        }
+
+        /// Returns the boost of `account` locked in the current reward token.
+        fn current_boost(&self, account: AccountId) -> Option<Boost> {
+            let boost = self.boosts.get(&account).copied()?;
+            match self.legacy_boost(account) {
+                Some(_) => None,
+                None => Some(boost),
+            }
+        }
+
+        /// @dev     Method #35 (READ)
+        /// @param   addr: AccountId
+        /// @return  (locked boost amount, current boost bonus in per mille).
+        #[ink(message)]
+        pub fn boost_of(&self, _addr: AccountId) -> (Balance, u32) {
+            let boosted = self.current_boost(_addr).map_or(0, |boost| boost.amount);
+            (
+                boosted,
+                self.boost_bonus(boosted, self.reward_account(_addr).staked),
+            )
+        }
+
+        /// @dev     Method #28 (WRITE)
+        /// @param   _max_share_permille: u32, `0` if unlimited.
+        /// @note    owner only. Caps the share of the pool a single account can hold.
+        #[ink(message)]
+        pub fn set_max_share(&mut self, _max_share_permille: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.max_share_permille = _max_share_permille;
+            self.log_admin(AdminAction::SetMaxShare, &_max_share_permille);
+            Ok(())
+        }
+
+        /// @dev     Method #29 (READ)
+        /// @param   addr: AccountId
+        /// @return  Largest amount _addr can stake without exceeding the maximal share.
+        /// @note    The cap only applies once other accounts stake in the pool,
+        ///          otherwise the first staker could never deposit.
+        #[ink(message)]
+        pub fn max_stake_headroom(&self, _addr: AccountId) -> Balance {
+            let cap = Balance::from(self.settings.max_share_permille);
+            let own = self.reward_account(_addr).staked;
+            if cap == 0 || cap >= 1000 || own == self.ledger.principal {
+                return Balance::MAX;
+            }
+            // (own + x) * 1000 <= cap * (total_staked + x)
+            let allowed = cap.saturating_mul(self.ledger.principal);
+            let held = own.saturating_mul(1000);
+            allowed.saturating_sub(held) / (1000 - cap)
+        }
+
+        /// @dev     Method #27 (READ)
+        /// @return  (last early-bird block, early-bird multiplier in per mille).
+        #[ink(message)]
+        pub fn early_bird(&self) -> (BlockNumber, u32) {
+            (self.bonus_end_block.0, self.bonus_multiplier)
+        }
+
+        /// @dev     Method #3 (READ)
+        /// @param   addr: AccountId
+        /// @return  Reward multiplier in per mille of _addr's stake at _index, `0` if
+        ///          there is none.
+        #[ink(message)]
+        pub fn get_staked_multiplier(&self, _addr: AccountId, _index: Balance) -> u32 {
+            self.stake_at(_addr, _index).map_or(0, |stake| stake.multiplier)
+        }
+
+        /// Whether rewards are paid in the staked token.
+        fn rewards_in_staked_token(&self) -> bool {
+            !self.native_rewards && self.reward_token.is_none()
+        }
+
+        /// Returns the token balances owed to the accounts: staked principal,
+        /// escrow and emitted rewards if paid in the staked token.
+        fn obligations(&self) -> Balance {
+            let rewards = if self.rewards_in_staked_token() {
+                self.ledger.rewards_owed + self.ledger.boosted
+            } else {
+                0
+            };
+            let legacy = self
+                .legacy_rewards
+                .filter(|legacy| legacy.token.is_none())
+                .map_or(0, |legacy| legacy.owed);
+            self.ledger.principal
+                + self.ledger.released
+                + self.ledger.treasury
+                + self.ledger.gifts
+                + self.ledger.otc
+                + self.ledger.deposits
+                + rewards
+                + legacy
+        }
+
+        /// @dev     Method #30 (WRITE)
+        /// @param   _fee_bps: withdrawal fee of a fresh stake in basis points.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_withdrawal_fee(&mut self, _fee_bps: u32) -> Result<()> {
+            self.ensure_owner()?;
+            if _fee_bps > MAX_FEE_BPS {
+                return Err(Error::FeeTooHigh {
+                    fee_bps: _fee_bps,
+                    max_fee_bps: MAX_FEE_BPS,
+                });
+            }
+            self.settings.withdrawal_fee_bps = _fee_bps;
+            self.log_admin(AdminAction::SetWithdrawalFee, &_fee_bps);
+            Ok(())
+        }
+
+        /// @dev     Method #31 (READ)
+        /// @param   addr: AccountId
+        /// @param   _amount: Balance
+        /// @return  Released amount, withdrawal fee and net payout of claiming _amount.
+        #[ink(message)]
+        pub fn preview_claim(&self, _addr: AccountId, _amount: Balance) -> ClaimPreview {
+            let fee = self.withdrawal_fee(_addr, _amount);
+            ClaimPreview {
+                amount: _amount,
+                fee,
+                net: _amount.saturating_sub(fee),
+            }
+        }
+
+        /// @dev     Method #32-1 (WRITE, payable)
+        /// @param   _min_token_out: smallest amount of the staked token to buy with
+        ///          the transferred native currency, the call reverts below it.
+        /// @return  Burned amount.
+        /// @note    owner only. Buys the staked token back through the router with
+        ///          the transferred native currency, e.g. fee revenue collected
+        ///          outside of the pool, and burns it together with the collected
+        ///          withdrawal fees instead of sending them to the treasury. Withdrawal
+        ///          fees are withheld in the staked token and burned without a swap,
+        ///          so no router is needed if nothing is transferred.
+        #[ink(message, payable)]
+        pub fn buyback_and_burn(&mut self, _min_token_out: Balance) -> Result<Balance> {
+            self.ensure_owner()?;
+            let native_in = self.env().transferred_value();
+            let token = self.get_token();
+            if native_in > 0 {
+                let router = self.settings.router.ok_or(Error::NoRouter)?;
+                let me = self.env().account_id();
+                // Trap instead of returning an error so that the transferred
+                // native currency goes back to the owner.
+                let swap = ink_lang::selector_bytes!("swap_exact_native_for_tokens");
+                let bought = Self::call_contract::<_, Balance>(
+                    router,
+                    swap,
+                    (token, _min_token_out, me),
+                    native_in,
+                )
+                .unwrap_or_else(|_| panic!("buyback swap failed"));
+                assert!(bought >= _min_token_out, "buyback below minimum output");
+                self.ledger.post(Book::Holdings, Book::Treasury, bought);
+                self.env().emit_event(BoughtBack { native_in, bought });
+            }
+            let amount = self.ledger.treasury;
+            if amount == 0 {
+                return Err(Error::NoSurplus);
+            }
+            Self::token_op(token, ink_lang::selector_bytes!("burn"), amount, amount)?;
+            self.ledger.post(Book::Treasury, Book::Holdings, amount);
+            self.env().emit_event(FeesBurned {
+                amount,
+                total_supply: self.token_total_supply().unwrap_or_default(),
+            });
+            self.log_admin(AdminAction::BuybackAndBurn, &amount);
+            Ok(amount)
+        }
+
+        /// @dev     Method #32 (WRITE)
+        /// @return  Collected fees transferred to the treasury.
+        #[ink(message)]
+        pub fn collect_fees(&mut self) -> Result<Balance> {
+            let me = self.env().account_id();
+            let amount = self.ledger.treasury;
+            if amount == 0 {
+                return Err(Error::NoSurplus);
+            }
+            let treasury = self.settings.treasury;
+            self.transfer_with_signature(me, treasury, amount)?;
+            self.ledger.post(Book::Treasury, Book::Holdings, amount);
+            Ok(amount)
+        }
+
+        /// Returns the withdrawal fee of claiming `amount` of `account`'s stakes,
+        /// released in the same order as `claim`.
+        fn withdrawal_fee(&self, account: AccountId, amount: Balance) -> Balance {
+            if self.refund_open {
+                return 0;
+            }
+            let staked = match self.staked.get(&account) {
+                Some(staked) => staked,
+                None => return 0,
+            };
+            let now = Balance::from(self.now());
+            let mut remaining = amount;
+            let mut fee = 0;
+            for stake in staked.iter() {
+                if remaining == 0 {
+                    break;
+                }
+                let portion = self
+                    .unlocked_of(stake)
+                    .saturating_sub(stake.released)
+                    .min(remaining);
+                remaining -= portion;
+                let age = now.saturating_sub(stake.timestamp);
+                let maturity = self.maturity_of(stake);
+                if age < maturity {
+                    let fee_bps = math::mul_div(
+                        self.settings.withdrawal_fee_bps.into(),
+                        maturity - age,
+                        maturity,
+                        Rounding::Floor,
+                    );
+                    fee += math::mul_div(portion, fee_bps, 10_000, Rounding::Floor);
+                }
+            }
+            fee
+        }
+
+        /// Reads `decimals()` of `token`.
+        /// Tokens without `decimals()` are treated as having `DEFAULT_DECIMALS`.
+        fn read_decimals(token: AccountId) -> u8 {
+            Self::call_contract(token, ink_lang::selector_bytes!("decimals"), (), 0)
+                .unwrap_or(DEFAULT_DECIMALS)
+        }
+
+        /// Rejects constructor parameters that would produce a bricked instance.
+        fn validate_params(
+            bonus_end_block: BlockNumber,
+            bonus_multiplier: u32,
+            withdrawal_fee_bps: u32,
+            rewards_duration: Timestamp,
+            block: BlockNumber,
+        ) -> Result<()> {
+            if !(MULTIPLIER_BASE..=MAX_MULTIPLIER).contains(&bonus_multiplier) {
+                return Err(Error::InvalidMultiplier {
+                    multiplier: bonus_multiplier,
+                });
+            }
+            if bonus_multiplier > MULTIPLIER_BASE && bonus_end_block <= block {
+                return Err(Error::BonusWindowElapsed {
+                    bonus_end_block,
+                    block,
+                });
+            }
+            if withdrawal_fee_bps > MAX_FEE_BPS {
+                return Err(Error::FeeTooHigh {
+                    fee_bps: withdrawal_fee_bps,
+                    max_fee_bps: MAX_FEE_BPS,
+                });
+            }
+            if rewards_duration == 0 {
+                return Err(Error::ZeroDuration);
+            }
+            Ok(())
+        }
+
+        /// Probes `account` with a cross-contract `total_supply()` call.
+        ///
+        /// `Erc20Ref::from_account_id` accepts any address, so this is used to
+        /// reject accounts that don't answer like an ERC20 token.
+        fn is_erc20(account: AccountId) -> bool {
+            let total_supply = ink_lang::selector_bytes!("total_supply");
+            Self::call_contract::<_, Balance>(account, total_supply, (), 0).is_ok()
+        }
+
+        /// Converts an amount of whole tokens into token units.
+        fn to_units(&self, whole_tokens: Balance) -> Result<Balance> {
+            Balance::from(10u8)
+                .checked_pow(self.decimals.into())
+                .and_then(|unit| whole_tokens.checked_mul(unit))
+                .ok_or(Error::LimitOverflow)
+        }
+
+        /// Returns the amount of `stake` unlocked so far, rounded per `self.rounding`.
+        fn unlocked_of(&self, stake: &Stake) -> Balance {
+            if self.refund_open {
+                return stake.amount;
+            }
+            math::unlocked_amount(
+                self.unlocked_fraction(stake.schedule, stake.timestamp),
+                stake.amount,
+                self.rounding,
+            )
+        }
+
+        /// @dev     Method #38 (WRITE)
+        /// @param   _role: Role
+        /// @param   _account: AccountId
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn grant_role(&mut self, _role: Role, _account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.roles.insert((_role, _account), ());
+            self.env().emit_event(RoleChanged {
+                account: _account,
+                role: _role,
+                granted: true,
+            });
+            self.log_admin(AdminAction::GrantRole, &(_role, _account));
+            Ok(())
+        }
+
+        /// @dev     Method #39 (WRITE)
+        /// @param   _role: Role
+        /// @param   _account: AccountId
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn revoke_role(&mut self, _role: Role, _account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.roles.take(&(_role, _account));
+            self.env().emit_event(RoleChanged {
+                account: _account,
+                role: _role,
+                granted: false,
+            });
+            self.log_admin(AdminAction::RevokeRole, &(_role, _account));
+            Ok(())
+        }
+
+        /// @dev     Method #39-1 (WRITE)
+        /// @param   _role: Role
+        /// @param   _old: account losing _role.
+        /// @param   _new: account gaining _role.
+        /// @note    owner only, e.g. to rotate the guardian in one call.
+        #[ink(message)]
+        pub fn rotate_role(&mut self, _role: Role, _old: AccountId, _new: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.has_role(_role, _old) {
+                return Err(Error::MissingRole(_role));
+            }
+            self.roles.take(&(_role, _old));
+            self.roles.insert((_role, _new), ());
+            self.env().emit_event(RoleChanged {
+                account: _old,
+                role: _role,
+                granted: false,
+            });
+            self.env().emit_event(RoleChanged {
+                account: _new,
+                role: _role,
+                granted: true,
+            });
+            self.log_admin(AdminAction::RotateRole, &(_role, _old, _new));
+            Ok(())
+        }
+
+        /// @dev     Method #40 (READ)
+        /// @param   _role: Role
+        /// @param   _account: AccountId
+        /// @return  Whether _account holds _role.
+        #[ink(message)]
+        pub fn has_role(&self, _role: Role, _account: AccountId) -> bool {
+            self.roles.contains_key(&(_role, _account))
+        }
+
+        /// @dev     Method #40-1 (READ)
+        /// @param   _offset: index of the first entry among the kept ones.
+        /// @param   _limit: maximal number of entries.
+        /// @return  Kept admin actions, oldest first.
+        /// @note    Complements the events on chains that prune them.
+        #[ink(message)]
+        pub fn admin_log(&self, _offset: u32, _limit: u32) -> Vec<AdminLogEntry> {
+            let first = self.admin_log_len.saturating_sub(MAX_ADMIN_LOG);
+            let start = first.saturating_add(_offset);
+            let end = start.saturating_add(_limit).min(self.admin_log_len);
+            (start..end)
+                .filter_map(|id| self.admin_log.get(&(id % MAX_ADMIN_LOG)).copied())
+                .collect()
+        }
+
+        /// @dev     Method #48 (WRITE)
+        /// @param   _successor: pool replacing this one.
+        /// @note    owner only. Stops new stakes, positions can then be moved into
+        ///          _successor with `migrate_position`.
+        #[ink(message)]
+        pub fn deprecate(&mut self, _successor: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            if let Some(successor) = self.successor {
+                return Err(Error::Deprecated { successor });
+            }
+            self.successor = Some(_successor);
+            self.env().emit_event(Deprecated {
+                successor: _successor,
+            });
+            self.log_admin(AdminAction::Deprecate, &_successor);
+            Ok(())
+        }
+
+        /// @dev     Method #49 (WRITE)
+        /// @param   _predecessor: deprecated pool, `None` to stop accepting positions.
+        /// @note    owner only. Allows _predecessor to migrate positions into this pool.
+        #[ink(message)]
+        pub fn set_predecessor(&mut self, _predecessor: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.predecessor = _predecessor;
+            self.log_admin(AdminAction::SetPredecessor, &_predecessor);
+            Ok(())
+        }
+
+        /// @dev     Method #50 (WRITE)
+        /// @param   _stake_id: StakeId
+        /// @return  Identifier of the position in the successor pool.
+        /// @note    moves the still staked part of caller's stake _stake_id into the
+        ///          successor pool, keeping its age and multiplier. The successor's
+        ///          latest unlock schedule applies from then on. Rejected if the
+        ///          successor stakes another token, reverts if it rejects the position.
+        #[ink(message)]
+        pub fn migrate_position(&mut self, _stake_id: StakeId) -> Result<StakeId> {
+            let caller = self.env().caller();
+            let me = self.env().account_id();
+            let successor = self.successor.ok_or(Error::NotDeprecated)?;
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            self.ensure_not_emergency()?;
+            let staked = self
+                .staked
+                .get(&caller)
+                .ok_or(Error::UnknownStake { stake_id: _stake_id })?;
+            let index = staked
+                .iter()
+                .position(|stake| stake.id == _stake_id)
+                .filter(|&index| staked[index].remaining() > 0)
+                .ok_or(Error::UnknownStake { stake_id: _stake_id })?;
+            let stake = staked[index];
+            let amount = stake.remaining();
+            let position = (
+                caller,
+                stake.amount,
+                stake.released,
+                stake.timestamp,
+                stake.multiplier,
+            );
+            let get_token = ink_lang::selector_bytes!("get_token");
+            let successor_token = Self::call_contract::<_, AccountId>(successor, get_token, (), 0);
+            if successor_token.ok() != Some(self.get_token()) {
+                return Err(Error::InvalidToken);
+            }
+            // The principal moves first, so that the successor never books a
+            // position it doesn't hold the tokens of.
+            self.transfer_with_signature(me, successor, amount)?;
+            let import = ink_lang::selector_bytes!("import_position");
+            // Trap instead of returning an error so that the transfer is reverted.
+            let successor_stake_id: StakeId =
+                match Self::call_contract::<_, Result<_>>(successor, import, position, 0) {
+                    Ok(Ok(stake_id)) => stake_id,
+                    _ => panic!("successor rejected the position"),
+                };
+            self.staked.get_mut(&caller).unwrap().remove(index);
+            let staked_after = self.reward_account(caller).staked - amount;
+            self.update_rewards(caller, staked_after, self.weight_of(caller));
+            self.ledger.post(Book::Principal, Book::Holdings, amount);
+            self.clear_if_empty(caller);
+            self.env().emit_event(PositionMigrated {
+                account: caller,
+                stake_id: _stake_id,
+                amount,
+                successor_stake_id,
+            });
+            Ok(successor_stake_id)
+        }
+
+        /// @dev     Method #51 (WRITE)
+        /// @param   _account: owner of the position.
+        /// @param   _amount: staked principal of the position.
+        /// @param   _released: part of `_amount` already claimed in the predecessor,
+        ///          the rest has been transferred by the predecessor.
+        /// @param   _timestamp: creation time of the position in ms.
+        /// @param   _multiplier: reward multiplier of the position.
+        /// @return  Identifier of the imported position.
+        /// @note    predecessor only.
+        #[ink(message)]
+        pub fn import_position(
+            &mut self,
+            _account: AccountId,
+            _amount: Balance,
+            _released: Balance,
+            _timestamp: Balance,
+            _multiplier: u32,
+        ) -> Result<StakeId> {
+            if self.predecessor != Some(self.env().caller()) {
+                return Err(Error::NotPredecessor);
+            }
+            self.ensure_not_emergency()?;
+            if let Some(successor) = self.successor {
+                return Err(Error::Deprecated { successor });
+            }
+            if _released > _amount {
+                return Err(Error::MigrationFailed);
+            }
+            self.ensure_stake_capacity(_account)?;
+            Ok(self.push_stake(
+                _account,
+                _amount,
+                _released,
+                _timestamp,
+                _multiplier.min(MAX_MULTIPLIER),
+            ))
+        }
+
+        /// @dev     Method #52 (WRITE)
+        /// @param   _to: recipient of the gift.
+        /// @param   _amount: Balance
+        /// @return  Identifier of the gift.
+        /// @note    transfers caller's tokens into the contract. They are only staked
+        ///          once `_to` accepts the gift within `GIFT_WINDOW`, otherwise caller
+        ///          can cancel the gift and get them back.
+        #[ink(message)]
+        pub fn gift_stake(&mut self, _to: AccountId, _amount: Balance) -> Result<GiftId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            self.ensure_stakeable(_to, _amount)?;
+            let balance = self.token_balance_of(caller)?;
+            if balance < _amount {
+                return Err(Error::InsufficientBalance {
+                    balance,
+                    required: _amount,
+                });
+            }
+            self.transfer_with_signature(caller, me, _amount)?;
+            self.ledger.post(Book::Holdings, Book::Gifts, _amount);
+            let gift_id = self.next_gift_id;
+            self.next_gift_id += 1;
+            self.count_entries(caller, |counts| counts.gifts += 1);
+            let expires_at = self.env().block_timestamp() + GIFT_WINDOW;
+            self.gifts.insert(
+                gift_id,
+                Gift {
+                    funder: caller,
+                    recipient: _to,
+                    amount: _amount,
+                    expires_at,
+                },
+            );
+            self.env().emit_event(GiftCreated {
+                gift_id,
+                funder: caller,
+                recipient: _to,
+                amount: _amount,
+                expires_at,
+            });
+            Ok(gift_id)
+        }
+
+        /// @dev     Method #53 (WRITE)
+        /// @param   _gift_id: GiftId
+        /// @return  Identifier of the created stake.
+        /// @note    recipient only, within the acceptance window. The stake starts
+        ///          at acceptance.
+        #[ink(message)]
+        pub fn accept_gift(&mut self, _gift_id: GiftId) -> Result<StakeId> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let gift = self
+                .gifts
+                .get(&_gift_id)
+                .copied()
+                .filter(|gift| gift.recipient == caller)
+                .ok_or(Error::UnknownGift { gift_id: _gift_id })?;
+            let now = self.env().block_timestamp();
+            if now > gift.expires_at {
+                return Err(Error::GiftExpired {
+                    expires_at: gift.expires_at,
+                });
+            }
+            // The limits may have changed since the gift was made.
+            self.ensure_stakeable(caller, gift.amount)?;
+            let multiplier = self.new_stake_multiplier();
+            self.gifts.take(&_gift_id);
+            self.count_entries(gift.funder, |counts| counts.gifts -= 1);
+            // The tokens are already held, `push_stake` moves them into the principal.
+            self.ledger.post(Book::Gifts, Book::Holdings, gift.amount);
+            let stake_id = self.push_stake(caller, gift.amount, 0, now.into(), multiplier);
+            self.env().emit_event(GiftAccepted {
+                gift_id: _gift_id,
+                stake_id,
+            });
+            Ok(stake_id)
+        }
+
+        /// @dev     Method #54 (WRITE)
+        /// @param   _gift_id: GiftId
+        /// @return  Amount transferred back to caller.
+        /// @note    funder only, once the acceptance window is over.
+        #[ink(message)]
+        pub fn cancel_gift(&mut self, _gift_id: GiftId) -> Result<Balance> {
+            let caller = self.env().caller();
+            let me = self.env().account_id();
+            let gift = self
+                .gifts
+                .get(&_gift_id)
+                .copied()
+                .filter(|gift| gift.funder == caller)
+                .ok_or(Error::UnknownGift { gift_id: _gift_id })?;
+            if self.env().block_timestamp() <= gift.expires_at {
+                return Err(Error::GiftPending {
+                    expires_at: gift.expires_at,
+                });
+            }
+            self.transfer_with_signature(me, caller, gift.amount)?;
+            self.gifts.take(&_gift_id);
+            self.count_entries(caller, |counts| counts.gifts -= 1);
+            self.ledger.post(Book::Gifts, Book::Holdings, gift.amount);
+            self.env().emit_event(GiftCancelled { gift_id: _gift_id });
+            Ok(gift.amount)
+        }
+
+        /// @dev     Method #55 (READ)
+        /// @param   _gift_id: GiftId
+        /// @return  The gift, `None` once accepted or cancelled.
+        #[ink(message)]
+        pub fn gift_of(&self, _gift_id: GiftId) -> Option<Gift> {
+            self.gifts.get(&_gift_id).copied()
+        }
+
+        /// @dev     Method #56 (WRITE)
+        /// @param   _buyer: only account allowed to take the offer.
+        /// @param   _amount: unstakable amount to sell.
+        /// @param   _price_token: ERC20 token the price is paid in.
+        /// @param   _price: Balance
+        /// @return  Identifier of the offer.
+        /// @note    releases `_amount` of caller's unstakable balance like `claim` and
+        ///          escrows it, net of the withdrawal fee, until `_buyer` pays the price
+        ///          or caller cancels the offer.
+        #[ink(message)]
+        pub fn list_otc(
+            &mut self,
+            _buyer: AccountId,
+            _amount: Balance,
+            _price_token: AccountId,
+            _price: Balance,
+        ) -> Result<OfferId> {
+            let caller = self.env().caller();
+            if !Self::is_erc20(_price_token) {
+                return Err(Error::InvalidToken);
+            }
+            let net = self.release(caller, _amount, None)?;
+            self.ledger.post(Book::Principal, Book::Otc, net);
+            self.issue_receipt(caller, net, _amount - net);
+            let offer_id = self.next_offer_id;
+            self.next_offer_id += 1;
+            self.count_entries(caller, |counts| counts.offers += 1);
+            self.otc_offers.insert(
+                offer_id,
+                OtcOffer {
+                    seller: caller,
+                    buyer: _buyer,
+                    amount: net,
+                    price_token: _price_token,
+                    price: _price,
+                },
+            );
+            self.env().emit_event(OtcListed {
+                offer_id,
+                seller: caller,
+                buyer: _buyer,
+                amount: net,
+                price_token: _price_token,
+                price: _price,
+            });
+            Ok(offer_id)
+        }
+
+        /// @dev     Method #57 (WRITE)
+        /// @param   _offer_id: OfferId
+        /// @return  Amount of the staked token received.
+        /// @note    buyer only. Pays the price from caller to the seller, which needs an
+        ///          allowance of this contract in the price token, and pays the escrowed
+        ///          amount to caller in the same call.
+        #[ink(message)]
+        pub fn take_otc(&mut self, _offer_id: OfferId) -> Result<Balance> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let me = self.env().account_id();
+            let offer = self
+                .otc_offers
+                .get(&_offer_id)
+                .copied()
+                .filter(|offer| offer.buyer == caller)
+                .ok_or(Error::UnknownOffer { offer_id: _offer_id })?;
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            Self::token_op(
+                offer.price_token,
+                ink_lang::selector_bytes!("transfer_from"),
+                (caller, offer.seller, offer.price),
+                offer.price,
+            )?;
+            self.otc_offers.take(&_offer_id);
+            self.count_entries(offer.seller, |counts| counts.offers -= 1);
+            self.pay_out(me, caller, offer.amount, Book::Otc);
+            self.env().emit_event(OtcSettled {
+                offer_id: _offer_id,
+            });
+            Ok(offer.amount)
+        }
+
+        /// @dev     Method #58 (WRITE)
+        /// @param   _offer_id: OfferId
+        /// @return  Escrowed amount paid back to caller's payout address.
+        /// @note    seller only.
+        #[ink(message)]
+        pub fn cancel_otc(&mut self, _offer_id: OfferId) -> Result<Balance> {
+            let caller = self.env().caller();
+            let me = self.env().account_id();
+            let offer = self
+                .otc_offers
+                .get(&_offer_id)
+                .copied()
+                .filter(|offer| offer.seller == caller)
+                .ok_or(Error::UnknownOffer { offer_id: _offer_id })?;
+            self.otc_offers.take(&_offer_id);
+            self.count_entries(caller, |counts| counts.offers -= 1);
+            let payout = self.payout_address_of(caller);
+            self.pay_out(me, payout, offer.amount, Book::Otc);
+            self.env().emit_event(OtcCancelled {
+                offer_id: _offer_id,
+            });
+            Ok(offer.amount)
+        }
+
+        /// @dev     Method #59 (READ)
+        /// @param   _offer_id: OfferId
+        /// @return  The offer, `None` once settled or cancelled.
+        #[ink(message)]
+        pub fn otc_offer(&self, _offer_id: OfferId) -> Option<OtcOffer> {
+            self.otc_offers.get(&_offer_id).copied()
+        }
+
+        /// @dev     Method #60 (WRITE)
+        /// @param   _eoa_only: bool
+        /// @note    owner only. While set, contracts not on the integrator allowlist
+        ///          can't stake, accept gifts, take OTC offers, claim rewards, lock boosts
+        ///          or claim for others. Withdrawing principal stays open to everyone, so
+        ///          contracts that staked before can always exit.
+        #[ink(message)]
+        pub fn set_eoa_only(&mut self, _eoa_only: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.eoa_only = _eoa_only;
+            self.log_admin(AdminAction::SetEoaOnly, &_eoa_only);
+            Ok(())
+        }
+
+        /// @dev     Method #61 (WRITE)
+        /// @param   _contract: integrator contract.
+        /// @param   _allowed: whether _contract may call while `eoa_only` is set.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_integrator(&mut self, _contract: AccountId, _allowed: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if _allowed {
+                self.integrators.insert(_contract, ());
+            } else {
+                self.integrators.take(&_contract);
+            }
+            self.log_admin(AdminAction::SetIntegrator, &(_contract, _allowed));
+            Ok(())
+        }
+
+        /// @dev     Method #62 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Whether _addr is on the integrator allowlist.
+        #[ink(message)]
+        pub fn is_integrator(&self, _addr: AccountId) -> bool {
+            self.integrators.contains_key(&_addr)
+        }
+
+        /// @dev     Method #63 (WRITE)
+        /// @param   _router: swap router, `None` to disable zapping out and buybacks.
+        /// @note    owner only. The router must expose `get_amount_out(token, amount_in)`
+        ///          quoting the native currency received for `amount_in` tokens, and
+        ///          `swap_exact_tokens_for_native(token, amount_in, min_out, to)` pulling
+        ///          the approved tokens from the caller and sending at least `min_out`
+        ///          native currency to `to`, returning the amount sent. Buybacks call
+        ///          the payable `swap_exact_native_for_tokens(token, min_out, to)`,
+        ///          sending at least `min_out` tokens to `to` and returning the amount.
+        #[ink(message)]
+        pub fn set_router(&mut self, _router: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.router = _router;
+            self.log_admin(AdminAction::SetRouter, &_router);
+            Ok(())
+        }
+
+        /// @dev     Method #64 (WRITE)
+        /// @param   _amount: Balance
+        /// @param   _min_native_out: nothing is claimed if the router quotes less, and
+        ///          the swap reverts if it would pay out less.
+        /// @return  Native currency paid out to caller's payout address, 0 if the
+        ///          claim was paid out in the staked token.
+        /// @note    Claims _amount like `claim` and swaps caller's share of the tokens
+        ///          net of the withdrawal fee, round-up donation and split to the
+        ///          native currency through the router. If the swap fails after the
+        ///          release, that share is paid out in the staked token instead.
+        #[ink(message)]
+        pub fn zap_out_native(
+            &mut self,
+            _amount: Balance,
+            _min_native_out: Balance,
+        ) -> Result<Balance> {
+            let caller = self.env().caller();
+            let router = self.settings.router.ok_or(Error::NoRouter)?;
+            let token = self.get_token();
+            let net = _amount.saturating_sub(self.withdrawal_fee(caller, _amount));
+            let get_amount_out = ink_lang::selector_bytes!("get_amount_out");
+            let quoted = Self::call_contract(router, get_amount_out, (token, net), 0)
+                .map_err(|_| Error::SwapFailed)?;
+            if quoted < _min_native_out {
+                return Err(Error::InsufficientOutput {
+                    out: quoted,
+                    min_expected_out: _min_native_out,
+                });
+            }
+            let net = self.release(caller, _amount, None)?;
+            let own = self.pay_out_shares(caller, net, _amount - net, Book::Principal);
+            if own == 0 {
+                return Ok(0);
+            }
+            let me = self.env().account_id();
+            let payout = self.payout_address_of(caller);
+            let approve = ink_lang::selector_bytes!("approve");
+            let native_out = if Self::token_op(token, approve, (router, own), own).is_ok() {
+                let swap = ink_lang::selector_bytes!("swap_exact_tokens_for_native");
+                let args = (token, own, _min_native_out, payout);
+                Self::call_contract::<_, Balance>(router, swap, args, 0).ok()
+            } else {
+                None
+            };
+            match native_out {
+                Some(native_out) => {
+                    self.ledger.post(Book::Principal, Book::Holdings, own);
+                    self.env().emit_event(ZappedOut {
+                        account: caller,
+                        amount: own,
+                        native_out,
+                    });
+                    Ok(native_out)
+                }
+                None => {
+                    let _ = Self::token_op(token, approve, (router, 0), 0);
+                    self.pay_out(me, payout, own, Book::Principal);
+                    Ok(0)
+                }
+            }
+        }
+
+        /// @dev     Method #65 (WRITE)
+        /// @param   _version: schedule version offered as a lock tier.
+        /// @param   _multiplier: reward multiplier of the tier in per mille, `None` to
+        ///          withdraw the tier.
+        /// @note    owner only. Stakes already moved to the tier keep their multiplier.
+        #[ink(message)]
+        pub fn set_tier_multiplier(
+            &mut self,
+            _version: ScheduleVersion,
+            _multiplier: Option<u32>,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.schedules.contains_key(&_version) {
+                return Err(Error::InvalidSchedule);
+            }
+            match _multiplier {
+                Some(multiplier) => {
+                    if !(MULTIPLIER_BASE..=MAX_MULTIPLIER).contains(&multiplier) {
+                        return Err(Error::InvalidMultiplier { multiplier });
+                    }
+                    self.tier_multipliers.insert(_version, multiplier);
+                }
+                None => {
+                    self.tier_multipliers.take(&_version);
+                }
+            }
+            self.log_admin(AdminAction::SetTierMultiplier, &(_version, _multiplier));
+            Ok(())
+        }
+
+        /// @dev     Method #66 (WRITE)
+        /// @param   _stake_id: StakeId
+        /// @param   _new_tier: schedule version of the tier to move to.
+        /// @return  Reward multiplier of the stake from now on, in per mille.
+        /// @note    Moves caller's stake to a tier maturing later, keeping its creation
+        ///          time. Rewards accrued so far are settled at the old multiplier; the
+        ///          stake keeps its early-bird multiplier if it is higher than the tier's.
+        ///          Moving to a tier maturing sooner or at the same time is rejected.
+        #[ink(message)]
+        pub fn change_tier(
+            &mut self,
+            _stake_id: StakeId,
+            _new_tier: ScheduleVersion,
+        ) -> Result<u32> {
+            let caller = self.env().caller();
+            if self.is_paused() {
+                return Err(Error::Paused);
+            }
+            self.ensure_not_emergency()?;
+            let tier_multiplier = *self
+                .tier_multipliers
+                .get(&_new_tier)
+                .ok_or(Error::UnknownTier { tier: _new_tier })?;
+            let staked = self
+                .staked
+                .get(&caller)
+                .ok_or(Error::UnknownStake { stake_id: _stake_id })?;
+            let index = staked
+                .iter()
+                .position(|stake| stake.id == _stake_id)
+                .filter(|&index| staked[index].remaining() > 0)
+                .ok_or(Error::UnknownStake { stake_id: _stake_id })?;
+            let stake = staked[index];
+            let new_maturity = self.maturity_of_version(_new_tier);
+            if new_maturity <= self.maturity_of(&stake) {
+                return Err(Error::TierDowngrade {
+                    current: stake.schedule,
+                    requested: _new_tier,
+                });
+            }
+            let multiplier = stake.multiplier.max(tier_multiplier);
+            let stakes = self.staked.get_mut(&caller).unwrap();
+            stakes[index].schedule = _new_tier;
+            stakes[index].multiplier = multiplier;
+            let staked_now = self.reward_account(caller).staked;
+            self.update_rewards(caller, staked_now, self.weight_of(caller));
+            self.env().emit_event(TierChanged {
+                account: caller,
+                stake_id: _stake_id,
+                tier: _new_tier,
+                multiplier,
+            });
+            Ok(multiplier)
+        }
+
+        /// @dev     Method #67 (READ)
+        /// @param   _version: ScheduleVersion
+        /// @return  Reward multiplier of the lock tier _version in per mille, `None` if
+        ///          the schedule isn't offered as a tier.
+        #[ink(message)]
+        pub fn tier_multiplier(&self, _version: ScheduleVersion) -> Option<u32> {
+            self.tier_multipliers.get(&_version).copied()
+        }
+
+        /// @dev     Method #68 (WRITE)
+        /// @param   _account: AccountId
+        /// @param   _excluded: whether _account stops earning rewards.
+        /// @note    owner only. The stakes of _account stay subject to their unlock
+        ///          schedules. Rewards accrued before the exclusion stay claimable,
+        ///          from then on the emission goes to the other stakers only.
+        #[ink(message)]
+        pub fn set_reward_excluded(&mut self, _account: AccountId, _excluded: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if _excluded {
+                self.reward_excluded.insert(_account, ());
+            } else {
+                self.reward_excluded.take(&_account);
+            }
+            let staked = self.reward_account(_account).staked;
+            self.update_rewards(_account, staked, self.weight_of(_account));
+            self.log_admin(AdminAction::SetRewardExcluded, &(_account, _excluded));
+            Ok(())
+        }
+
+        /// @dev     Method #69 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Whether _addr is excluded from earning rewards.
+        #[ink(message)]
+        pub fn is_reward_excluded(&self, _addr: AccountId) -> bool {
+            self.reward_excluded.contains_key(&_addr)
+        }
+
+        /// @dev     Method #70 (WRITE, payable)
+        /// @note    owner only. Adds the transferred native currency to the pot
+        ///          sponsoring the storage deposits of new stakers. The value is
+        ///          refunded if the funding is rejected.
+        #[ink(message, payable)]
+        pub fn fund_rent_pot(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            let amount = self.env().transferred_value();
+            if let Err(error) = self.ensure_owner() {
+                // Returning an error doesn't revert the transfer of the value.
+                self.env()
+                    .transfer(caller, amount)
+                    .map_err(|_| Error::TransferFailed { amount })?;
+                return Err(error);
+            }
+            self.ledger.post(Book::NativeHoldings, Book::RentPot, amount);
+            self.log_admin(AdminAction::FundRentPot, &amount);
+            Ok(())
+        }
+
+        /// @dev     Method #71 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    owner only. Transfers _amount of the rent pot to the owner.
+        #[ink(message)]
+        pub fn withdraw_rent_pot(&mut self, _amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            if _amount > self.ledger.rent_pot {
+                return Err(Error::InsufficientBalance {
+                    balance: self.ledger.rent_pot,
+                    required: _amount,
+                });
+            }
+            self.env()
+                .transfer(self.owner, _amount)
+                .map_err(|_| Error::TransferFailed { amount: _amount })?;
+            self.ledger.post(Book::RentPot, Book::NativeHoldings, _amount);
+            self.log_admin(AdminAction::WithdrawRentPot, &_amount);
+            Ok(())
+        }
+
+        /// @dev     Method #72 (WRITE)
+        /// @param   _max_balance: token balance below which a new staker is sponsored.
+        /// @param   _amount: native currency sent to each sponsored staker, covering
+        ///          the storage deposit of its entries, `0` to stop sponsoring.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_rent_sponsorship(
+            &mut self,
+            _max_balance: Balance,
+            _amount: Balance,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.rent_max_balance = _max_balance;
+            self.settings.rent_amount = _amount;
+            self.log_admin(AdminAction::SetRentSponsorship, &(_max_balance, _amount));
+            Ok(())
+        }
+
+        /// @dev     Method #73 (READ)
+        /// @return  (native currency left in the rent pot, token balance below which
+        ///          new stakers are sponsored, native currency sent to each of them).
+        #[ink(message)]
+        pub fn rent_sponsorship(&self) -> (Balance, Balance, Balance) {
+            (self.ledger.rent_pot, self.settings.rent_max_balance, self.settings.rent_amount)
+        }
+
+        /// @dev     Method #74 (READ)
+        /// @return  Worst-case stake entries, storage accesses and cross-contract
+        ///          calls of the staking and claiming messages.
+        /// @note    multiply by the weights of the target chain to model the block
+        ///          fullness, e.g. before whitelisting the contract. The bounds follow
+        ///          from `MAX_STAKES`, `MAX_SPLIT_SHARES` and `MAX_POOLS`.
+        #[ink(message)]
+        pub fn weights(&self) -> Vec<MessageWeight> {
+            let entries = MAX_STAKES as u32;
+            // Split beneficiaries and the donation fund, each paid by a transfer
+            // or credited to its escrow.
+            let payees = MAX_SPLIT_SHARES as u32 + 1;
+            let weight = |selector: [u8; 4], reads: u32, writes: u32, calls: u32| MessageWeight {
+                selector,
+                max_entries: entries,
+                storage_reads: SETTLE_READS + reads,
+                storage_writes: SETTLE_WRITES + writes,
+                calls,
+            };
+            vec![
+                // Stakes, integrators, rent sponsorship; token balance and a transfer.
+                weight(STAKE_SELECTOR, 3, 2, 1 + TRANSFER_CALLS),
+                // Stakes, integrators, rent sponsorship; balance, permit, transfer_from.
+                weight(ink_lang::selector_bytes!("stake_with_permit"), 3, 2, 3),
+                // Stakes, integrators, rent sponsorship; balance, allowance,
+                // transfer_from.
+                weight(ink_lang::selector_bytes!("stake_from_allowance"), 3, 2, 3),
+                // Stakes, integrators.
+                weight(ink_lang::selector_bytes!("on_token_received"), 2, 1, 0),
+                // Stakes, payout address, split, round-up, donations, receipt, escrow
+                // per payee; a transfer per payee.
+                weight(
+                    CLAIM_SELECTOR,
+                    6 + payees,
+                    3 + payees,
+                    TRANSFER_CALLS * payees,
+                ),
+                // As `claim`, plus the entries deleted when closing the account.
+                weight(
+                    CLAIM_ALL_SELECTOR,
+                    6 + payees,
+                    3 + payees + CLOSED_ENTRIES,
+                    TRANSFER_CALLS * payees,
+                ),
+                // As `claim`, plus a listed pool and its `claim_all_for` per pool.
+                weight(
+                    ink_lang::selector_bytes!("claim_all_pools"),
+                    6 + payees + MAX_POOLS,
+                    3 + payees,
+                    TRANSFER_CALLS * payees + MAX_POOLS,
+                ),
+                MessageWeight {
+                    selector: ink_lang::selector_bytes!("consolidate"),
+                    max_entries: entries,
+                    storage_reads: 1,
+                    storage_writes: 1,
+                    calls: 0,
+                },
+            ]
+        }
+
+        /// @dev     Method #75 (WRITE)
+        /// @param   _amount: Balance
+        /// @note    demo pools only (feature `demo`). Mints _amount of the staked
+        ///          token to the caller, at most `DEMO_FAUCET_LIMIT` whole tokens per
+        ///          call.
+        #[cfg(feature = "demo")]
+        #[ink(message)]
+        pub fn faucet(&mut self, _amount: Balance) -> Result<()> {
+            let maximum = self.to_units(DEMO_FAUCET_LIMIT)?;
+            if _amount > maximum {
+                return Err(Error::AboveMaximum {
+                    amount: _amount,
+                    maximum,
+                });
+            }
+            let caller = self.env().caller();
+            let token = self.get_token();
+            // The token mints to its caller, this contract, which passes it on.
+            Self::token_op(token, ink_lang::selector_bytes!("mint"), _amount, _amount)?;
+            let transfer = (caller, _amount);
+            Self::token_op(token, ink_lang::selector_bytes!("transfer"), transfer, _amount)
+        }
+
+        /// @dev     Method #76 (READ)
+        /// @param   _addr: AccountId
+        /// @return  Open stakes of _addr as `FlatStake` tuples of primitives, for
+        ///          clients without the generated types of `stakes_of`.
+        #[ink(message)]
+        pub fn stakes_flat(&self, _addr: AccountId) -> Vec<FlatStake> {
+            self.staked
+                .get(&_addr)
+                .into_iter()
+                .flatten()
+                .map(|stake| {
+                    (
+                        stake.id,
+                        u128::from(stake.amount),
+                        u128::from(stake.released),
+                        stake.timestamp as u64,
+                        stake.multiplier,
+                        stake.schedule,
+                    )
+                })
+                .collect()
+        }
+
+        /// @dev     Method #77 (READ)
+        /// @param   _addr: AccountId
+        /// @return  `account_summary` of _addr as a `FlatSummary` tuple.
+        #[ink(message)]
+        pub fn account_summary_flat(&self, _addr: AccountId) -> FlatSummary {
+            let summary = StakingView::account_summary(self, _addr);
+            (
+                u128::from(summary.staked),
+                u128::from(summary.unstakable),
+                u128::from(summary.pending_rewards),
+                u128::from(summary.escrow),
+                u128::from(summary.boost),
+                summary.stakes,
+            )
+        }
+
+        /// @dev     Method #78 (READ)
+        /// @return  `obligations_report` as a `FlatObligations` tuple.
+        #[ink(message)]
+        pub fn obligations_flat(&self) -> FlatObligations {
+            let report = self.obligations_report();
+            (
+                u128::from(report.principal),
+                u128::from(report.rewards_accrued),
+                u128::from(report.escrow),
+                u128::from(report.fees),
+                u128::from(report.token_balance),
+                u128::from(report.invested),
+                u128::from(report.shortfall),
+                report.timestamp,
+            )
+        }
+
+        /// @dev     Method #79 (WRITE)
+        /// @param   _enabled: bool
+        /// @note    owner only. While enabled, rejected stakes and claims emit
+        ///          `OperationRejected` on top of returning the error.
+        #[ink(message)]
+        pub fn set_reject_events(&mut self, _enabled: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.reject_events = _enabled;
+            self.log_admin(AdminAction::SetRejectEvents, &_enabled);
+            Ok(())
+        }
+
+        /// @dev     Method #80 (READ)
+        /// @return  Whether rejected stakes and claims emit `OperationRejected`.
+        #[ink(message)]
+        pub fn reject_events(&self) -> bool {
+            self.settings.reject_events
+        }
+
+        /// @dev     Method #81 (WRITE)
+        /// @param   _merchant: account allowed to pull, a contract needs to be an
+        ///          integrator while `eoa_only` is set.
+        /// @param   _amount: amount pulled per period.
+        /// @param   _period: length of a period in ms.
+        /// @note    the first pull is possible right away, replaces an earlier
+        ///          authorization of _merchant.
+        #[ink(message)]
+        pub fn authorize_pull(
+            &mut self,
+            _merchant: AccountId,
+            _amount: Balance,
+            _period: Timestamp,
+        ) -> Result<()> {
+            let caller = self.env().caller();
+            if _period == 0 {
+                return Err(Error::ZeroDuration);
+            }
+            if _amount == 0 {
+                return Err(Error::BelowMinimum {
+                    amount: _amount,
+                    minimum: 1,
+                });
+            }
+            if !self.pull_authorizations.contains_key(&(caller, _merchant)) {
+                self.count_entries(caller, |counts| counts.pulls += 1);
+            }
+            self.pull_authorizations.insert(
+                (caller, _merchant),
+                PullAuthorization {
+                    amount: _amount,
+                    period: _period,
+                    next_pull: self.env().block_timestamp(),
+                },
+            );
+            self.env().emit_event(PullAuthorized {
+                account: caller,
+                merchant: _merchant,
+                amount: _amount,
+                period: _period,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #82 (WRITE)
+        /// @param   _merchant: AccountId
+        /// @note    stops the pulls of _merchant from caller.
+        #[ink(message)]
+        pub fn revoke_pull(&mut self, _merchant: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            self.pull_authorizations
+                .take(&(caller, _merchant))
+                .ok_or(Error::NoPullAuthorization)?;
+            self.count_entries(caller, |counts| counts.pulls -= 1);
+            self.env().emit_event(PullAuthorized {
+                account: caller,
+                merchant: _merchant,
+                amount: 0,
+                period: 0,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #83 (WRITE)
+        /// @param   _account: account authorizing the caller.
+        /// @return  Pulled amount.
+        /// @note    authorized merchants only, once per period. Releases the agreed
+        ///          amount of _account's unstakable balance like `claim` and pays it
+        ///          to the caller. Missed periods can be pulled later. Rejected if a
+        ///          withdrawal fee would cut the amount, so only matured stakes pay.
+        #[ink(message)]
+        pub fn pull(&mut self, _account: AccountId) -> Result<Balance> {
+            let caller = self.env().caller();
+            self.ensure_caller_allowed(caller)?;
+            let mut authorization = self
+                .pull_authorizations
+                .get(&(_account, caller))
+                .copied()
+                .ok_or(Error::NoPullAuthorization)?;
+            if self.env().block_timestamp() < authorization.next_pull {
+                return Err(Error::PullNotDue {
+                    next_pull: authorization.next_pull,
+                });
+            }
+            let amount = authorization.amount;
+            let net = self.release(_account, amount, Some(amount))?;
+            authorization.next_pull += authorization.period;
+            self.pull_authorizations.insert((_account, caller), authorization);
+            self.pay_out(self.env().account_id(), caller, net, Book::Principal);
+            self.issue_receipt(_account, net, amount - net);
+            self.env().emit_event(Pulled {
+                account: _account,
+                merchant: caller,
+                amount: net,
+            });
+            Ok(net)
+        }
+
+        /// @dev     Method #84 (READ)
+        /// @param   _account: paying account.
+        /// @param   _merchant: AccountId
+        /// @return  Pulls _merchant may make from _account, `None` if not authorized.
+        #[ink(message)]
+        pub fn pull_authorization(
+            &self,
+            _account: AccountId,
+            _merchant: AccountId,
+        ) -> Option<PullAuthorization> {
+            self.pull_authorizations.get(&(_account, _merchant)).copied()
+        }
+
+        /// @dev     Method #85 (WRITE)
+        /// @param   _fund: fund receiving the round-up donations, `None` to stop
+        ///          donating.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_donation_fund(&mut self, _fund: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.donation_fund = _fund;
+            self.log_admin(AdminAction::SetDonationFund, &_fund);
+            Ok(())
+        }
+
+        /// @dev     Method #86 (WRITE)
+        /// @param   _granularity: token units caller's claims are rounded down to,
+        ///          `0` to opt out.
+        /// @note    the remainder of each principal claim is donated to the donation
+        ///          fund, while one is set.
+        #[ink(message)]
+        pub fn set_round_up(&mut self, _granularity: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            if _granularity == 0 {
+                self.round_ups.take(&caller);
+            } else {
+                self.round_ups.insert(caller, _granularity);
+            }
+            Ok(())
+        }
+
+        /// @dev     Method #87 (READ)
+        /// @param   _addr: AccountId
+        /// @return  (granularity of _addr's round-ups, `0` if opted out, amount donated
+        ///          by _addr, amount donated by all accounts, donation fund).
+        #[ink(message)]
+        pub fn donations_of(
+            &self,
+            _addr: AccountId,
+        ) -> (Balance, Balance, Balance, Option<AccountId>) {
+            (
+                self.round_ups.get(&_addr).copied().unwrap_or(0),
+                self.donations.get(&_addr).copied().unwrap_or(0),
+                self.total_donated,
+                self.settings.donation_fund,
+            )
+        }
+
+        /// @dev     Method #88 (WRITE)
+        /// @param   _max_stakers: maximal number of accounts holding stakes, `0` if
+        ///          unlimited.
+        /// @note    owner only. A cap below the current count only keeps new stakers
+        ///          out until enough accounts left.
+        #[ink(message)]
+        pub fn set_max_stakers(&mut self, _max_stakers: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_stakers = _max_stakers;
+            self.log_admin(AdminAction::SetMaxStakers, &_max_stakers);
+            Ok(())
+        }
+
+        /// @dev     Method #89 (READ)
+        /// @return  (accounts holding stakes, staker cap or `0` if unlimited, slots
+        ///          left for new stakers or `None` if unlimited).
+        #[ink(message)]
+        pub fn staker_slots(&self) -> (u32, u32, Option<u32>) {
+            let remaining = if self.max_stakers == 0 {
+                None
+            } else {
+                Some(self.max_stakers.saturating_sub(self.stakers.len()))
+            };
+            (self.stakers.len(), self.max_stakers, remaining)
+        }
+
+        /// @dev     Method #90 (WRITE)
+        /// @param   _pool: staking pool to list in the registry of this pool.
+        /// @return  Identifier of the pool in the registry.
+        /// @note    owner only. The listed pool has to trust this pool with `set_hub`
+        ///          for `claim_all_pools` to claim from it.
+        #[ink(message)]
+        pub fn register_pool(&mut self, _pool: AccountId) -> Result<PoolId> {
+            self.ensure_owner()?;
+            if self.pools.values().any(|&pool| pool == _pool) {
+                return Err(Error::PoolRegistered);
+            }
+            if self.next_pool_id >= MAX_POOLS {
+                return Err(Error::TooManyPools { maximum: MAX_POOLS });
+            }
+            let pool_id = self.next_pool_id;
+            self.next_pool_id += 1;
+            self.pools.insert(pool_id, _pool);
+            self.log_admin(AdminAction::RegisterPool, &(pool_id, _pool));
+            Ok(pool_id)
+        }
+
+        /// @dev     Method #91 (WRITE)
+        /// @param   _pool_id: PoolId
+        /// @note    owner only. The identifier isn't reused.
+        #[ink(message)]
+        pub fn unregister_pool(&mut self, _pool_id: PoolId) -> Result<()> {
+            self.ensure_owner()?;
+            self.pools.take(&_pool_id).ok_or(Error::UnknownPool)?;
+            self.log_admin(AdminAction::UnregisterPool, &_pool_id);
+            Ok(())
+        }
+
+        /// @dev     Method #92 (READ)
+        /// @return  Pools listed in the registry with their identifiers.
+        #[ink(message)]
+        pub fn pools(&self) -> Vec<(PoolId, AccountId)> {
+            (0..self.next_pool_id)
+                .filter_map(|pool_id| self.pools.get(&pool_id).map(|&pool| (pool_id, pool)))
+                .collect()
+        }
+
+        /// @dev     Method #93 (WRITE)
+        /// @param   _hub: registry allowed to claim for the stakers of this pool,
+        ///          `None` to allow none.
+        /// @note    owner only.
+        #[ink(message)]
+        pub fn set_hub(&mut self, _hub: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.settings.hub = _hub;
+            self.log_admin(AdminAction::SetHub, &_hub);
+            Ok(())
+        }
+
+        /// @dev     Method #94 (WRITE)
+        /// @param   _account: AccountId
+        /// @return  Claimed amount net of the withdrawal fee.
+        /// @note    hub only. Claims all of _account's unstakable tokens like
+        ///          `claim_all`, paid out to _account.
+        #[ink(message)]
+        pub fn claim_all_for(&mut self, _account: AccountId) -> Result<Balance> {
+            if self.settings.hub != Some(self.env().caller()) {
+                return Err(Error::NotHub);
+            }
+            self.claim_all_impl(_account, false)
+        }
+
+        /// @dev     Method #95 (WRITE)
+        /// @param   _start: identifier of the first listed pool to claim from.
+        /// @param   _limit: maximal number of identifiers to go through.
+        /// @return  (claimed amount, identifier to resume from or `None` once the
+        ///          last listed pool is done).
+        /// @note    Claims caller's unstakable tokens from this pool on the first
+        ///          page, then from the listed pools through `claim_all_for`. Pools
+        ///          rejecting the claim, e.g. paused ones, are skipped.
+        #[ink(message)]
+        pub fn claim_all_pools(
+            &mut self,
+            _start: PoolId,
+            _limit: u32,
+        ) -> Result<(Balance, Option<PoolId>)> {
+            let caller = self.env().caller();
+            let mut claimed = 0;
+            if _start == 0 {
+                claimed += self.claim_all_impl(caller, false).unwrap_or(0);
+            }
+            let end = _start.saturating_add(_limit).min(self.next_pool_id);
+            let claim_all_for = ink_lang::selector_bytes!("claim_all_for");
+            for pool_id in _start..end {
+                let pool = match self.pools.get(&pool_id) {
+                    Some(&pool) => pool,
+                    None => continue,
+                };
+                if let Ok(Ok(amount)) =
+                    Self::call_contract::<_, Result<Balance>>(pool, claim_all_for, caller, 0)
+                {
+                    claimed += amount;
+                }
+            }
+            let next = if end < self.next_pool_id { Some(end) } else { None };
+            Ok((claimed, next))
+        }
+
+        /// @dev     Method #96 (WRITE)
+        /// @param   _paused: bool
+        /// @param   _start: identifier of the first listed pool to notify.
+        /// @param   _limit: maximal number of identifiers to go through.
+        /// @return  Identifier to resume from or `None` once the last listed pool
+        ///          is done.
+        /// @note    owner and global guardians only. Suspends or resumes staking and
+        ///          claiming in this pool and in the listed pools trusting it with
+        ///          `set_hub`, independently of the pause of each pool.
+        #[ink(message)]
+        pub fn set_global_paused(
+            &mut self,
+            _paused: bool,
+            _start: PoolId,
+            _limit: u32,
+        ) -> Result<Option<PoolId>> {
+            self.ensure_owner()
+                .or_else(|_| self.ensure_role(Role::GlobalGuardian))?;
+            if _start == 0 {
+                self.global_paused = _paused;
+                self.env().emit_event(GlobalPauseChanged { paused: _paused });
+                self.log_admin(AdminAction::SetGlobalPaused, &_paused);
+            }
+            let end = _start.saturating_add(_limit).min(self.next_pool_id);
+            let apply = ink_lang::selector_bytes!("apply_global_pause");
+            for pool_id in _start..end {
+                if let Some(&pool) = self.pools.get(&pool_id) {
+                    // Trap so that a pool left running can't go unnoticed.
+                    match Self::call_contract::<_, Result<()>>(pool, apply, _paused, 0) {
+                        Ok(Ok(())) => {}
+                        _ => panic!("pool rejected the global pause"),
+                    }
+                }
+            }
+            Ok(if end < self.next_pool_id { Some(end) } else { None })
+        }
+
+        /// @dev     Method #97 (WRITE)
+        /// @param   _paused: bool
+        /// @note    hub only. The pause set by the owner with `set_paused` stays.
+        #[ink(message)]
+        pub fn apply_global_pause(&mut self, _paused: bool) -> Result<()> {
+            if self.settings.hub != Some(self.env().caller()) {
+                return Err(Error::NotHub);
+            }
+            self.global_paused = _paused;
+            self.env().emit_event(GlobalPauseChanged { paused: _paused });
+            Ok(())
+        }
+
+        /// @dev     Method #98 (WRITE)
+        /// @param   _pool_id: listed pool to direct the emission to.
+        /// @param   _weight: voting power given to the pool.
+        /// @note    The voting power of an epoch is caller's staked principal at the
+        ///          first block of the epoch, and can be split across several pools.
+        ///          Votes can't be taken back.
+        #[ink(message)]
+        pub fn vote_gauge(&mut self, _pool_id: PoolId, _weight: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            if !self.pools.contains_key(&_pool_id) {
+                return Err(Error::UnknownPool);
+            }
+            let epoch = self.gauge_epoch();
+            let mut vote = match self.gauge_votes.get(&caller) {
+                Some(&vote) if vote.epoch == epoch => vote,
+                _ => GaugeVote { epoch, used: 0 },
+            };
+            let available = self.gauge_power_of(caller, epoch).saturating_sub(vote.used);
+            if _weight > available {
+                return Err(Error::VotingPowerExceeded {
+                    available,
+                    requested: _weight,
+                });
+            }
+            if _weight == 0 {
+                return Ok(());
+            }
+            vote.used += _weight;
+            self.gauge_votes.insert(caller, vote);
+            let tally = self.gauge_tallies.get(&(epoch, _pool_id)).copied().unwrap_or(0);
+            self.gauge_tallies.insert((epoch, _pool_id), tally + _weight);
+            let total = self.gauge_totals.get(&epoch).copied().unwrap_or(0);
+            self.gauge_totals.insert(epoch, total + _weight);
+            self.env().emit_event(GaugeVoted {
+                voter: caller,
+                pool_id: _pool_id,
+                epoch,
+                weight: _weight,
+            });
+            Ok(())
+        }
+
+        /// @dev     Method #99 (WRITE)
+        /// @param   _epoch: ended gauge epoch.
+        /// @param   _amount: emission of the epoch, in the staked token.
+        /// @return  Emission paid to the pools.
+        /// @note    owner only. Pulls the emission from caller and splits it across
+        ///          the listed pools by their votes in _epoch, funding each through
+        ///          its `notify_reward`, which needs this pool to be a reward
+        ///          injector of it. The shares of unlisted pools and the rounding
+        ///          remainder stay with caller.
+        #[ink(message)]
+        pub fn distribute_gauges(&mut self, _epoch: u32, _amount: Balance) -> Result<Balance> {
+            self.ensure_owner()?;
+            if _epoch >= self.gauge_epoch() {
+                return Err(Error::GaugeEpochActive {
+                    epoch_end: _epoch.saturating_add(1).saturating_mul(GAUGE_EPOCH),
+                });
+            }
+            let total = self.gauge_totals.get(&_epoch).copied().ok_or(Error::NoGaugeVotes)?;
+            let shares: Vec<(AccountId, Balance)> = self
+                .pools()
+                .into_iter()
+                .filter_map(|(pool_id, pool)| {
+                    let votes = self.gauge_tallies.get(&(_epoch, pool_id))?;
+                    Some((pool, math::mul_div(_amount, *votes, total, Rounding::Floor)))
+                })
+                .filter(|&(_, share)| share > 0)
+                .collect();
+            if shares.is_empty() {
+                return Err(Error::NoGaugeVotes);
+            }
+            let amount = shares.iter().map(|&(_, share)| share).sum();
+            let caller = self.env().caller();
+            let me = self.env().account_id();
+            self.transfer_with_signature(caller, me, amount)?;
+            self.gauge_totals.take(&_epoch);
+            for pool_id in 0..self.next_pool_id {
+                self.gauge_tallies.take(&(_epoch, pool_id));
+            }
+            let token = self.get_token();
+            let approve = ink_lang::selector_bytes!("approve");
+            let notify_reward = ink_lang::selector_bytes!("notify_reward");
+            for (pool, share) in shares {
+                let funded = Self::token_op(token, approve, (pool, share), share).is_ok()
+                    && matches!(
+                        Self::call_contract::<_, Result<()>>(pool, notify_reward, share, 0),
+                        Ok(Ok(()))
+                    );
+                // Trap so that the pulled emission doesn't stay in this pool.
+                if !funded {
+                    panic!("pool rejected the gauge emission");
+                }
+            }
+            self.env().emit_event(GaugesDistributed {
+                epoch: _epoch,
+                amount,
+            });
+            self.log_admin(AdminAction::DistributeGauges, &(_epoch, amount));
+            Ok(amount)
+        }
+
+        /// @dev     Method #100 (READ)
+        /// @param   _epoch: gauge epoch.
+        /// @return  (gauge votes per listed pool, votes over all pools) in _epoch,
+        ///          empty once distributed.
+        #[ink(message)]
+        pub fn gauge_tallies(&self, _epoch: u32) -> (Vec<(PoolId, Balance)>, Balance) {
+            let tallies = (0..self.next_pool_id)
+                .filter_map(|pool_id| {
+                    let votes = self.gauge_tallies.get(&(_epoch, pool_id))?;
+                    Some((pool_id, *votes))
+                })
+                .collect();
+            (tallies, self.gauge_totals.get(&_epoch).copied().unwrap_or(0))
+        }
+
+        /// @dev     Method #101 (READ)
+        /// @param   _addr: AccountId
+        /// @return  (current gauge epoch, voting power _addr has left in it).
+        #[ink(message)]
+        pub fn gauge_power(&self, _addr: AccountId) -> (u32, Balance) {
+            let epoch = self.gauge_epoch();
+            let used = match self.gauge_votes.get(&_addr) {
+                Some(vote) if vote.epoch == epoch => vote.used,
+                _ => 0,
+            };
+            (epoch, self.gauge_power_of(_addr, epoch).saturating_sub(used))
+        }
+
+        /// Returns the current gauge epoch.
+        fn gauge_epoch(&self) -> u32 {
+            self.env().block_number() / GAUGE_EPOCH
+        }
+
+        /// Returns the staked principal of `account` at the first block of `epoch`.
+        fn gauge_power_of(&self, account: AccountId, epoch: u32) -> Balance {
+            let start = BlockNumberOf(epoch.saturating_mul(GAUGE_EPOCH));
+            let checkpoints = match self.checkpoints.get(&account) {
+                Some(checkpoints) => checkpoints,
+                None => return 0,
+            };
+            match checkpoints.partition_point(|c| c.block <= start) {
+                0 => 0,
+                index => checkpoints[index - 1].balance.0,
+            }
+        }
+
+        /// Donates the remainder of `amount` claimed by `account` rounded down to
+        /// its granularity, if opted in and a donation fund is set. Returns the
+        /// donated amount.
+        fn donate_round_up(&mut self, account: AccountId, amount: Balance, book: Book) -> Balance {
+            let granularity = match self.round_ups.get(&account) {
+                Some(&granularity) => granularity,
+                None => return 0,
+            };
+            let fund = match self.settings.donation_fund {
+                Some(fund) => fund,
+                None => return 0,
+            };
+            let donation = amount % granularity;
+            if donation == 0 {
+                return 0;
+            }
+            self.pay_out(self.env().account_id(), fund, donation, book);
+            let donated = self.donations.get(&account).copied().unwrap_or(0);
+            self.donations.insert(account, donated + donation);
+            self.total_donated += donation;
+            self.env().emit_event(Donated {
+                account,
+                fund,
+                amount: donation,
+            });
+            donation
+        }
+
+        /// Sends the sponsored storage deposit to `account` staking `staked` for the
+        /// first time with a token `balance` below the threshold, once per account
+        /// and while the pot covers it. Stakes below the minimum, or of nothing,
+        /// aren't sponsored so that the pot can't be drained by empty stakes. A
+        /// failed transfer leaves the stake untouched.
+        fn sponsor_rent(&mut self, account: AccountId, staked: Balance, balance: Balance) {
+            let amount = self.settings.rent_amount;
+            if amount == 0
+                || staked < self.min_stake.max(1)
+                || balance >= self.settings.rent_max_balance
+                || amount > self.ledger.rent_pot
+                || self.rent_sponsored.contains_key(&account)
+            {
+                return;
+            }
+            if self.env().transfer(account, amount).is_ok() {
+                self.ledger.post(Book::RentPot, Book::NativeHoldings, amount);
+                self.rent_sponsored.insert(account, ());
+                self.env().emit_event(RentSponsored { account, amount });
+            }
+        }
+
+        /// Rejects contract callers not on the integrator allowlist while
+        /// `eoa_only` is set.
+        fn ensure_caller_allowed(&self, caller: AccountId) -> Result<()> {
+            if self.eoa_only
+                && self.env().is_contract(&caller)
+                && !self.integrators.contains_key(&caller)
+            {
+                return Err(Error::ContractCaller { caller });
+            }
+            Ok(())
+        }
+
+        /// Emits `OperationRejected` for a failed `result` of the message with
+        /// `selector` if enabled, and passes `result` on. Errors don't revert the
+        /// call, so the event is kept.
+        fn report_rejection<T>(&self, selector: [u8; 4], result: Result<T>) -> Result<T> {
+            if let Err(reason) = &result {
+                if self.settings.reject_events {
+                    self.env().emit_event(OperationRejected {
+                        selector,
+                        reason: reason.clone(),
+                    });
+                }
+            }
+            result
+        }
+
+        /// Appends `action` with the hash of its `params` to the audit trail.
+        fn log_admin<P: Encode>(&mut self, action: AdminAction, params: &P) {
+            use ink_env::hash::{Blake2x256, HashOutput};
+            let mut params_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(params, &mut params_hash);
+            let id = self.admin_log_len;
+            if self.env().caller() == self.owner {
+                self.last_owner_action = self.env().block_timestamp();
+            }
+            self.last_admin_activity = self.env().block_number();
+            self.admin_log.insert(
+                id % MAX_ADMIN_LOG,
+                AdminLogEntry {
+                    id,
+                    action,
+                    actor: self.env().caller(),
+                    params_hash: params_hash.into(),
+                    block: self.env().block_number(),
+                },
+            );
+            self.admin_log_len += 1;
+        }
+
+        fn ensure_role(&self, role: Role) -> Result<()> {
+            if !self.has_role(role, self.env().caller()) {
+                return Err(Error::MissingRole(role));
+            }
+            Ok(())
+        }
+
+        /// Whether staking and claiming are suspended, by this pool's owner or
+        /// by the hub.
+        fn is_paused(&self) -> bool {
+            self.paused || self.global_paused
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Pays `balance` owed in `book` out of the contract, crediting it to the
+        /// escrow of `to` if the token transfer fails so the accounting already
+        /// made stays valid.
+        fn pay_out(&mut self, from: AccountId, to: AccountId, balance: Balance, book: Book) {
+            if self.transfer_with_signature(from, to, balance).is_ok() {
+                self.ledger.post(book, Book::Holdings, balance);
+            } else {
+                let escrowed = self.escrow_of(to);
+                self.escrow.insert(to, escrowed + balance);
+                self.ledger.post(book, Book::Released, balance);
+                self.env().emit_event(EscrowCredited {
+                    account: to,
+                    amount: balance,
+                });
+            }
+        }
+
+        // EIP-2612: Digital Signature Algorithm
+        // This makes Transfer with signature of owner.
+        fn transfer_with_signature(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
             balance: Balance,
-        ) {
+        ) -> Result<()> {
             // Make hash
             let deadline = self.env().block_timestamp() + 86400000;
-            let nonce = self.token.nonce();
+            let token = self.get_token();
+            let nonce: Balance = Self::call_token(token, ink_lang::selector_bytes!("nonce"), ())?;
             let encodable = (from, to, balance, deadline, nonce); // Implements `scale::Encode`
             use ink_env::hash::{HashOutput, Keccak256};
             let mut hash_out = <Keccak256 as HashOutput>::Type::default(); // 256-bit buffer
@@ -304,7 +6881,7 @@ mod staking {
             let mut sig = [0u8; 65];
             self.sig_status += 1; ///////////////////////////////////////////
                                   // Make signature from ownerId
-            #[cfg(all(feature = "std", feature = "rand-std"))]
+            #[cfg(feature = "rand-std")]
             {
                 let secp = Secp256k1::new();
                 let secret_key = SecretKey::from_slice(&[0xcd; 32])
@@ -314,78 +6891,2614 @@ mod staking {
                 // See the above example for how to use this library together with `bitcoin_hashes`.
                 let message = Message::from_slice(&hash_out).expect("32 bytes");
 
-                sig = secp.sign_ecdsa(&message, &secret_key);
-                assert!(secp.verify_ecdsa(&message, &sig, &public_key).is_ok());
-                // self.sig_status += 1; // This code doesn't work cozOf block. So don use self.????
+                sig = secp.sign_ecdsa(&message, &secret_key);
+                assert!(secp.verify_ecdsa(&message, &sig, &public_key).is_ok());
+                // self.sig_status += 1; // This code doesn't work cozOf block. So don use self.????
+            }
+            Self::token_op(
+                token,
+                ink_lang::selector_bytes!("transfer_with_signature"),
+                (from, to, balance, deadline, nonce, sig, hash_out),
+                balance,
+            )
+        }
+
+        /// Calls the message `selector` of the ERC20 `token` with `args`, the tuple
+        /// of its arguments.
+        ///
+        /// A token that traps or answers with an undecodable result yields
+        /// `TokenCallFailed`, instead of trapping this contract along with it.
+        fn call_token<Args: Encode, R: scale::Decode>(
+            token: AccountId,
+            selector: [u8; 4],
+            args: Args,
+        ) -> Result<R> {
+            Self::call_contract(token, selector, args, 0)
+                .map_err(|_| Error::TokenCallFailed { token, selector })
+        }
+
+        /// Calls the message `selector` of the contract `callee` with `args`, the
+        /// tuple of its arguments, transferring `value` of the native currency.
+        #[cfg(not(any(test, feature = "e2e")))]
+        fn call_contract<Args: Encode, R: scale::Decode>(
+            callee: AccountId,
+            selector: [u8; 4],
+            args: Args,
+            value: Balance,
+        ) -> ink_env::Result<R> {
+            use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+            build_call::<StakingEnvironment>()
+                .call_type(Call::new().callee(callee).gas_limit(0).transferred_value(value))
+                .exec_input(ExecutionInput::new(Selector::new(selector)).push_arg(args))
+                .returns::<R>()
+                .fire()
+        }
+
+        /// Answers calls from the contract mocks, the off-chain environment
+        /// can't call other contracts.
+        #[cfg(test)]
+        fn call_contract<Args: Encode, R: scale::Decode>(
+            callee: AccountId,
+            selector: [u8; 4],
+            args: Args,
+            value: Balance,
+        ) -> ink_env::Result<R> {
+            let caller = ink_env::account_id::<StakingEnvironment>();
+            let output = crate::mock::call(caller, callee, selector, &args.encode(), value)
+                .ok_or(ink_env::Error::CalleeTrapped)?;
+            R::decode(&mut &output[..]).map_err(ink_env::Error::Decode)
+        }
+
+        /// Hands the calls to the dispatcher of the end-to-end tests, which runs
+        /// the called contract in the same off-chain environment.
+        #[cfg(all(feature = "e2e", not(test)))]
+        fn call_contract<Args: Encode, R: scale::Decode>(
+            callee: AccountId,
+            selector: [u8; 4],
+            args: Args,
+            value: Balance,
+        ) -> ink_env::Result<R> {
+            let caller = ink_env::account_id::<StakingEnvironment>();
+            let output = crate::e2e::call(caller, callee, selector, &args.encode(), value)
+                .ok_or(ink_env::Error::CalleeTrapped)?;
+            R::decode(&mut &output[..]).map_err(ink_env::Error::Decode)
+        }
+
+        /// Calls a state changing message of the ERC20 `token` moving `amount`,
+        /// converting the error returned by the token into `TransferFailed`.
+        fn token_op<Args: Encode>(
+            token: AccountId,
+            selector: [u8; 4],
+            args: Args,
+            amount: Balance,
+        ) -> Result<()> {
+            // The token's own errors are fieldless, decoding their index suffices.
+            Self::call_token::<_, core::result::Result<(), u8>>(token, selector, args)?
+                .map_err(|_| Error::TransferFailed { amount })
+        }
+
+        /// Calls `deposit` or `withdraw` of the yield `strategy`, returning
+        /// whether the call went through and the strategy accepted it.
+        fn strategy_op(strategy: AccountId, selector: [u8; 4], amount: Balance) -> bool {
+            // Like the token's, the strategy's errors are fieldless.
+            let result: ink_env::Result<core::result::Result<(), u8>> =
+                Self::call_contract(strategy, selector, amount, 0);
+            matches!(result, Ok(Ok(())))
+        }
+
+        /// Returns the balance of `owner` in the staked token.
+        fn token_balance_of(&self, owner: AccountId) -> Result<Balance> {
+            Self::call_token(self.get_token(), ink_lang::selector_bytes!("balance_of"), owner)
+        }
+
+        /// Returns the total supply of the staked token.
+        fn token_total_supply(&self) -> Result<Balance> {
+            Self::call_token(self.get_token(), ink_lang::selector_bytes!("total_supply"), ())
+        }
+    }
+
+    impl StakingView for Staking {
+        #[ink(message)]
+        fn token(&self) -> AccountId {
+            self.get_token()
+        }
+
+        #[ink(message)]
+        fn total_staked(&self) -> Balance {
+            self.ledger.principal
+        }
+
+        #[ink(message)]
+        fn stakes_of(&self, account: AccountId) -> Vec<Stake> {
+            self.staked
+                .get(&account)
+                .map(|staked| staked.to_vec())
+                .unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn account_summary(&self, account: AccountId) -> AccountSummary {
+            let stakes = self.staked.get(&account).map_or(0, |v| v.len() as u32);
+            AccountSummary {
+                staked: self.reward_account(account).staked,
+                unstakable: if stakes == 0 { 0 } else { self.get_balance(account) },
+                pending_rewards: self.pending_rewards(account),
+                escrow: self.escrow_of(account),
+                boost: self.boost_of(account).0,
+                stakes,
+            }
+        }
+    }
+
+    /// Messages of the original API under their original selectors, as thin
+    /// wrappers over the redesigned ones, so that dApps built against it keep
+    /// working for one more release. They trap where the original messages
+    /// silently did nothing.
+    impl Staking {
+        /// @dev     Legacy `stake` (WRITE)
+        /// @param   _amount: Balance
+        /// @note    `stake` without deadline. Removed in the next release.
+        #[ink(message, selector = 0x5ADB38DE)]
+        pub fn legacy_stake(&mut self, _amount: Balance) {
+            if self.stake(_amount, None).is_err() {
+                panic!("stake rejected");
+            }
+        }
+
+        /// @dev     Legacy `claim` (WRITE)
+        /// @param   _amount: Balance
+        /// @note    `claim` without minimal output or deadline. Removed in the next
+        ///          release.
+        #[ink(message, selector = 0xB388803F)]
+        pub fn legacy_claim(&mut self, _amount: Balance) {
+            if self.claim(_amount, None, None).is_err() {
+                panic!("claim rejected");
+            }
+        }
+
+        /// @dev     Legacy `claim_all` (WRITE)
+        /// @note    `claim_all` keeping the account open. Removed in the next release.
+        #[ink(message, selector = 0x381B1590)]
+        pub fn legacy_claim_all(&mut self) {
+            if self.claim_all(false).is_err() {
+                panic!("claim rejected");
+            }
+        }
+    }
+
+    // Odded out Unit Test.
+    // module and test functions are marked with a `#[test]` attribute.
+    // The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        use crate::mock;
+        use crate::staking::Staking;
+        use staking_traits::StakingEnvironment as Environment;
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        fn set_sender(sender: AccountId) {
+            ink_env::test::set_caller::<Environment>(sender);
+        }
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<Environment> {
+            ink_env::test::default_accounts::<Environment>()
+        }
+
+        fn contract_id() -> AccountId {
+            ink_env::test::callee::<Environment>()
+        }
+
+        fn alice() -> AccountId {
+            default_accounts().alice
+        }
+
+        fn bob() -> AccountId {
+            default_accounts().bob
+        }
+
+        fn charlie() -> AccountId {
+            default_accounts().charlie
+        }
+
+        fn set_balance(account_id: AccountId, balance: Balance) {
+            ink_env::test::set_account_balance::<Environment>(
+                account_id, balance,
+            )
+        }
+
+        fn get_balance(account_id: AccountId) -> Balance {
+            ink_env::test::get_account_balance::<Environment>(account_id)
+                .expect("Cannot get account balance")
+        }
+
+        fn django() -> AccountId {
+            default_accounts().django
+        }
+
+        /// Account of the pool under test.
+        fn pool() -> AccountId {
+            AccountId::from([0x50; 32])
+        }
+
+        fn set_timestamp(timestamp: Timestamp) {
+            ink_env::test::set_block_timestamp::<Environment>(timestamp);
+        }
+
+        fn advance_blocks(blocks: u32) {
+            for _ in 0..blocks {
+                ink_env::test::advance_block::<Environment>();
+            }
+        }
+
+        /// Lets the stakes made at `0` fully unlock.
+        fn mature() {
+            set_timestamp(6 * INITIAL_STEP + PAYOUT_TIMELOCK);
+        }
+
+        /// Deploys a pool of the mocked token owned by alice, without fees, in
+        /// which bob stakes `amount` at `0`.
+        fn pool_with_stake(amount: Balance) -> Staking {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_timestamp(0);
+            set_sender(alice());
+            let mut staking = Staking::new(mock::token(), 0, MULTIPLIER_BASE, 0, INITIAL_STEP);
+            mock::mint(bob(), amount);
+            set_sender(bob());
+            assert_eq!(staking.stake(amount, None), Ok(0));
+            staking
+        }
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn default_works() {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_sender(alice());
+            let staking = Staking::new(mock::token(), 0, MULTIPLIER_BASE, 0, INITIAL_STEP);
+            assert_eq!(staking.get_token(), mock::token());
+            assert_eq!(staking.get_owner(), alice());
+            assert_eq!(staking.stakes_flat(bob()), vec![]);
+        }
+
+        /// Nonsensical constructor parameters are rejected.
+        #[ink::test]
+        fn validate_params_works() {
+            const WEEK: Timestamp = 7 * 86400_000;
+            assert_eq!(Staking::validate_params(100, 1500, 300, WEEK, 10), Ok(()));
+            // No bonus needs no window.
+            assert_eq!(Staking::validate_params(0, 1000, 0, WEEK, 10), Ok(()));
+            assert_eq!(
+                Staking::validate_params(100, 900, 300, WEEK, 10),
+                Err(Error::InvalidMultiplier { multiplier: 900 })
+            );
+            assert_eq!(
+                Staking::validate_params(5, 1500, 300, WEEK, 10),
+                Err(Error::BonusWindowElapsed {
+                    bonus_end_block: 5,
+                    block: 10
+                })
+            );
+            assert_eq!(
+                Staking::validate_params(100, 1500, 1001, WEEK, 10),
+                Err(Error::FeeTooHigh {
+                    fee_bps: 1001,
+                    max_fee_bps: 1000
+                })
+            );
+            assert_eq!(
+                Staking::validate_params(100, 1500, 300, 0, 10),
+                Err(Error::ZeroDuration)
+            );
+        }
+
+        /// A merchant pulls the authorized amount once per period.
+        #[ink::test]
+        fn pull_pays_merchant_once_per_period() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            assert_eq!(staking.authorize_pull(charlie(), 100, INITIAL_STEP), Ok(()));
+            set_sender(charlie());
+            assert_eq!(staking.pull(bob()), Ok(100));
+            assert_eq!(mock::balance_of(charlie()), 100);
+            assert_eq!(
+                staking.pull(bob()),
+                Err(Error::PullNotDue {
+                    next_pull: 7 * INITIAL_STEP + PAYOUT_TIMELOCK
+                })
+            );
+            assert_eq!(staking.pull(alice()), Err(Error::NoPullAuthorization));
+        }
+
+        /// Claims are split along the shares once the split is effective.
+        #[ink::test]
+        fn split_pays_beneficiaries() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.set_split(vec![(bob(), 600), (charlie(), 400)]), Ok(()));
+            mature();
+            assert_eq!(staking.claim(1000, None, None), Ok(1000));
+            assert_eq!(mock::balance_of(bob()), 600);
+            assert_eq!(mock::balance_of(charlie()), 400);
+        }
+
+        /// Splits whose shares don't sum up to 1000 per mille are rejected,
+        /// including sums only reaching it by overflowing.
+        #[ink::test]
+        fn split_rejects_invalid_shares() {
+            let mut staking = pool_with_stake(1000);
+            for shares in [
+                vec![(bob(), 600), (charlie(), 300)],
+                vec![(bob(), 1000), (charlie(), 0)],
+                vec![(bob(), 1001)],
+                vec![(bob(), u32::MAX), (charlie(), 1001)],
+            ] {
+                assert_eq!(staking.set_split(shares), Err(Error::InvalidSplit));
+            }
+            assert_eq!(staking.split_of(bob()), None);
+        }
+
+        /// Rounding dust of the shares goes to the last beneficiary.
+        #[ink::test]
+        fn split_rounds_down_to_last_beneficiary() {
+            let mut staking = pool_with_stake(1000);
+            let shares = vec![(bob(), 333), (charlie(), 333), (django(), 334)];
+            assert_eq!(staking.set_split(shares), Ok(()));
+            mature();
+            assert_eq!(staking.claim(100, None, None), Ok(100));
+            assert_eq!(mock::balance_of(bob()), 33);
+            assert_eq!(mock::balance_of(charlie()), 33);
+            assert_eq!(mock::balance_of(django()), 34);
+        }
+
+        /// The remainder of a claim rounded down is donated to the fund.
+        #[ink::test]
+        fn round_up_donates_remainder() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_donation_fund(Some(django())), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.set_round_up(100), Ok(()));
+            mature();
+            assert_eq!(staking.claim(250, None, None), Ok(250));
+            assert_eq!(mock::balance_of(bob()), 200);
+            assert_eq!(mock::balance_of(django()), 50);
+            assert_eq!(staking.donations_of(bob()), (100, 50, 50, Some(django())));
+        }
+
+        /// Claims and stakes executed after their deadline are rejected with
+        /// nothing moved, claims before it go through.
+        #[ink::test]
+        fn expired_deadline_is_returned() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            let now = 6 * INITIAL_STEP + PAYOUT_TIMELOCK;
+            let deadline = Some(now - 1);
+            assert_eq!(
+                staking.claim(100, None, deadline),
+                Err(Error::DeadlineExpired {
+                    deadline: now - 1,
+                    now
+                })
+            );
+            assert_eq!(mock::balance_of(bob()), 0);
+            mock::mint(bob(), 100);
+            assert_eq!(
+                staking.stake(100, deadline),
+                Err(Error::DeadlineExpired {
+                    deadline: now - 1,
+                    now
+                })
+            );
+            assert_eq!(mock::balance_of(bob()), 100);
+            assert_eq!(staking.claim(100, None, Some(now)), Ok(100));
+        }
+
+        /// The calls reported by `weights` are those of the worst case: a claim
+        /// split among `MAX_SPLIT_SHARES` beneficiaries after a round-up donation,
+        /// and stakes.
+        #[ink::test]
+        fn weights_match_calls() {
+            let mut staking = pool_with_stake(1000);
+            let weights = staking.weights();
+            let calls_of = |selector: [u8; 4]| {
+                weights.iter().find(|weight| weight.selector == selector).map(|w| w.calls)
+            };
+            let calls = mock::calls();
+            mock::mint(bob(), 200);
+            assert_eq!(staking.stake(100, None), Ok(1));
+            assert_eq!(Some(mock::calls() - calls), calls_of(STAKE_SELECTOR));
+            let calls = mock::calls();
+            assert_eq!(staking.stake_with_permit(100, 0, [0; 65]), Ok(2));
+            assert_eq!(
+                Some(mock::calls() - calls),
+                calls_of(ink_lang::selector_bytes!("stake_with_permit"))
+            );
+            set_sender(alice());
+            assert_eq!(staking.set_donation_fund(Some(django())), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.set_round_up(100), Ok(()));
+            let shares = (0..MAX_SPLIT_SHARES as u8)
+                .map(|i| (AccountId::from([0x60 + i; 32]), 1000 / MAX_SPLIT_SHARES as u32))
+                .collect();
+            assert_eq!(staking.set_split(shares), Ok(()));
+            mature();
+            let calls = mock::calls();
+            assert_eq!(staking.claim(999, None, None), Ok(999));
+            assert_eq!(Some(mock::calls() - calls), calls_of(CLAIM_SELECTOR));
+            mock::mint(bob(), 100);
+            mock::approve(bob(), pool(), 100);
+            let calls = mock::calls();
+            assert_eq!(staking.stake_from_allowance(100, None), Ok(3));
+            assert_eq!(
+                Some(mock::calls() - calls),
+                calls_of(ink_lang::selector_bytes!("stake_from_allowance"))
+            );
+        }
+
+        /// `claim_all_pools` calls at most every pool `MAX_POOLS` allows on top of
+        /// the worst-case claim.
+        #[ink::test]
+        fn weights_bound_claim_all_pools() {
+            let mut staking = pool_with_stake(999);
+            let weights = staking.weights();
+            let calls_of = |selector: [u8; 4]| {
+                weights.iter().find(|weight| weight.selector == selector).map(|w| w.calls)
+            };
+            set_sender(alice());
+            assert_eq!(staking.set_donation_fund(Some(django())), Ok(()));
+            for n in 0..MAX_POOLS as u8 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            set_sender(bob());
+            assert_eq!(staking.set_round_up(100), Ok(()));
+            let shares = (0..MAX_SPLIT_SHARES as u8)
+                .map(|i| (AccountId::from([0x70 + i; 32]), 1000 / MAX_SPLIT_SHARES as u32))
+                .collect();
+            assert_eq!(staking.set_split(shares), Ok(()));
+            mature();
+            let calls = mock::calls();
+            let claimed = 999 + MAX_POOLS as Balance * mock::SIBLING_CLAIM;
+            assert_eq!(staking.claim_all_pools(0, MAX_POOLS), Ok((claimed, None)));
+            assert_eq!(
+                Some(mock::calls() - calls),
+                calls_of(ink_lang::selector_bytes!("claim_all_pools"))
+            );
+            assert_eq!(
+                calls_of(ink_lang::selector_bytes!("claim_all_pools")),
+                calls_of(CLAIM_SELECTOR).map(|calls| calls + MAX_POOLS)
+            );
+        }
+
+        /// A position moves into the successor pool along with its principal.
+        #[ink::test]
+        fn migrate_moves_position_and_principal() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.deprecate(mock::successor()), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.migrate_position(0), Ok(0));
+            assert_eq!(
+                mock::imports(),
+                vec![mock::Import {
+                    account: bob(),
+                    amount: 1000,
+                    released: 0,
+                    timestamp: 0,
+                    multiplier: MULTIPLIER_BASE,
+                }]
+            );
+            assert_eq!(mock::balance_of(mock::successor()), 1000);
+            assert_eq!(mock::balance_of(pool()), 0);
+            assert_eq!(staking.stakes_flat(bob()), vec![]);
+        }
+
+        /// A position the successor rejects reverts the migration, along with
+        /// the transfer of its principal.
+        #[ink::test]
+        #[should_panic(expected = "successor rejected the position")]
+        fn migrate_traps_on_rejected_import() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.deprecate(mock::successor()), Ok(()));
+            mock::fail(ink_lang::selector_bytes!("import_position"));
+            set_sender(bob());
+            let _ = staking.migrate_position(0);
+        }
+
+        /// Only the surplus is invested, and divesting brings it back.
+        #[ink::test]
+        fn invest_moves_surplus_to_strategy() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(pool(), 500);
+            set_sender(alice());
+            assert_eq!(staking.set_strategy(Some(mock::strategy())), Ok(()));
+            assert_eq!(staking.invest_surplus(501), Err(Error::NoSurplus));
+            assert_eq!(staking.invest_surplus(500), Ok(()));
+            assert_eq!(mock::deposited(), 500);
+            assert_eq!(mock::balance_of(pool()), 1000);
+            assert_eq!(staking.divest(500), Ok(500));
+            assert_eq!(mock::balance_of(pool()), 1500);
+        }
+
+        /// A deposit the strategy rejects reverts the investment, along with
+        /// the transfer of the tokens.
+        #[ink::test]
+        #[should_panic(expected = "strategy rejected the deposit")]
+        fn invest_traps_on_rejected_deposit() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(pool(), 500);
+            set_sender(alice());
+            assert_eq!(staking.set_strategy(Some(mock::strategy())), Ok(()));
+            mock::fail(ink_lang::selector_bytes!("deposit"));
+            let _ = staking.invest_surplus(500);
+        }
+
+        /// Claims are swapped to the native currency, or paid in the token if
+        /// the swap fails.
+        #[ink::test]
+        fn zap_swaps_claim_to_native() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_router(Some(mock::router())), Ok(()));
+            mature();
+            set_sender(bob());
+            assert_eq!(
+                staking.zap_out_native(400, 801),
+                Err(Error::InsufficientOutput {
+                    out: 400 * mock::SWAP_RATE,
+                    min_expected_out: 801
+                })
+            );
+            assert_eq!(staking.zap_out_native(400, 800), Ok(400 * mock::SWAP_RATE));
+            assert_eq!(mock::native_out(bob()), 400 * mock::SWAP_RATE);
+            assert_eq!(mock::balance_of(mock::router()), 400);
+            mock::fail(ink_lang::selector_bytes!("swap_exact_tokens_for_native"));
+            assert_eq!(staking.zap_out_native(600, 0), Ok(0));
+            assert_eq!(mock::balance_of(bob()), 600);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// Only the caller's own share of a split claim is swapped.
+        #[ink::test]
+        fn zap_pays_split_shares_in_tokens() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_router(Some(mock::router())), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.set_split(vec![(bob(), 500), (charlie(), 500)]), Ok(()));
+            mature();
+            assert_eq!(staking.zap_out_native(400, 0), Ok(200 * mock::SWAP_RATE));
+            assert_eq!(mock::balance_of(charlie()), 200);
+            assert_eq!(mock::native_out(bob()), 200 * mock::SWAP_RATE);
+            assert_eq!(mock::balance_of(mock::router()), 200);
+        }
+
+        /// Fully exited accounts read as empty instead of trapping.
+        #[ink::test]
+        fn views_of_exited_account_are_empty() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            assert_eq!(staking.get_staked_amount(bob(), 0), 1000);
+            assert_eq!(staking.get_staked_timestamp(bob(), 0), 0);
+            assert_eq!(staking.get_staked_amount(bob(), 1), 0);
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            assert_eq!(staking.get_balance(bob()), 0);
+            assert_eq!(staking.get_staked_amount(bob(), 0), 0);
+            assert_eq!(staking.get_staked_timestamp(charlie(), 0), 0);
+        }
+
+        /// Closing an account leaves the entries kept for others: checkpoints,
+        /// receipts and their count.
+        #[ink::test]
+        fn storage_footprint_counts_kept_entries() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            assert_eq!(staking.claim_all(true), Ok(1000));
+            let checkpoints = staking.checkpoints.get(&bob()).map_or(0, Encode::encoded_size);
+            let kept = checkpoints
+                + EntryCounts::default().encoded_size()
+                + Receipt::default().encoded_size();
+            assert_eq!(staking.storage_footprint(bob()), kept as u32);
+        }
+
+        /// Stakes made before the end of the bonus window keep the bonus
+        /// multiplier, later ones get the base multiplier.
+        #[ink::test]
+        fn early_birds_keep_bonus_multiplier() {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_sender(alice());
+            let mut staking = Staking::new(mock::token(), 2, 1500, 0, INITIAL_STEP);
+            assert_eq!(staking.early_bird(), (2, 1500));
+            mock::mint(bob(), 200);
+            set_sender(bob());
+            assert_eq!(staking.stake(100, None), Ok(0));
+            advance_blocks(2);
+            assert_eq!(staking.stake(100, None), Ok(1));
+            assert_eq!(staking.get_staked_multiplier(bob(), 0), 1500);
+            assert_eq!(staking.get_staked_multiplier(bob(), 1), MULTIPLIER_BASE);
+            assert_eq!(staking.get_staked_multiplier(bob(), 2), 0);
+            assert_eq!(staking.get_staked_multiplier(charlie(), 0), 0);
+        }
+
+        /// Once a supermajority voted for the refund, the whole principal can be
+        /// withdrawn without fee, even while paused and before maturity.
+        #[ink::test]
+        fn refund_vote_voids_locks_while_paused() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 400);
+            set_sender(charlie());
+            assert_eq!(staking.stake(400, None), Ok(1));
+            set_sender(alice());
+            assert_eq!(staking.pause(), Ok(()));
+            set_sender(charlie());
+            assert_eq!(staking.vote_refund(), Ok(false));
+            assert_eq!(staking.vote_refund(), Err(Error::AlreadyVoted));
+            assert_eq!(staking.claim_all(false), Err(Error::Paused));
+            set_sender(bob());
+            assert_eq!(staking.vote_refund(), Ok(true));
+            assert_eq!(staking.refund_vote(), (1400, REFUND_VOTE_WINDOW, true));
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            set_sender(charlie());
+            assert_eq!(staking.claim(400, None, None), Ok(400));
+            assert_eq!(mock::balance_of(bob()), 1000);
+            assert_eq!(mock::balance_of(charlie()), 400);
+        }
+
+        /// The receipt of a tipped claim records what the owner receives.
+        #[ink::test]
+        fn tipped_claim_receipt_deducts_tip() {
+            let mut staking = pool_with_stake(1000);
+            let bot = ClaimBot {
+                threshold: 0,
+                tip_bps: 100,
+            };
+            assert_eq!(staking.set_claim_bot(Some(bot)), Ok(()));
+            mature();
+            set_sender(charlie());
+            assert_eq!(staking.claim_for_with_tip(bob()), Ok(10));
+            let receipt = staking.receipt(0).unwrap();
+            assert_eq!((receipt.account, receipt.amount, receipt.fee), (bob(), 990, 10));
+            assert_eq!(mock::balance_of(bob()), 990);
+        }
+
+        /// Positions only move to a successor staking the same token.
+        #[ink::test]
+        fn migrate_rejects_successor_of_other_token() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.deprecate(mock::successor()), Ok(()));
+            mock::set_successor_token(mock::router());
+            set_sender(bob());
+            assert_eq!(staking.migrate_position(0), Err(Error::InvalidToken));
+            assert_eq!(mock::balance_of(pool()), 1000);
+            assert_eq!(mock::imports(), vec![]);
+        }
+
+        /// The state dump pages over the stakers left, not over emptied slots.
+        #[ink::test]
+        fn dump_state_pages_over_stakers() {
+            let mut staking = pool_with_stake(1000);
+            for (staker, stake_id) in [(charlie(), 1), (django(), 2)] {
+                mock::mint(staker, 100);
+                set_sender(staker);
+                assert_eq!(staking.stake(100, None), Ok(stake_id));
+            }
+            mature();
+            set_sender(bob());
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            set_sender(alice());
+            assert_eq!(staking.set_paused(true), Ok(()));
+            let accounts = |dump: Result<Vec<AccountDump>>| {
+                dump.unwrap().iter().map(|dump| dump.account).collect::<Vec<_>>()
+            };
+            assert_eq!(accounts(staking.dump_state(0, 10)), vec![django(), charlie()]);
+            assert_eq!(accounts(staking.dump_state(1, 10)), vec![charlie()]);
+            assert_eq!(accounts(staking.dump_state(2, 10)), vec![]);
+            assert_eq!(staking.staker_index.get(&django()), Some(&0));
+        }
+
+        /// Rejected idempotent claims are reported like plain claims.
+        #[ink::test]
+        fn claim_with_id_reports_rejection() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_reject_events(true), Ok(()));
+            set_sender(bob());
+            let emitted = ink_env::test::recorded_events().count();
+            assert!(staking.claim_with_id(1000, 7).is_err());
+            let events: Vec<_> = ink_env::test::recorded_events().collect();
+            assert_eq!(events.len(), emitted + 1);
+            match <Event as scale::Decode>::decode(&mut &events[emitted].data[..]) {
+                Ok(Event::OperationRejected(rejected)) => {
+                    assert_eq!(rejected.selector, ink_lang::selector_bytes!("claim_with_id"))
+                }
+                _ => panic!("expected OperationRejected"),
+            }
+            assert_eq!(staking.get_claim(bob(), 7), None);
+        }
+
+        /// Claiming everything counts as user activity like any claim.
+        #[ink::test]
+        fn claim_all_records_activity() {
+            let mut staking = pool_with_stake(1000);
+            let (_, staked_at) = staking.last_activity();
+            mature();
+            advance_blocks(3);
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            assert_eq!(staking.last_activity().1, staked_at + 3);
+        }
+
+        /// The keys returned for light clients are where the storage keeps the
+        /// entries of the account.
+        #[ink::test]
+        fn storage_keys_match_the_storage() {
+            let staking = pool_with_stake(1000);
+            let keys = staking.storage_keys_of(bob());
+            assert!(!keys.is_empty());
+            ink_storage::traits::push_spread_root(&staking, &Key::from([0x00; 32]));
+            for key in keys {
+                let stored = ink_env::get_contract_storage::<()>(&Key::from(key));
+                assert!(matches!(stored, Ok(Some(()))));
+            }
+            assert_eq!(staking.storage_keys_of(charlie()), Vec::<[u8; 32]>::new());
+        }
+
+        /// Pools are listed once, up to `MAX_POOLS`, by the owner only.
+        #[ink::test]
+        fn register_pool_lists_pools() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(bob());
+            assert_eq!(staking.register_pool(mock::sibling(0)), Err(Error::NotOwner));
+            set_sender(alice());
+            assert_eq!(staking.register_pool(mock::sibling(0)), Ok(0));
+            assert_eq!(staking.register_pool(mock::sibling(0)), Err(Error::PoolRegistered));
+            assert_eq!(staking.register_pool(mock::sibling(1)), Ok(1));
+            assert_eq!(staking.unregister_pool(0), Ok(()));
+            assert_eq!(staking.unregister_pool(0), Err(Error::UnknownPool));
+            assert_eq!(staking.pools(), vec![(1, mock::sibling(1))]);
+            for n in 2..MAX_POOLS as u8 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            assert_eq!(
+                staking.register_pool(mock::sibling(0)),
+                Err(Error::TooManyPools { maximum: MAX_POOLS })
+            );
+        }
+
+        /// Only the hub trusted by the pool claims for its stakers.
+        #[ink::test]
+        fn claim_all_for_needs_the_hub() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            set_sender(charlie());
+            assert_eq!(staking.claim_all_for(bob()), Err(Error::NotHub));
+            set_sender(alice());
+            assert_eq!(staking.set_hub(Some(charlie())), Ok(()));
+            set_sender(charlie());
+            assert_eq!(staking.claim_all_for(bob()), Ok(1000));
+            assert_eq!(mock::balance_of(bob()), 1000);
+            assert_eq!(mock::balance_of(charlie()), 0);
+        }
+
+        /// `claim_all_pools` claims here on the first page and pages over the
+        /// listed pools, skipping the ones that trap.
+        #[ink::test]
+        fn claim_all_pools_pages_over_the_registry() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            for n in 0..3 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            assert_eq!(staking.unregister_pool(1), Ok(()));
+            mature();
+            set_sender(bob());
+            assert_eq!(staking.claim_all_pools(0, 2), Ok((1000 + mock::SIBLING_CLAIM, Some(2))));
+            assert_eq!(mock::balance_of(bob()), 1000);
+            assert_eq!(staking.claim_all_pools(2, 2), Ok((mock::SIBLING_CLAIM, None)));
+            assert_eq!(
+                mock::sibling_claims(),
+                vec![(mock::sibling(0), bob()), (mock::sibling(2), bob())]
+            );
+            mock::fail(ink_lang::selector_bytes!("claim_all_for"));
+            assert_eq!(staking.claim_all_pools(0, 3), Ok((0, None)));
+        }
+
+        /// The hub's pause holds whatever the owner of the pool sets.
+        #[ink::test]
+        fn global_pause_overrides_the_pool_pause() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(bob(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.apply_global_pause(true), Err(Error::NotHub));
+            set_sender(alice());
+            assert_eq!(staking.set_hub(Some(charlie())), Ok(()));
+            set_sender(charlie());
+            assert_eq!(staking.apply_global_pause(true), Ok(()));
+            set_sender(alice());
+            assert_eq!(staking.set_paused(false), Ok(()));
+            assert!(staking.health().paused);
+            assert!(!staking.get_config().paused);
+            set_sender(bob());
+            assert_eq!(staking.stake(1000, None), Err(Error::Paused));
+            set_sender(charlie());
+            assert_eq!(staking.apply_global_pause(false), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+        }
+
+        /// Global guardians pause the hub and the listed pools page by page, and
+        /// only the owner of a pool resumes its own pause.
+        #[ink::test]
+        fn set_global_paused_pages_over_the_registry() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            for n in 0..3 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            set_sender(charlie());
+            assert_eq!(
+                staking.set_global_paused(true, 0, 2),
+                Err(Error::MissingRole(Role::GlobalGuardian))
+            );
+            set_sender(alice());
+            assert_eq!(staking.grant_role(Role::GlobalGuardian, charlie()), Ok(()));
+            set_sender(charlie());
+            assert_eq!(staking.set_global_paused(true, 0, 2), Ok(Some(2)));
+            assert!(staking.get_config().global_paused);
+            assert_eq!(staking.set_global_paused(true, 2, 2), Ok(None));
+            assert_eq!(
+                mock::sibling_pauses(),
+                vec![
+                    (mock::sibling(0), true),
+                    (mock::sibling(1), true),
+                    (mock::sibling(2), true)
+                ]
+            );
+            assert_eq!(staking.set_paused(false), Err(Error::NotOwner));
+            set_sender(bob());
+            assert_eq!(staking.claim_all(false), Err(Error::Paused));
+        }
+
+        /// Stakers split the principal they held at the start of the epoch across
+        /// the listed pools.
+        #[ink::test]
+        fn vote_gauge_spends_the_epoch_power() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            for n in 0..2 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            set_sender(bob());
+            assert_eq!(staking.vote_gauge(2, 1), Err(Error::UnknownPool));
+            assert_eq!(staking.vote_gauge(0, 600), Ok(()));
+            assert_eq!(staking.vote_gauge(1, 400), Ok(()));
+            assert_eq!(
+                staking.vote_gauge(1, 1),
+                Err(Error::VotingPowerExceeded {
+                    available: 0,
+                    requested: 1
+                })
+            );
+            assert_eq!(staking.gauge_tallies(0), (vec![(0, 600), (1, 400)], 1000));
+            // Principal staked during the epoch only votes from the next one on.
+            advance_blocks(1);
+            mock::mint(charlie(), 500);
+            set_sender(charlie());
+            assert_eq!(staking.stake(500, None), Ok(1));
+            assert_eq!(staking.gauge_power(charlie()), (0, 0));
+            advance_blocks(GAUGE_EPOCH);
+            assert_eq!(staking.gauge_power(charlie()), (1, 500));
+            assert_eq!(staking.gauge_power(bob()), (1, 1000));
+        }
+
+        /// The emission of an ended epoch is split by the votes and funds each
+        /// pool once.
+        #[ink::test]
+        fn distribute_gauges_funds_the_pools() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            for n in 0..3 {
+                assert_eq!(staking.register_pool(mock::sibling(n)), Ok(n as PoolId));
+            }
+            set_sender(bob());
+            assert_eq!(staking.vote_gauge(0, 500), Ok(()));
+            assert_eq!(staking.vote_gauge(1, 200), Ok(()));
+            assert_eq!(staking.vote_gauge(2, 300), Ok(()));
+            set_sender(alice());
+            assert_eq!(staking.unregister_pool(2), Ok(()));
+            assert_eq!(
+                staking.distribute_gauges(0, 999),
+                Err(Error::GaugeEpochActive {
+                    epoch_end: GAUGE_EPOCH
+                })
+            );
+            advance_blocks(GAUGE_EPOCH);
+            mock::mint(alice(), 999);
+            assert_eq!(staking.distribute_gauges(0, 999), Ok(698));
+            assert_eq!(mock::balance_of(mock::sibling(0)), 499);
+            assert_eq!(mock::balance_of(mock::sibling(1)), 199);
+            assert_eq!(mock::balance_of(mock::sibling(2)), 0);
+            assert_eq!(mock::balance_of(alice()), 999 - 698);
+            assert_eq!(mock::balance_of(pool()), 1000);
+            assert_eq!(staking.distribute_gauges(0, 999), Err(Error::NoGaugeVotes));
+            assert_eq!(staking.gauge_tallies(0), (vec![], 0));
+        }
+
+        /// The metadata of a stake carries its amount, unlock time and multiplier.
+        #[ink::test]
+        fn token_uri_describes_the_stake() {
+            let staking = pool_with_stake(1000);
+            let unlock = staking.unlock_timeline(bob()).last().unwrap().0;
+            assert_eq!(
+                staking.token_uri(bob(), 0),
+                Some(format!(
+                    "data:application/json,{{\"name\":\"Stake #0\",\"amount\":1000,\
+                     \"released\":0,\"start\":0,\"unlock\":{},\"multiplier\":1000}}",
+                    unlock
+                ))
+            );
+            assert_eq!(staking.token_uri(bob(), 1), None);
+            assert_eq!(staking.token_uri(charlie(), 0), None);
+        }
+
+        /// The legacy messages keep the original selectors and arguments, the
+        /// redesigned ones take the selectors of their `_v2` names.
+        #[ink::test]
+        fn legacy_messages_wrap_the_new_api() {
+            assert_eq!(ink_lang::selector_bytes!("stake"), [0x5A, 0xDB, 0x38, 0xDE]);
+            assert_eq!(ink_lang::selector_bytes!("claim"), [0xB3, 0x88, 0x80, 0x3F]);
+            assert_eq!(ink_lang::selector_bytes!("claim_all"), [0x38, 0x1B, 0x15, 0x90]);
+            assert_eq!(STAKE_SELECTOR, [0x3F, 0x1D, 0x81, 0x40]);
+            assert_eq!(CLAIM_SELECTOR, [0x08, 0xB8, 0x25, 0x17]);
+            assert_eq!(CLAIM_ALL_SELECTOR, [0x78, 0xF2, 0xD9, 0x05]);
+            let mut staking = pool_with_stake(1000);
+            mock::mint(bob(), 500);
+            staking.legacy_stake(500);
+            assert_eq!(staking.get_staked_amount(bob(), 1), 500);
+            mature();
+            staking.legacy_claim(300);
+            assert_eq!(mock::balance_of(bob()), 300);
+            staking.legacy_claim_all();
+            assert_eq!(mock::balance_of(bob()), 1500);
+        }
+
+        /// Unlike the original messages, the legacy ones trap on a rejection.
+        #[ink::test]
+        #[should_panic(expected = "claim rejected")]
+        fn legacy_claim_traps_on_rejection() {
+            let mut staking = pool_with_stake(1000);
+            staking.legacy_claim(2000);
+        }
+
+        /// A short allowance is reported with the allowance to add, and the stake
+        /// goes through once approved.
+        #[ink::test]
+        fn stake_from_allowance_reports_the_missing_allowance() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(bob(), 500);
+            mock::approve(bob(), pool(), 200);
+            assert_eq!(
+                staking.stake_from_allowance(500, None),
+                Err(Error::InsufficientAllowance {
+                    allowance: 200,
+                    missing: 300
+                })
+            );
+            assert_eq!(mock::balance_of(bob()), 500);
+            mock::approve(bob(), pool(), 500);
+            assert_eq!(staking.stake_from_allowance(500, None), Ok(1));
+            assert_eq!(mock::balance_of(bob()), 0);
+            assert_eq!(mock::balance_of(pool()), 1500);
+        }
+
+        /// A payout the token rejects is escrowed until the staker withdraws it.
+        #[ink::test]
+        fn failed_payout_is_escrowed() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            let transfer = ink_lang::selector_bytes!("transfer_with_signature");
+            mock::fail(transfer);
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            assert_eq!(staking.escrow_of(bob()), 1000);
+            assert_eq!(mock::balance_of(bob()), 0);
+            assert!(staking.health().ledger_balanced);
+            assert!(staking.withdraw_escrow().is_err());
+            assert_eq!(staking.escrow_of(bob()), 1000);
+            mock::restore(transfer);
+            assert_eq!(staking.withdraw_escrow(), Ok(1000));
+            assert_eq!(staking.escrow_of(bob()), 0);
+            assert_eq!(mock::balance_of(bob()), 1000);
+            assert_eq!(staking.withdraw_escrow(), Err(Error::EmptyEscrow));
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// A retried claim id returns the first outcome without claiming again.
+        #[ink::test]
+        fn claim_id_is_idempotent() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            assert_eq!(staking.claim_with_id(400, 1), Ok(400));
+            assert_eq!(staking.claim_with_id(400, 1), Ok(400));
+            assert_eq!(mock::balance_of(bob()), 400);
+            assert_eq!(staking.get_claim(bob(), 1), Some(400));
+            assert_eq!(staking.claim_with_id(400, 2), Ok(400));
+            assert_eq!(mock::balance_of(bob()), 800);
+            set_sender(charlie());
+            assert_eq!(staking.get_claim(charlie(), 1), None);
+        }
+
+        /// The rounding set by the owner applies to the unlocked amounts.
+        #[ink::test]
+        fn rounding_applies_to_unlocked_amounts() {
+            let mut staking = pool_with_stake(15);
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.get_rounding(), Rounding::Floor);
+            assert_eq!(staking.get_balance(bob()), 7);
+            assert_eq!(staking.set_rounding(Rounding::Ceil), Err(Error::NotOwner));
+            set_sender(alice());
+            for (rounding, unlocked) in
+                [(Rounding::Ceil, 8), (Rounding::HalfEven, 8), (Rounding::Floor, 7)]
+            {
+                assert_eq!(staking.set_rounding(rounding), Ok(()));
+                assert_eq!(staking.get_rounding(), rounding);
+                assert_eq!(staking.get_balance(bob()), unlocked);
+            }
+        }
+
+        /// Stakes of an 18-decimal token close to `Balance::MAX` unlock without
+        /// overflowing the intermediate products.
+        #[ink::test]
+        fn large_stakes_unlock_without_overflow() {
+            let amount = 10u128.pow(36);
+            let mut staking = pool_with_stake(amount);
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.get_balance(bob()), amount / 2);
+            mature();
+            assert_eq!(staking.claim_all(false), Ok(amount));
+            assert_eq!(mock::balance_of(bob()), amount);
+        }
+
+        /// Limits are set in whole tokens and kept in units of the decimals the
+        /// token reports.
+        #[ink::test]
+        fn limits_follow_the_token_decimals() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.token_decimals(), 12);
+            let unit = 10u128.pow(12);
+            set_sender(alice());
+            assert_eq!(staking.set_limits(1, 5, 0), Ok(()));
+            assert_eq!(staking.get_limits(), (unit, 5 * unit, 0));
+            assert_eq!(staking.set_limits(Balance::MAX, 0, 0), Err(Error::LimitOverflow));
+            assert_eq!(staking.get_limits(), (unit, 5 * unit, 0));
+            mock::mint(bob(), 6 * unit);
+            set_sender(bob());
+            assert_eq!(
+                staking.stake(unit - 1, None),
+                Err(Error::BelowMinimum {
+                    amount: unit - 1,
+                    minimum: unit
+                })
+            );
+            assert_eq!(
+                staking.stake(6 * unit, None),
+                Err(Error::AboveMaximum {
+                    amount: 6 * unit,
+                    maximum: 5 * unit
+                })
+            );
+            assert_eq!(staking.stake(unit, None), Ok(1));
+        }
+
+        /// An account not answering `total_supply` can't be the staked token.
+        #[ink::test]
+        #[should_panic(expected = "token account is not a callable ERC20 contract")]
+        fn constructor_rejects_non_token_account() {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_sender(alice());
+            mock::fail(ink_lang::selector_bytes!("total_supply"));
+            Staking::new(charlie(), 0, MULTIPLIER_BASE, 0, INITIAL_STEP);
+        }
+
+        /// The token is only replaced after the timelock, once the pool owes
+        /// nothing in the old one.
+        #[ink::test]
+        fn token_replacement_waits_for_timelock_and_empty_pool() {
+            let mut staking = pool_with_stake(1000);
+            let new_token = mock::successor();
+            set_sender(alice());
+            assert_eq!(staking.set_token(new_token), Err(Error::NotProposed));
+            assert_eq!(staking.propose_token(new_token), Ok(()));
+            assert_eq!(staking.get_pending_token(), Some((new_token, ADMIN_TIMELOCK)));
+            assert_eq!(
+                staking.set_token(new_token),
+                Err(Error::TimelockActive {
+                    now: 0,
+                    eta: ADMIN_TIMELOCK
+                })
+            );
+            assert_eq!(staking.set_token(charlie()), Err(Error::NotProposed));
+            mature();
+            assert_eq!(
+                staking.set_token(new_token),
+                Err(Error::OutstandingBalances {
+                    staked: 1000,
+                    escrowed: 0,
+                    rewards: 0
+                })
+            );
+            set_sender(bob());
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            set_sender(alice());
+            assert_eq!(staking.set_token(new_token), Ok(()));
+            assert_eq!(staking.get_token(), new_token);
+            assert_eq!(staking.get_pending_token(), None);
+        }
+
+        /// Rejected stakes and claims carry the values the check failed on.
+        #[ink::test]
+        fn errors_carry_their_context() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(bob(), 300);
+            assert_eq!(
+                staking.stake(500, None),
+                Err(Error::InsufficientBalance {
+                    balance: 300,
+                    required: 500
+                })
+            );
+            advance_blocks(4);
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(
+                staking.claim(501, None, None),
+                Err(Error::ExceedsUnstakable {
+                    requested: 501,
+                    unstakable: 500,
+                    block: 4
+                })
+            );
+        }
+
+        /// The health report follows the balance, the obligations and the admin
+        /// state of the pool.
+        #[ink::test]
+        fn health_reports_solvency_and_admin_state() {
+            let mut staking = pool_with_stake(1000);
+            let health = staking.health();
+            assert!(!health.paused);
+            assert_eq!(health.token_balance, 1000);
+            assert_eq!(health.obligations, 1000);
+            assert_eq!(health.solvency_permille, Some(1000));
+            assert_eq!(health.pending_admin_actions, 0);
+            assert!(health.ledger_balanced);
+            mock::mint(pool(), 500);
+            set_sender(alice());
+            assert_eq!(staking.propose_token(mock::successor()), Ok(()));
+            assert_eq!(staking.set_paused(true), Ok(()));
+            let health = staking.health();
+            assert!(health.paused);
+            assert_eq!(health.solvency_permille, Some(1500));
+            assert_eq!(health.pending_admin_actions, 1);
+        }
+
+        /// Rewards accrue per unit of weight between interactions and are split
+        /// pro rata among the stakers.
+        #[ink::test]
+        fn rewards_accrue_pro_rata() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 3000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(3000, None), Ok(1));
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            assert_eq!(staking.get_reward_rate(), (1, INITIAL_STEP, INITIAL_STEP));
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(staking.pending_rewards(bob()), 10_800);
+            assert_eq!(staking.pending_rewards(charlie()), 32_400);
+            set_sender(bob());
+            assert_eq!(staking.claim_rewards(None), Ok(10_800));
+            assert_eq!(mock::balance_of(bob()), 10_800);
+            assert_eq!(staking.claim_rewards(None), Err(Error::NoRewards));
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 10_800);
+            assert_eq!(staking.pending_rewards(charlie()), 64_800);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// The average stake weighs each principal by the blocks it was held for.
+        #[ink::test]
+        fn twab_averages_over_blocks() {
+            let mut staking = pool_with_stake(1000);
+            advance_blocks(10);
+            mock::mint(bob(), 1000);
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            advance_blocks(10);
+            assert_eq!(staking.twab(bob(), 0, 20), 1500);
+            assert_eq!(staking.twab(bob(), 10, 20), 2000);
+            // The window is capped at the current block.
+            assert_eq!(staking.twab(bob(), 0, 100), 1500);
+            assert_eq!(staking.twab(bob(), 20, 10), 0);
+            assert_eq!(staking.twab(charlie(), 0, 20), 0);
+        }
+
+        /// No account may stake beyond the maximal share once others stake too.
+        #[ink::test]
+        fn max_share_caps_the_stakers() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.set_max_share(500), Err(Error::NotOwner));
+            set_sender(alice());
+            assert_eq!(staking.set_max_share(500), Ok(()));
+            // The only staker is not capped.
+            assert_eq!(staking.max_stake_headroom(bob()), Balance::MAX);
+            assert_eq!(staking.max_stake_headroom(charlie()), 1000);
+            mock::mint(charlie(), 2000);
+            set_sender(charlie());
+            assert_eq!(
+                staking.stake(1001, None),
+                Err(Error::AboveMaximum {
+                    amount: 1001,
+                    maximum: 1000
+                })
+            );
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            mock::mint(bob(), 1);
+            set_sender(bob());
+            assert_eq!(
+                staking.stake(1, None),
+                Err(Error::AboveMaximum {
+                    amount: 1,
+                    maximum: 0
+                })
+            );
+        }
+
+        /// The withdrawal fee decays linearly with the age of the claimed stake.
+        #[ink::test]
+        fn withdrawal_fee_decays_with_age() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(
+                staking.set_withdrawal_fee(1001),
+                Err(Error::FeeTooHigh {
+                    fee_bps: 1001,
+                    max_fee_bps: 1000
+                })
+            );
+            assert_eq!(staking.set_withdrawal_fee(600), Ok(()));
+            // 600 bps * 5/6 of the maturity left.
+            set_timestamp(INITIAL_STEP);
+            let preview = staking.preview_claim(bob(), 400);
+            assert_eq!((preview.fee, preview.net), (20, 380));
+            set_timestamp(3 * INITIAL_STEP);
+            assert_eq!(staking.preview_claim(bob(), 400).fee, 12);
+            mature();
+            assert_eq!(staking.preview_claim(bob(), 400).fee, 0);
+        }
+
+        /// Locked rewards raise the reward weight until they are unlocked after
+        /// the cooldown.
+        #[ink::test]
+        fn boost_raises_the_reward_weight() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP / 2);
+            set_sender(bob());
+            assert_eq!(staking.lock_boost(21_601), Err(Error::NoRewards));
+            assert_eq!(staking.lock_boost(200), Ok(()));
+            assert_eq!(staking.boost_of(bob()), (200, 200));
+            // The second half is split 1200:1000.
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 21_400 + 23_563);
+            assert_eq!(staking.pending_rewards(charlie()), 41_236);
+            let unlock_at = INITIAL_STEP / 2 + BOOST_COOLDOWN;
+            assert_eq!(staking.unlock_boost(), Err(Error::BoostLocked { unlock_at }));
+            set_timestamp(unlock_at);
+            assert_eq!(staking.unlock_boost(), Ok(200));
+            assert_eq!(staking.boost_of(bob()), (0, 0));
+            assert_eq!(staking.pending_rewards(bob()), 45_163);
+        }
+
+        /// Eligibility checks read the current principal and the checkpoints.
+        #[ink::test]
+        fn eligibility_checks_follow_the_principal() {
+            let mut staking = pool_with_stake(1000);
+            advance_blocks(10);
+            assert!(staking.has_min_stake(bob(), 1000));
+            assert!(!staking.has_min_stake(bob(), 1001));
+            assert!(staking.has_min_stake(charlie(), 0));
+            assert!(staking.staked_since(bob(), 0));
+            assert!(staking.staked_since(bob(), 5));
+            assert!(!staking.staked_since(charlie(), 0));
+            mature();
+            assert_eq!(staking.claim_all(false), Ok(1000));
+            assert!(!staking.has_min_stake(bob(), 1));
+            assert!(!staking.staked_since(bob(), 0));
+        }
+
+        /// Only accounts granted the injector role may push rewards.
+        #[ink::test]
+        fn reward_injector_notifies_rewards() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(django(), 2 * 86_400);
+            set_sender(django());
+            let missing = Err(Error::MissingRole(Role::RewardInjector));
+            assert_eq!(staking.notify_reward(86_400), missing);
+            set_sender(alice());
+            assert_eq!(staking.grant_role(Role::RewardInjector, django()), Ok(()));
+            set_sender(django());
+            assert_eq!(staking.notify_reward(86_400), Ok(()));
+            assert_eq!(staking.get_reward_rate(), (1, INITIAL_STEP, INITIAL_STEP));
+            assert_eq!(mock::balance_of(django()), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.revoke_role(Role::RewardInjector, django()), Ok(()));
+            set_sender(django());
+            assert_eq!(staking.notify_reward(86_400), missing);
+        }
+
+        /// A new funding rolls the rewards not emitted yet into a fresh period,
+        /// and nothing accrues after the period ends.
+        #[ink::test]
+        fn funding_rolls_over_into_a_new_period() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400 + 43_200);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(staking.fund_rewards(43_200), Ok(()));
+            let finish = INITIAL_STEP / 2 + INITIAL_STEP;
+            assert_eq!(staking.get_reward_rate(), (1, finish, INITIAL_STEP));
+            assert_eq!(staking.pending_rewards(bob()), 43_200);
+            set_timestamp(2 * INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 129_600);
+            set_sender(bob());
+            assert_eq!(staking.claim_rewards(None), Ok(129_600));
+            assert_eq!(staking.get_ledger().reward_funds, 0);
+        }
+
+        /// The emergency mode freezes the unlocked amounts and the reward emission.
+        #[ink::test]
+        fn emergency_freezes_schedules_and_rewards() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            assert_eq!(staking.enter_emergency(), Err(Error::NotOwner));
+            set_sender(alice());
+            assert_eq!(staking.enter_emergency(), Ok(()));
+            assert_eq!(staking.enter_emergency(), Err(Error::Emergency));
+            assert_eq!(staking.emergency_since(), Some(INITIAL_STEP));
+            set_timestamp(3 * INITIAL_STEP);
+            assert_eq!(staking.get_balance(bob()), 500);
+            assert_eq!(staking.pending_rewards(bob()), 43_200);
+            mock::mint(bob(), 1000);
+            set_sender(bob());
+            assert_eq!(staking.stake(1000, None), Err(Error::Emergency));
+            assert_eq!(staking.claim(500, None, None), Ok(500));
+            assert_eq!(staking.get_balance(bob()), 0);
+        }
+
+        /// The configuration is returned in one call and follows the admin changes.
+        #[ink::test]
+        fn config_reflects_the_settings() {
+            let mut staking = pool_with_stake(1000);
+            let config = staking.get_config();
+            assert_eq!(config.token, mock::token());
+            assert_eq!(config.token_decimals, 12);
+            assert_eq!((config.owner, config.treasury), (alice(), alice()));
+            assert_eq!(config.maturity, 6 * Balance::from(INITIAL_STEP));
+            assert_eq!(config.withdrawal_fee_bps, 0);
+            assert_eq!(config.bonus_multiplier, MULTIPLIER_BASE);
+            assert_eq!(config.rewards_duration, INITIAL_STEP);
+            assert_eq!(config.successor, None);
+            assert!(!config.paused && config.emergency_since.is_none());
+            set_sender(alice());
+            assert_eq!(staking.set_withdrawal_fee(300), Ok(()));
+            assert_eq!(staking.set_max_share(250), Ok(()));
+            assert_eq!(staking.set_paused(true), Ok(()));
+            let config = staking.get_config();
+            assert_eq!(config.withdrawal_fee_bps, 300);
+            assert_eq!(config.max_share_permille, 250);
+            assert!(config.paused);
+        }
+
+        /// The guardian moves the stakes of a lost account after the challenge
+        /// delay, unless the owner of the account cancels it.
+        #[ink::test]
+        fn guardian_recovers_the_stakes() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(
+                staking.set_recovery(charlie(), MIN_RECOVERY_DELAY - 1),
+                Err(Error::RecoveryDelayTooShort {
+                    delay: MIN_RECOVERY_DELAY - 1,
+                    min_delay: MIN_RECOVERY_DELAY
+                })
+            );
+            assert_eq!(staking.set_recovery(charlie(), MIN_RECOVERY_DELAY), Ok(()));
+            set_sender(django());
+            assert_eq!(staking.initiate_recovery(bob(), django()), Err(Error::NotGuardian));
+            set_sender(charlie());
+            assert_eq!(staking.initiate_recovery(bob(), django()), Ok(()));
+            assert_eq!(
+                staking.execute_recovery(bob()),
+                Err(Error::TimelockActive {
+                    now: 0,
+                    eta: MIN_RECOVERY_DELAY
+                })
+            );
+            set_sender(bob());
+            assert_eq!(staking.cancel_recovery(), Ok(()));
+            set_timestamp(MIN_RECOVERY_DELAY);
+            set_sender(charlie());
+            assert_eq!(staking.execute_recovery(bob()), Err(Error::NotProposed));
+            assert_eq!(staking.initiate_recovery(bob(), django()), Ok(()));
+            set_timestamp(2 * MIN_RECOVERY_DELAY);
+            assert_eq!(staking.execute_recovery(bob()), Ok(()));
+            assert_eq!(staking.recovery_of(bob()), None);
+            assert!(staking.has_min_stake(django(), 1000));
+            assert!(!staking.has_min_stake(bob(), 1));
+            assert_eq!(staking.get_balance(django()), 1000);
+        }
+
+        /// Admin actions are kept in a bounded trail with the hash of their
+        /// parameters.
+        #[ink::test]
+        fn admin_log_records_the_actions() {
+            use ink_env::hash::{Blake2x256, HashOutput};
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_withdrawal_fee(300), Ok(()));
+            advance_blocks(3);
+            assert_eq!(staking.set_max_share(250), Ok(()));
+            let log = staking.admin_log(0, 10);
+            assert_eq!(log.len(), 2);
+            let mut params_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&300u32, &mut params_hash);
+            assert_eq!(
+                log[0],
+                AdminLogEntry {
+                    id: 0,
+                    action: AdminAction::SetWithdrawalFee,
+                    actor: alice(),
+                    params_hash: params_hash.into(),
+                    block: 0,
+                }
+            );
+            assert_eq!((log[1].id, log[1].action), (1, AdminAction::SetMaxShare));
+            assert_eq!(log[1].block, 3);
+            // Only the last `MAX_ADMIN_LOG` actions are kept.
+            for share in 0..MAX_ADMIN_LOG {
+                assert_eq!(staking.set_max_share(share), Ok(()));
             }
-            self.token.transfer_with_signature(
-                from, to, balance, deadline, nonce, sig, hash_out,
+            let log = staking.admin_log(0, MAX_ADMIN_LOG + 1);
+            assert_eq!(log.len() as u32, MAX_ADMIN_LOG);
+            assert_eq!(log[0].id, 2);
+            assert_eq!(staking.admin_log(MAX_ADMIN_LOG - 1, 5).len(), 1);
+        }
+
+        /// Fundings emitting outside the reward rate bounds are rejected.
+        #[ink::test]
+        fn reward_rate_stays_within_bounds() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 2 * 86_400);
+            set_sender(alice());
+            assert_eq!(staking.set_reward_rate_bounds(2, 3), Ok(()));
+            assert_eq!(staking.get_reward_rate_bounds(), (2, 3));
+            let out_of_bounds = |reward_rate| {
+                Err(Error::RewardRateOutOfBounds {
+                    reward_rate,
+                    min_reward_rate: 2,
+                    max_reward_rate: 3,
+                })
+            };
+            assert_eq!(staking.fund_rewards(86_400), out_of_bounds(1));
+            assert_eq!(staking.fund_rewards(4 * 86_400), out_of_bounds(4));
+            assert_eq!(mock::balance_of(alice()), 2 * 86_400);
+            assert_eq!(staking.fund_rewards(2 * 86_400), Ok(()));
+            assert_eq!(staking.get_reward_rate().0, 2);
+            // The rewards not emitted yet count towards the next period.
+            set_timestamp(INITIAL_STEP / 2);
+            mock::mint(alice(), 3 * 86_400);
+            assert_eq!(staking.fund_rewards(3 * 86_400), out_of_bounds(4));
+            assert_eq!(staking.fund_rewards(2 * 86_400), Ok(()));
+            assert_eq!(staking.get_reward_rate().0, 3);
+        }
+
+        /// The utilization scales the emitted rewards within the model's bounds.
+        #[ink::test]
+        fn rate_model_scales_with_utilization() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 10 * 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(10 * 86_400), Ok(()));
+            assert_eq!(staking.effective_reward_rate(), 10);
+            assert_eq!(
+                staking.set_rate_model(500, 2000, 1500),
+                Err(Error::InvalidRateModel {
+                    min_permille: 2000,
+                    max_permille: 1500
+                })
             );
+            assert_eq!(staking.set_rate_model(2000, 200, 1500), Ok(()));
+            assert_eq!(staking.effective_reward_rate(), 15);
+            assert_eq!(staking.set_rate_model(100, 200, 1500), Ok(()));
+            assert_eq!(staking.effective_reward_rate(), 2);
+            // Twice the target TVL halves the emission.
+            assert_eq!(staking.set_rate_model(500, 200, 1500), Ok(()));
+            assert_eq!(staking.effective_reward_rate(), 5);
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(staking.pending_rewards(bob()), 5 * 43_200);
         }
-    }
 
-    // Odded out Unit Test.
-    // module and test functions are marked with a `#[test]` attribute.
-    // The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        /// The accrual is suspended once it would exceed the funded rewards.
+        #[ink::test]
+        fn exhausted_rewards_suspend_the_accrual() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 11 * 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(10 * 86_400), Ok(()));
+            // Emits 15 per second out of 10 per second funded.
+            assert_eq!(staking.set_rate_model(2000, 200, 1500), Ok(()));
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 10 * 86_400);
+            set_sender(bob());
+            let emitted = ink_env::test::recorded_events().count();
+            assert_eq!(staking.claim_rewards(None), Ok(10 * 86_400));
+            let exhausted = ink_env::test::recorded_events().skip(emitted).any(|event| {
+                matches!(
+                    <Event as scale::Decode>::decode(&mut &event.data[..]),
+                    Ok(Event::RewardsExhausted(RewardsExhausted { accrued, available }))
+                        if accrued == 15 * 86_400 && available == 10 * 86_400
+                )
+            });
+            assert!(exhausted);
+            assert_eq!(staking.get_reward_rate(), (0, INITIAL_STEP, INITIAL_STEP));
+            set_timestamp(2 * INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 0);
+            assert!(staking.health().ledger_balanced);
+            // The next funding resumes the accrual.
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            assert_eq!(staking.get_reward_rate().0, 1);
+        }
 
-        use crate::staking::Staking;
-        use ink_env::DefaultEnvironment as Environment;
-        /// Imports `ink_lang` so we can use `#[ink::test]`.
-        use ink_lang as ink;
+        /// Stakes keep the schedule active at their creation while new stakes use
+        /// the latest one.
+        #[ink::test]
+        fn stakes_keep_their_schedule() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(
+                staking.add_schedule(INITIAL_STEP, vec![0, UNLOCK_PRECISION - 1]),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(staking.add_schedule(INITIAL_STEP, vec![0, UNLOCK_PRECISION]), Ok(1));
+            assert_eq!(staking.get_config().maturity, Balance::from(INITIAL_STEP));
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            assert_eq!(staking.schedule_of(bob(), 0), Some(0));
+            assert_eq!(staking.schedule_of(charlie(), 1), Some(1));
+            assert_eq!(staking.schedule_of(charlie(), 0), None);
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.get_balance(bob()), 500);
+            assert_eq!(staking.get_balance(charlie()), 1000);
+        }
 
-        fn set_sender(sender: AccountId) {
-            ink_env::test::set_caller::<Environment>(sender);
+        /// Rewards funded in the native currency are paid in it.
+        #[ink::test]
+        fn native_rewards_are_paid_natively() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_native_rewards(true), Ok(()));
+            mock::mint(alice(), 86_400);
+            assert_eq!(
+                staking.fund_rewards(86_400),
+                Err(Error::WrongRewardCurrency {
+                    native_rewards: true
+                })
+            );
+            set_balance(pool(), 86_400);
+            ink_env::test::set_value_transferred::<Environment>(86_400);
+            assert_eq!(staking.fund_native_rewards(), Ok(()));
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(staking.get_reward_rate().0, 1);
+            assert_eq!(
+                staking.set_native_rewards(false),
+                Err(Error::OutstandingBalances {
+                    staked: 1000,
+                    escrowed: 0,
+                    rewards: 86_400
+                })
+            );
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            let native = get_balance(bob());
+            assert_eq!(staking.claim_rewards(None), Ok(86_400));
+            assert_eq!(get_balance(bob()), native + 86_400);
+            assert_eq!(mock::balance_of(bob()), 0);
         }
 
-        fn default_accounts() -> ink_env::test::DefaultAccounts<Environment> {
-            ink_env::test::default_accounts::<Environment>()
+        /// The reward rate is retargeted once per epoch to emit the target value
+        /// at the oracle price, stretching or shortening the period.
+        #[ink::test]
+        fn reward_rate_follows_the_oracle_price() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            assert_eq!(staking.retarget_reward_rate(), Err(Error::NoOracle));
+            const EPOCH: Timestamp = 3_600_000;
+            assert_eq!(staking.set_price_oracle(Some(mock::oracle()), 2, EPOCH), Ok(()));
+            // A whole token of 10^12 units is quoted 10^12, i.e. 1 per unit.
+            mock::set_price(1_000_000_000_000);
+            set_timestamp(1000);
+            assert_eq!(staking.retarget_reward_rate(), Ok(2));
+            // The 86_399 rewards left last half as long at twice the rate.
+            assert_eq!(staking.get_reward_rate(), (2, 1000 + 43_199_500, INITIAL_STEP));
+            set_timestamp(2000);
+            assert_eq!(
+                staking.retarget_reward_rate(),
+                Err(Error::EpochNotElapsed {
+                    next_retarget: 1000 + EPOCH
+                })
+            );
+            mock::set_price(0);
+            set_timestamp(1000 + EPOCH);
+            assert_eq!(staking.retarget_reward_rate(), Err(Error::OracleFailed));
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 86_400);
+            assert_eq!(staking.retarget_reward_rate(), Err(Error::NoRewards));
         }
 
-        fn contract_id() -> AccountId {
-            ink_env::test::callee::<Environment>()
+        /// The timeline lists the future unlock steps aggregated across stakes.
+        #[ink::test]
+        fn unlock_timeline_aggregates_the_stakes() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(bob(), 2000);
+            assert_eq!(staking.stake(2000, None), Ok(1));
+            let step = |k: Timestamp| k * INITIAL_STEP;
+            assert_eq!(
+                staking.unlock_timeline(bob()),
+                vec![
+                    (step(1), 1500),
+                    (step(2), 300),
+                    (step(3), 300),
+                    (step(4), 300),
+                    (step(5), 300),
+                    (step(6), 300)
+                ]
+            );
+            set_timestamp(step(4));
+            assert_eq!(staking.unlock_timeline(bob()), vec![(step(5), 300), (step(6), 300)]);
+            set_timestamp(step(6));
+            assert_eq!(staking.unlock_timeline(bob()), vec![]);
+            assert_eq!(staking.unlock_timeline(charlie()), vec![]);
         }
 
-        fn alice() -> AccountId {
-            default_accounts().alice
+        /// The first update of each day checkpoints the staked principal and the
+        /// rewards paid so far.
+        #[ink::test]
+        fn history_checkpoints_each_day() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(HISTORY_PERIOD);
+            set_sender(bob());
+            assert_eq!(staking.claim_rewards(None), Ok(86_400));
+            set_timestamp(2 * HISTORY_PERIOD + 1);
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            let entry = |day, total_staked, rewards_paid| HistoryEntry {
+                day,
+                total_staked,
+                rewards_paid,
+            };
+            assert_eq!(
+                staking.history(0, 10),
+                vec![entry(0, 0, 0), entry(1, 1000, 0), entry(2, 1000, 86_400)]
+            );
+            assert_eq!(staking.history(1, 1), vec![entry(1, 1000, 0)]);
+            assert_eq!(staking.history(3, 1), vec![]);
         }
 
-        fn bob() -> AccountId {
-            default_accounts().bob
+        /// The audit exposes the accumulator values `pending_rewards` derives from.
+        #[ink::test]
+        fn audit_rewards_explains_the_pending_rewards() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            advance_blocks(5);
+            set_timestamp(INITIAL_STEP / 2);
+            mock::mint(charlie(), 3000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(3000, None), Ok(1));
+            set_timestamp(INITIAL_STEP);
+            // 43_200 over 1000, then 43_200 over 4000 weight units.
+            let stored = 43_200 * math::ACC_PRECISION / 1000;
+            let current = 54_000 * math::ACC_PRECISION / 1000;
+            assert_eq!(
+                staking.audit_rewards(bob()),
+                RewardAudit {
+                    reward_per_token_stored: stored,
+                    reward_per_token: current,
+                    last_update_time: INITIAL_STEP / 2,
+                    total_weight: 4000,
+                    weight: 1000,
+                    reward_debt: 0,
+                    settled: 0,
+                    pending: 54_000,
+                    last_update: 0,
+                }
+            );
+            let audit = staking.audit_rewards(charlie());
+            assert_eq!((audit.weight, audit.reward_debt), (3000, 129_600));
+            assert_eq!((audit.pending, audit.last_update), (32_400, 5));
+            assert_eq!(audit.pending, staking.pending_rewards(charlie()));
         }
 
-        fn charlie() -> AccountId {
-            default_accounts().charlie
+        /// A candidate schedule is evaluated without being added.
+        #[ink::test]
+        fn simulate_schedule_evaluates_candidates() {
+            let staking = pool_with_stake(1000);
+            let candidate = |unlocked: Vec<u32>| Schedule {
+                step: INITIAL_STEP,
+                unlocked: unlocked.into_iter().map(math::from_permille).collect(),
+            };
+            let halves = candidate(vec![0, 500, 1000]);
+            assert_eq!(staking.simulate_schedule(halves.clone(), 1000, 0), Ok(0));
+            assert_eq!(
+                staking.simulate_schedule(halves.clone(), 1000, INITIAL_STEP + 1),
+                Ok(500)
+            );
+            assert_eq!(staking.simulate_schedule(halves, 1000, 5 * INITIAL_STEP), Ok(1000));
+            assert_eq!(
+                staking.simulate_schedule(candidate(vec![0, 600, 500, 1000]), 1000, 0),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(
+                staking.simulate_schedule(candidate(vec![0, 900]), 1000, 0),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(staking.get_schedule(1), None);
         }
 
-        fn set_balance(account_id: AccountId, balance: Balance) {
-            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(
-                account_id, balance,
-            )
+        /// Anyone may claim for an opted-in account once the threshold is
+        /// reached, earning the tip.
+        #[ink::test]
+        fn claim_bot_claims_above_threshold() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(charlie());
+            assert_eq!(staking.claim_for_with_tip(bob()), Err(Error::NotOptedIn));
+            set_sender(bob());
+            let greedy = ClaimBot {
+                threshold: 0,
+                tip_bps: MAX_TIP_BPS + 1,
+            };
+            assert_eq!(
+                staking.set_claim_bot(Some(greedy)),
+                Err(Error::FeeTooHigh {
+                    fee_bps: MAX_TIP_BPS + 1,
+                    max_fee_bps: MAX_TIP_BPS
+                })
+            );
+            let bot = ClaimBot {
+                threshold: 600,
+                tip_bps: 100,
+            };
+            assert_eq!(staking.set_claim_bot(Some(bot)), Ok(()));
+            assert_eq!(staking.claim_bot_of(bob()), Some(bot));
+            set_timestamp(INITIAL_STEP);
+            set_sender(charlie());
+            assert_eq!(
+                staking.claim_for_with_tip(bob()),
+                Err(Error::BelowMinimum {
+                    amount: 500,
+                    minimum: 600
+                })
+            );
+            set_timestamp(2 * INITIAL_STEP);
+            assert_eq!(staking.claim_for_with_tip(bob()), Ok(6));
+            assert_eq!(mock::balance_of(charlie()), 6);
+            assert_eq!(mock::balance_of(bob()), 594);
+            set_sender(bob());
+            assert_eq!(staking.set_claim_bot(None), Ok(()));
+            assert_eq!(staking.claim_bot_of(bob()), None);
         }
 
-        fn get_balance(account_id: AccountId) -> Balance {
-            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(account_id)
-                .expect("Cannot get account balance")
+        /// Withheld fees are burned, together with the tokens bought back with
+        /// the transferred native currency.
+        #[ink::test]
+        fn buyback_burns_fees_and_bought_tokens() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.set_withdrawal_fee(600), Ok(()));
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            assert_eq!(staking.claim(400, None, None), Ok(380));
+            assert_eq!(staking.buyback_and_burn(0), Err(Error::NotOwner));
+            set_sender(alice());
+            assert_eq!(staking.buyback_and_burn(0), Ok(20));
+            assert_eq!(mock::total_supply(), 980);
+            assert_eq!(staking.buyback_and_burn(0), Err(Error::NoSurplus));
+            ink_env::test::set_value_transferred::<Environment>(50);
+            assert_eq!(staking.buyback_and_burn(0), Err(Error::NoRouter));
+            assert_eq!(staking.set_router(Some(mock::router())), Ok(()));
+            mock::mint(mock::router(), 50 * mock::SWAP_RATE);
+            assert_eq!(staking.buyback_and_burn(100), Ok(100));
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(mock::balance_of(mock::router()), 0);
+            assert_eq!(mock::total_supply(), 980);
+            assert_eq!(mock::balance_of(pool()), 620);
+            assert!(staking.health().ledger_balanced);
         }
-        /// We test if the default constructor does its job.
+
+        /// Guardians may pause but nothing else, resuming is up to the owner.
         #[ink::test]
-        fn default_works() {
-            use ink_env::hash::{HashOutput, Sha2x256};
-            let input: &[u8] = &[13, 14, 15];
-            let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
-            let hash = ink_env::hash_bytes::<Sha2x256>(input, &mut output);
-            // acNMiQfPMbgTyM1ABJiXFiE7WnB2pitAV7TpJScmPAVB5Sr
-            const erc20_hash: [u8; 32] = [
-                0x7c, 0x96, 0x32, 0x97, 0xc5, 0xb0, 0x80, 0x7c, 0xe4, 0x13, 0xa5, 0xeb,
-                0x8d, 0x87, 0x77, 0x9b, 0x10, 0x5a, 0xd2, 0x50, 0xeb, 0xd1, 0x8e, 0x21,
-                0x65, 0xe8, 0xb5, 0x6c, 0xbd, 0x5f, 0x67, 0xbf,
-            ];
-            ink_env::debug_println!("{:?}", erc20_hash);
-            let staking = Staking::new_init(erc20_hash.into());
+        fn guardian_can_only_pause() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(django());
+            assert_eq!(staking.pause(), Err(Error::MissingRole(Role::Guardian)));
+            set_sender(alice());
+            assert_eq!(staking.grant_role(Role::Guardian, django()), Ok(()));
+            set_sender(django());
+            assert_eq!(staking.pause(), Ok(()));
+            assert!(staking.get_config().paused);
+            assert_eq!(staking.set_paused(false), Err(Error::NotOwner));
+            assert_eq!(staking.set_withdrawal_fee(0), Err(Error::NotOwner));
+            mature();
+            set_sender(bob());
+            assert_eq!(staking.claim_all(false), Err(Error::Paused));
+            set_sender(alice());
+            assert_eq!(staking.set_paused(false), Ok(()));
+            set_sender(bob());
+            assert_eq!(staking.claim_all(false), Ok(1000));
+        }
+
+        /// Partner dApps read the fee discount tier of the staked principal.
+        #[ink::test]
+        fn discount_tiers_follow_the_principal() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(
+                staking.set_discount_tiers(vec![500, 500]),
+                Err(Error::InvalidDiscountTiers)
+            );
+            let too_many = (1..=MAX_DISCOUNT_TIERS as Balance + 1).collect();
+            assert_eq!(
+                staking.set_discount_tiers(too_many),
+                Err(Error::InvalidDiscountTiers)
+            );
+            assert_eq!(staking.set_discount_tiers(vec![500, 1000, 5000]), Ok(()));
+            assert_eq!(staking.discount_tier_of(bob()), 2);
+            assert_eq!(staking.discount_tier_of(charlie()), 0);
+            mock::mint(charlie(), 500);
+            set_sender(charlie());
+            assert_eq!(staking.stake(500, None), Ok(1));
+            assert_eq!(staking.discount_tiers_of(vec![bob(), charlie(), django()]), vec![2, 1, 0]);
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            assert_eq!(staking.claim(1, None, None), Ok(1));
+            assert_eq!(staking.discount_tier_of(bob()), 1);
+        }
+
+        /// The report compares the amounts owed with the tokens held.
+        #[ink::test]
+        fn obligations_report_reveals_shortfalls() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.set_withdrawal_fee(600), Ok(()));
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            assert_eq!(staking.claim(400, None, None), Ok(380));
+            let report = ObligationsReport {
+                principal: 600,
+                rewards_accrued: 86_400,
+                native_rewards: false,
+                escrow: 0,
+                fees: 20,
+                token_balance: 1000 + 86_400 - 380,
+                invested: 0,
+                shortfall: 0,
+                timestamp: INITIAL_STEP,
+            };
+            assert_eq!(staking.obligations_report(), report);
+            // Tokens leaving the pool behind its back.
+            let transfer = ink_lang::selector_bytes!("transfer");
+            mock::call(pool(), mock::token(), transfer, &(django(), 1000u128).encode(), 0);
+            let report = staking.obligations_report();
+            assert_eq!((report.token_balance, report.shortfall), (86_020, 1000));
+            set_sender(charlie());
+            assert_eq!(staking.publish_report(), Ok(()));
+            assert_eq!(
+                staking.publish_report(),
+                Err(Error::ReportTooEarly {
+                    next_report: INITIAL_STEP + REPORT_INTERVAL
+                })
+            );
+        }
+
+        /// Rewards owed at a reward token migration stay claimable in the old
+        /// token during the grace period.
+        #[ink::test]
+        fn reward_token_migration_keeps_legacy_rewards() {
+            let mut staking = pool_with_stake(1000);
+            let new_token = AccountId::from([0x90; 32]);
+            const GRACE: Timestamp = 7 * 86400_000;
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(
+                staking.migrate_reward_token(Some(new_token), GRACE),
+                Err(Error::RewardPeriodActive {
+                    period_finish: INITIAL_STEP
+                })
+            );
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.migrate_reward_token(Some(new_token), GRACE), Ok(()));
+            assert_eq!(staking.get_reward_token(), Some(new_token));
+            let claim_deadline = INITIAL_STEP + GRACE;
+            assert_eq!(
+                staking.legacy_rewards_of(bob()),
+                Some((86_400, None, claim_deadline))
+            );
+            assert_eq!(staking.pending_rewards(bob()), 0);
+            assert_eq!(
+                staking.migrate_reward_token(None, GRACE),
+                Err(Error::LegacyRewardsActive { claim_deadline })
+            );
+            set_sender(bob());
+            assert_eq!(staking.claim_legacy_rewards(), Ok(86_400));
+            assert_eq!(mock::balance_of(bob()), 86_400);
+            assert_eq!(staking.claim_legacy_rewards(), Err(Error::NoRewards));
+            set_timestamp(claim_deadline + 1);
+            assert_eq!(
+                staking.claim_legacy_rewards(),
+                Err(Error::GracePeriodOver { claim_deadline })
+            );
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// Claims go to the payout address once its timelock elapsed.
+        #[ink::test]
+        fn payout_address_receives_the_claims() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.set_payout_address(django()), Err(Error::NotProposed));
+            assert_eq!(staking.propose_payout_address(django()), Ok(()));
+            assert_eq!(staking.set_payout_address(charlie()), Err(Error::NotProposed));
+            assert_eq!(
+                staking.set_payout_address(django()),
+                Err(Error::TimelockActive {
+                    now: 0,
+                    eta: PAYOUT_TIMELOCK
+                })
+            );
+            set_timestamp(PAYOUT_TIMELOCK);
+            assert_eq!(staking.set_payout_address(django()), Ok(()));
+            assert_eq!(staking.payout_address_of(bob()), django());
+            assert_eq!(staking.claim(600, None, None), Ok(600));
+            assert_eq!(mock::balance_of(django()), 600);
+            assert_eq!(mock::balance_of(bob()), 0);
+            // Proposing the account itself removes the payout address.
+            assert_eq!(staking.propose_payout_address(bob()), Ok(()));
+            set_timestamp(2 * PAYOUT_TIMELOCK);
+            assert_eq!(staking.set_payout_address(bob()), Ok(()));
+            assert_eq!(staking.payout_address_of(bob()), bob());
+            assert_eq!(staking.claim(100, None, None), Ok(100));
+            assert_eq!(mock::balance_of(bob()), 100);
+        }
+
+        /// An account holds at most `MAX_STAKES` stakes, bounding the loops over
+        /// them, and frees slots by claiming.
+        #[ink::test]
+        fn stake_capacity_bounds_the_entries() {
+            let mut staking = pool_with_stake(1000);
+            let count = MAX_STAKES as Balance;
+            mock::mint(bob(), count);
+            for id in 1..MAX_STAKES {
+                assert_eq!(staking.stake(1, None), Ok(id as StakeId));
+            }
+            assert_eq!(
+                staking.stake(1, None),
+                Err(Error::TooManyStakes {
+                    maximum: MAX_STAKES as u32
+                })
+            );
+            mature();
+            assert_eq!(staking.claim_all(false), Ok(1000 + count - 1));
+            assert_eq!(staking.stake(1, None), Ok(MAX_STAKES as StakeId));
+        }
+
+        /// Claims paying out less than the expected minimum change nothing.
+        #[ink::test]
+        fn claims_respect_the_expected_output() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            // The fee is raised between the preview and the claim.
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.preview_claim(bob(), 400).net, 400);
+            assert_eq!(staking.set_withdrawal_fee(600), Ok(()));
+            set_sender(bob());
+            assert_eq!(
+                staking.claim(400, Some(400), None),
+                Err(Error::InsufficientOutput {
+                    out: 380,
+                    min_expected_out: 400
+                })
+            );
+            assert_eq!(mock::balance_of(bob()), 0);
+            assert_eq!(staking.get_balance(bob()), 500);
+            assert_eq!(staking.claim(400, Some(380), None), Ok(380));
+            assert_eq!(
+                staking.claim_rewards(Some(86_401)),
+                Err(Error::InsufficientOutput {
+                    out: 86_400,
+                    min_expected_out: 86_401
+                })
+            );
+            assert_eq!(staking.claim_rewards(Some(86_400)), Ok(86_400));
+        }
+
+        /// Claims release the oldest stakes first and track the released amount
+        /// on each stake.
+        #[ink::test]
+        fn claims_track_the_release_per_stake() {
+            let mut staking = pool_with_stake(1000);
+            set_timestamp(INITIAL_STEP / 2);
+            mock::mint(bob(), 1000);
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            set_timestamp(2 * INITIAL_STEP);
+            // 600 of the first stake and 500 of the second are unlocked.
+            assert_eq!(staking.get_balance(bob()), 1100);
+            assert_eq!(staking.claim(800, None, None), Ok(800));
+            let start = INITIAL_STEP / 2;
+            assert_eq!(
+                staking.stakes_flat(bob()),
+                vec![
+                    (0, 1000, 600, 0, MULTIPLIER_BASE, 0),
+                    (1, 1000, 200, start, MULTIPLIER_BASE, 0)
+                ]
+            );
+            assert_eq!(staking.get_balance(bob()), 300);
+            assert!(staking.has_min_stake(bob(), 1200));
+        }
+
+        /// A gift is staked once the recipient accepts it, otherwise the funder
+        /// gets it back after the window.
+        #[ink::test]
+        fn gifts_stake_on_acceptance() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(django(), 500);
+            set_sender(django());
+            assert_eq!(staking.gift_stake(charlie(), 300), Ok(0));
+            assert_eq!(staking.gift_stake(bob(), 200), Ok(1));
+            assert_eq!(mock::balance_of(django()), 0);
+            assert_eq!(staking.accept_gift(0), Err(Error::UnknownGift { gift_id: 0 }));
+            assert_eq!(
+                staking.cancel_gift(1),
+                Err(Error::GiftPending {
+                    expires_at: GIFT_WINDOW
+                })
+            );
+            set_timestamp(GIFT_WINDOW);
+            set_sender(charlie());
+            assert_eq!(staking.accept_gift(0), Ok(2));
+            assert_eq!(staking.gift_of(0), None);
+            assert_eq!(staking.stakes_flat(charlie())[0].3, GIFT_WINDOW);
+            set_timestamp(GIFT_WINDOW + 1);
+            set_sender(bob());
+            assert_eq!(
+                staking.accept_gift(1),
+                Err(Error::GiftExpired {
+                    expires_at: GIFT_WINDOW
+                })
+            );
+            set_sender(django());
+            assert_eq!(staking.cancel_gift(1), Ok(200));
+            assert_eq!(mock::balance_of(django()), 200);
+            assert_eq!(staking.get_total_staked(), 1300);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// Matured balances sold over the counter are escrowed until the buyer
+        /// pays or the seller cancels.
+        #[ink::test]
+        fn otc_offers_escrow_the_balance() {
+            let mut staking = pool_with_stake(1000);
+            mature();
+            assert_eq!(staking.list_otc(charlie(), 400, mock::token(), 300), Ok(0));
+            assert_eq!(staking.get_balance(bob()), 600);
+            assert_eq!(staking.otc_offer(0).map(|offer| offer.amount), Some(400));
+            mock::mint(charlie(), 300);
+            set_sender(django());
+            assert_eq!(staking.take_otc(0), Err(Error::UnknownOffer { offer_id: 0 }));
+            set_sender(charlie());
+            assert_eq!(staking.take_otc(0), Ok(400));
+            assert_eq!(staking.otc_offer(0), None);
+            assert_eq!(mock::balance_of(charlie()), 400);
+            assert_eq!(mock::balance_of(bob()), 300);
+            set_sender(bob());
+            assert_eq!(staking.list_otc(charlie(), 600, mock::token(), 1), Ok(1));
+            set_sender(charlie());
+            assert_eq!(staking.cancel_otc(1), Err(Error::UnknownOffer { offer_id: 1 }));
+            set_sender(bob());
+            assert_eq!(staking.cancel_otc(1), Ok(600));
+            assert_eq!(mock::balance_of(bob()), 900);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// While `eoa_only` is set, only allowlisted contracts may stake.
+        #[ink::test]
+        fn eoa_only_rejects_unlisted_contracts() {
+            // The default callee of the off-chain environment is a contract account.
+            let integrator = contract_id();
+            let mut staking = pool_with_stake(1000);
+            mock::mint(integrator, 100);
+            mock::mint(bob(), 100);
+            set_sender(bob());
+            assert_eq!(staking.set_eoa_only(true), Err(Error::NotOwner));
+            set_sender(alice());
+            assert_eq!(staking.set_eoa_only(true), Ok(()));
+            assert!(staking.get_config().eoa_only);
+            set_sender(integrator);
+            assert_eq!(
+                staking.stake(100, None),
+                Err(Error::ContractCaller { caller: integrator })
+            );
+            set_sender(bob());
+            assert_eq!(staking.stake(100, None), Ok(1));
+            set_sender(alice());
+            assert_eq!(staking.set_integrator(integrator, true), Ok(()));
+            assert!(staking.is_integrator(integrator));
+            set_sender(integrator);
+            assert_eq!(staking.stake(100, None), Ok(2));
+        }
+
+        /// Moving a stake to a longer tier keeps its start and raises its multiplier.
+        #[ink::test]
+        fn change_tier_extends_the_lock() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.add_schedule(INITIAL_STEP, vec![0, UNLOCK_PRECISION]), Ok(1));
+            let unlocked = vec![0, 500, 600, 700, 800, 900, UNLOCK_PRECISION];
+            assert_eq!(staking.add_schedule(2 * INITIAL_STEP, unlocked), Ok(2));
+            assert_eq!(staking.set_tier_multiplier(3, Some(1500)), Err(Error::InvalidSchedule));
+            assert_eq!(
+                staking.set_tier_multiplier(2, Some(MULTIPLIER_BASE - 1)),
+                Err(Error::InvalidMultiplier {
+                    multiplier: MULTIPLIER_BASE - 1
+                })
+            );
+            assert_eq!(staking.set_tier_multiplier(1, Some(1200)), Ok(()));
+            assert_eq!(staking.set_tier_multiplier(2, Some(1500)), Ok(()));
+            assert_eq!(staking.tier_multiplier(2), Some(1500));
+            set_sender(bob());
+            assert_eq!(staking.change_tier(0, 3), Err(Error::UnknownTier { tier: 3 }));
+            assert_eq!(
+                staking.change_tier(0, 1),
+                Err(Error::TierDowngrade {
+                    current: 0,
+                    requested: 1
+                })
+            );
+            assert_eq!(staking.change_tier(0, 2), Ok(1500));
+            assert_eq!(staking.schedule_of(bob(), 0), Some(2));
+            assert_eq!(staking.get_staked_multiplier(bob(), 0), 1500);
+            assert_eq!(staking.get_staked_timestamp(bob(), 0), 0);
+            set_timestamp(6 * INITIAL_STEP);
+            assert_eq!(staking.get_balance(bob()), 700);
+        }
+
+        /// The backup owner takes over once the owner left the pool paused and
+        /// inactive for the failover interval.
+        #[ink::test]
+        fn backup_owner_takes_over_an_inactive_owner() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(
+                staking.set_backup_owner(Some(charlie()), INITIAL_STEP),
+                Err(Error::FailoverIntervalTooShort {
+                    interval: INITIAL_STEP,
+                    min_interval: MIN_FAILOVER_INTERVAL
+                })
+            );
+            assert_eq!(staking.set_backup_owner(Some(charlie()), MIN_FAILOVER_INTERVAL), Ok(()));
+            assert_eq!(staking.failover_at(), None);
+            set_sender(django());
+            assert_eq!(staking.assume_ownership(), Err(Error::NotOwner));
+            set_sender(charlie());
+            assert_eq!(
+                staking.assume_ownership(),
+                Err(Error::OwnerActive { failover_at: None })
+            );
+            set_timestamp(INITIAL_STEP);
+            set_sender(alice());
+            assert_eq!(staking.pause(), Ok(()));
+            assert_eq!(staking.failover_at(), Some(INITIAL_STEP + MIN_FAILOVER_INTERVAL));
+            set_timestamp(2 * INITIAL_STEP);
+            assert_eq!(staking.heartbeat(), Ok(()));
+            let failover_at = 2 * INITIAL_STEP + MIN_FAILOVER_INTERVAL;
+            set_timestamp(failover_at - 1);
+            set_sender(charlie());
+            assert_eq!(
+                staking.assume_ownership(),
+                Err(Error::OwnerActive {
+                    failover_at: Some(failover_at)
+                })
+            );
+            set_timestamp(failover_at);
+            assert_eq!(staking.assume_ownership(), Ok(()));
+            assert_eq!(staking.get_config().owner, charlie());
+            assert_eq!(staking.failover_at(), None);
+            set_sender(alice());
+            assert_eq!(staking.heartbeat(), Err(Error::NotOwner));
+        }
+
+        /// Excluded accounts keep their accrued rewards but earn nothing more.
+        #[ink::test]
+        fn excluded_accounts_stop_earning() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            assert_eq!(staking.set_reward_excluded(charlie(), true), Err(Error::NotOwner));
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP / 2);
+            assert_eq!(staking.set_reward_excluded(charlie(), true), Ok(()));
+            assert!(staking.is_reward_excluded(charlie()));
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.pending_rewards(bob()), 64_800);
+            assert_eq!(staking.pending_rewards(charlie()), 21_600);
+            set_sender(charlie());
+            assert_eq!(staking.claim_rewards(None), Ok(21_600));
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// New stakers holding little of the token get their storage deposit
+        /// from the rent pot, once and while it lasts.
+        #[ink::test]
+        fn rent_pot_sponsors_new_stakers() {
+            let mut staking = pool_with_stake(1000);
+            let eve = default_accounts().eve;
+            set_sender(alice());
+            assert_eq!(staking.set_rent_sponsorship(500, 10), Ok(()));
+            set_balance(pool(), 20);
+            ink_env::test::set_value_transferred::<Environment>(20);
+            assert_eq!(staking.fund_rent_pot(), Ok(()));
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(staking.rent_sponsorship(), (20, 500, 10));
+            for (staker, amount) in [(charlie(), 400), (django(), 600)] {
+                mock::mint(staker, amount);
+            }
+            let (charlie_native, django_native) = (get_balance(charlie()), get_balance(django()));
+            set_sender(django());
+            assert_eq!(staking.stake(600, None), Ok(1));
+            assert_eq!(get_balance(django()), django_native);
+            set_sender(charlie());
+            assert_eq!(staking.stake(200, None), Ok(2));
+            assert_eq!(staking.stake(200, None), Ok(3));
+            assert_eq!(get_balance(charlie()), charlie_native + 10);
+            mock::mint(eve, 100);
+            set_sender(eve);
+            assert_eq!(staking.stake(100, None), Ok(4));
+            assert_eq!(staking.rent_sponsorship().0, 0);
+            set_sender(alice());
+            assert_eq!(
+                staking.withdraw_rent_pot(1),
+                Err(Error::InsufficientBalance {
+                    balance: 0,
+                    required: 1
+                })
+            );
+        }
+
+        /// Accounts can only be closed once fully exited, closing drops their entries.
+        #[ink::test]
+        fn close_account_needs_a_full_exit() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.close_account(), Err(Error::AccountInUse));
+            assert_eq!(staking.propose_payout_address(django()), Ok(()));
+            set_timestamp(PAYOUT_TIMELOCK);
+            assert_eq!(staking.set_payout_address(django()), Ok(()));
+            mature();
+            let emitted = ink_env::test::recorded_events().count();
+            assert_eq!(staking.claim_all(true), Ok(1000));
+            assert_eq!(mock::balance_of(django()), 1000);
+            let closed = ink_env::test::recorded_events().skip(emitted).any(|event| {
+                matches!(
+                    <Event as scale::Decode>::decode(&mut &event.data[..]),
+                    Ok(Event::AccountClosed(AccountClosed { account })) if account == bob()
+                )
+            });
+            assert!(closed);
+            assert_eq!(staking.payout_address_of(bob()), bob());
+            assert_eq!(staking.close_account(), Ok(()));
+        }
+
+        /// Tokens sent with `transfer_and_call` are staked for their sender.
+        #[ink::test]
+        fn received_tokens_are_staked_for_the_sender() {
+            let mut staking = pool_with_stake(1000);
+            // The token contract moved the tokens before notifying the pool.
+            mock::mint(pool(), 500);
+            set_sender(charlie());
+            assert_eq!(
+                staking.on_token_received(charlie(), 500, Vec::new()),
+                Err(Error::InvalidToken)
+            );
+            set_sender(mock::token());
+            assert_eq!(
+                staking.on_token_received(charlie(), 500, vec![1]),
+                Err(Error::InvalidPayload)
+            );
+            assert_eq!(
+                staking.on_token_received(charlie(), 0, Vec::new()),
+                Err(Error::BelowMinimum {
+                    amount: 0,
+                    minimum: 1
+                })
+            );
+            assert_eq!(staking.on_token_received(charlie(), 500, Vec::new()), Ok(()));
+            assert_eq!(staking.get_staked_amount(charlie(), 0), 500);
+            assert_eq!(staking.get_staked_amount(django(), 0), 0);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// Schedules unlock in parts of `UNLOCK_PRECISION`, finer than 10% steps.
+        #[ink::test]
+        fn schedules_unlock_in_parts_of_the_precision() {
+            let mut staking = pool_with_stake(1000);
+            assert_eq!(staking.unlock_precision(), UNLOCK_PRECISION);
+            set_sender(alice());
+            let unlocked = vec![0, UNLOCK_PRECISION / 40, UNLOCK_PRECISION];
+            assert_eq!(staking.add_schedule(INITIAL_STEP, unlocked), Ok(1));
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            set_timestamp(INITIAL_STEP);
+            assert_eq!(staking.get_unstakable(0), Balance::from(UNLOCK_PRECISION / 40));
+            assert_eq!(staking.get_balance(charlie()), 25);
+            assert_eq!(staking.get_balance(bob()), 500);
+        }
+
+        /// Linear schedules release an equal part per step period.
+        #[ink::test]
+        fn linear_schedules_release_per_step() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            for (duration, step) in [
+                (4 * INITIAL_STEP, 0),
+                (4 * INITIAL_STEP, 3 * INITIAL_STEP),
+                (MAX_SCHEDULE_STEPS as Timestamp + 1, 1),
+            ] {
+                assert_eq!(
+                    staking.add_linear_schedule(duration, step),
+                    Err(Error::InvalidStepPeriod { step, duration })
+                );
+            }
+            assert_eq!(staking.add_linear_schedule(4 * INITIAL_STEP, INITIAL_STEP), Ok(1));
+            let quarter = UNLOCK_PRECISION / 4;
+            assert_eq!(
+                staking.get_schedule(1).map(|schedule| schedule.unlocked),
+                Some(vec![0, quarter, 2 * quarter, 3 * quarter, UNLOCK_PRECISION])
+            );
+            mock::mint(charlie(), 1000);
+            set_sender(charlie());
+            assert_eq!(staking.stake(1000, None), Ok(1));
+            set_timestamp(2 * INITIAL_STEP + INITIAL_STEP / 2);
+            assert_eq!(staking.get_balance(charlie()), 500);
+        }
+
+        /// A trapping token fails the message with `TokenCallFailed` instead of
+        /// trapping the pool, and nothing is booked.
+        #[ink::test]
+        fn trapping_token_fails_the_call() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 500);
+            set_sender(charlie());
+            let selector = ink_lang::selector_bytes!("transfer_with_signature");
+            mock::fail(selector);
+            assert_eq!(
+                staking.stake(500, None),
+                Err(Error::TokenCallFailed {
+                    token: mock::token(),
+                    selector
+                })
+            );
+            assert_eq!(staking.get_staked_amount(charlie(), 0), 0);
+            assert_eq!(mock::balance_of(charlie()), 500);
+            mock::restore(selector);
+            assert_eq!(staking.stake(500, None), Ok(1));
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// A position whose principal can't be moved stays in the pool untouched.
+        #[ink::test]
+        fn failed_transfer_keeps_the_position() {
+            let mut staking = pool_with_stake(1000);
+            set_sender(alice());
+            assert_eq!(staking.deprecate(mock::successor()), Ok(()));
+            let selector = ink_lang::selector_bytes!("transfer_with_signature");
+            mock::fail(selector);
+            set_sender(bob());
+            assert_eq!(
+                staking.migrate_position(0),
+                Err(Error::TokenCallFailed {
+                    token: mock::token(),
+                    selector
+                })
+            );
+            assert_eq!(mock::imports(), vec![]);
+            assert_eq!(staking.get_staked_amount(bob(), 0), 1000);
+            assert_eq!(mock::balance_of(pool()), 1000);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// Deposits wait for confirmation and the unconfirmed part is refundable.
+        #[ink::test]
+        fn pending_deposits_are_staked_or_refunded() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(charlie(), 500);
+            set_sender(charlie());
+            assert_eq!(staking.deposit(500), Ok(()));
+            assert_eq!(staking.pending_deposit_of(charlie()), 500);
+            assert_eq!(mock::balance_of(pool()), 1500);
+            assert_eq!(
+                staking.confirm_stake(600),
+                Err(Error::InsufficientBalance {
+                    balance: 500,
+                    required: 600
+                })
+            );
+            assert_eq!(staking.confirm_stake(300), Ok(1));
+            assert_eq!(staking.get_staked_amount(charlie(), 0), 300);
+            assert_eq!(staking.cancel_pending(), Ok(200));
+            assert_eq!(staking.cancel_pending(), Err(Error::NoPendingDeposit));
+            assert_eq!(mock::balance_of(charlie()), 200);
+            assert!(staking.health().ledger_balanced);
+        }
+
+        /// The faucet of demo pools mints up to `DEMO_FAUCET_LIMIT` whole tokens.
+        #[cfg(feature = "demo")]
+        #[ink::test]
+        fn faucet_mints_up_to_the_limit() {
+            let mut staking = pool_with_stake(1000);
+            let maximum = DEMO_FAUCET_LIMIT * 1_000_000_000_000;
+            set_sender(charlie());
+            assert_eq!(
+                staking.faucet(maximum + 1),
+                Err(Error::AboveMaximum {
+                    amount: maximum + 1,
+                    maximum
+                })
+            );
+            assert_eq!(staking.faucet(maximum), Ok(()));
+            assert_eq!(mock::balance_of(charlie()), maximum);
+            assert_eq!(mock::balance_of(pool()), 1000);
+        }
+
+        /// Scaled pools run the schedules `time_scale` times faster.
+        #[ink::test]
+        fn time_scale_accelerates_the_schedules() {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_timestamp(0);
+            set_sender(alice());
+            let mut staking = Staking::new_scaled(
+                mock::token(),
+                0,
+                MULTIPLIER_BASE,
+                0,
+                INITIAL_STEP,
+                60,
+                false,
+            );
+            assert_eq!(staking.time_scale(), 60);
+            mock::mint(bob(), 1000);
+            set_sender(bob());
+            assert_eq!(staking.stake(1000, None), Ok(0));
+            set_timestamp(INITIAL_STEP / 60);
+            assert_eq!(staking.get_balance(bob()), 500);
+            set_timestamp(6 * INITIAL_STEP / 60);
+            assert_eq!(staking.get_balance(bob()), 1000);
+        }
+
+        /// Production chains refuse a time scale.
+        #[ink::test]
+        #[should_panic(expected = "time scale refused on a production chain")]
+        fn time_scale_is_refused_in_production() {
+            ink_env::test::set_callee::<Environment>(pool());
+            set_sender(alice());
+            let _ = Staking::new_scaled(
+                mock::token(),
+                0,
+                MULTIPLIER_BASE,
+                0,
+                INITIAL_STEP,
+                1440,
+                true,
+            );
+        }
+
+        /// The flat views return the structured views as tuples of primitives.
+        #[ink::test]
+        fn flat_views_mirror_the_structured_views() {
+            let mut staking = pool_with_stake(1000);
+            mock::mint(alice(), 86_400);
+            set_sender(alice());
+            assert_eq!(staking.set_withdrawal_fee(600), Ok(()));
+            assert_eq!(staking.fund_rewards(86_400), Ok(()));
+            set_timestamp(INITIAL_STEP);
+            set_sender(bob());
+            assert_eq!(staking.claim(400, None, None), Ok(380));
+            assert_eq!(staking.stakes_flat(bob()), vec![(0, 1000, 400, 0, MULTIPLIER_BASE, 0)]);
+            assert_eq!(staking.account_summary_flat(bob()), (600, 100, 86_400, 0, 0, 1));
+            assert_eq!(
+                staking.obligations_flat(),
+                (600, 86_400, 0, 20, 1000 + 86_400 - 380, 0, 0, INITIAL_STEP)
+            );
+        }
+    }
+
+    /// Canonical input/output tables of the unlock formula, taken from the
+    /// written spec of the initial schedule. A refactor of the unlock math or
+    /// of `get_unstakable` must keep reproducing these numbers.
+    #[cfg(test)]
+    mod spec_vectors {
+        use super::*;
+
+        /// A step of the initial schedule, a minute with the `demo` feature.
+        const DAY: Balance = INITIAL_STEP as Balance;
+
+        /// (age of the stake in ms, unlocked per mille).
+        const UNLOCK_VECTORS: [(Balance, Balance); 19] = [
+            (0, 0),
+            (1, 0),
+            (DAY - 1, 0),
+            (DAY, 500),
+            (DAY + 1, 500),
+            (2 * DAY - 1, 500),
+            (2 * DAY, 600),
+            (3 * DAY - 1, 600),
+            (3 * DAY, 700),
+            (4 * DAY - 1, 700),
+            (4 * DAY, 800),
+            (5 * DAY - 1, 800),
+            (5 * DAY, 900),
+            (6 * DAY - 1, 900),
+            (6 * DAY, 1000),
+            (6 * DAY + 1, 1000),
+            (7 * DAY, 1000),
+            (365 * DAY, 1000),
+            (Balance::MAX, 1000),
+        ];
+
+        #[test]
+        fn initial_schedule_matches_spec() {
+            let schedule = Schedule::initial();
+            assert!(schedule.is_valid());
+            assert_eq!(schedule.maturity(), 6 * DAY);
+            for (age, permille) in UNLOCK_VECTORS {
+                let fraction = Balance::from(math::from_permille(permille as u32));
+                assert_eq!(schedule.unlocked_at(age), fraction, "age {}", age);
+            }
         }
     }
 }
@@ -0,0 +1,241 @@
+//! Unlock and reward math as pure functions.
+//!
+//! Nothing in here touches the contract storage or the ink! environment, so
+//! the formulas can be unit-, fuzz- and mutation-tested on their own. The
+//! contract only reads its state, calls into this module and writes back.
+
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use primitive_types::U256;
+use staking_traits::{Balance, Timestamp};
+
+/// Scale of the accumulated reward per unit of weight.
+pub const ACC_PRECISION: Balance = 1_000_000_000_000;
+
+/// Denominator of the unlocked fractions of a schedule: per mille, or parts
+/// per million with the `unlock-ppm` feature for schedules finer than 0.1%
+/// steps and less rounding loss on small stakes.
+#[cfg(not(feature = "unlock-ppm"))]
+pub const UNLOCK_PRECISION: u32 = 1000;
+#[cfg(feature = "unlock-ppm")]
+pub const UNLOCK_PRECISION: u32 = 1_000_000;
+
+/// Converts `permille` to parts of `UNLOCK_PRECISION`.
+pub const fn from_permille(permille: u32) -> u32 {
+    permille * (UNLOCK_PRECISION / 1000)
+}
+
+/// Step of the initial schedule in ms: a day, or a minute in demo pools so
+/// that the whole lifecycle can be exercised within minutes.
+#[cfg(not(feature = "demo"))]
+pub const INITIAL_STEP: Timestamp = 86400_000;
+#[cfg(feature = "demo")]
+pub const INITIAL_STEP: Timestamp = 60_000;
+
+/// Per mille unlocked by the initial schedule after each number of full steps:
+/// half after the first step, then 10% more per step until the sixth.
+pub const INITIAL_UNLOCKED_PERMILLE: [u32; 7] = [0, 500, 600, 700, 800, 900, 1000];
+
+/// Reward multiplier of a stake without bonus.
+pub const MULTIPLIER_BASE: u32 = 1000;
+
+/// Rounding mode of the integer division in the unlock math.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, PackedLayout, SpreadLayout, scale::Encode, scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum Rounding {
+    /// Round towards zero.
+    Floor,
+    /// Round away from zero.
+    Ceil,
+    /// Round half to even (banker's rounding).
+    HalfEven,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Rounding::Floor
+    }
+}
+
+/// Computes `a * b / denominator` applying `rounding`.
+///
+/// # Note
+///
+/// The product is computed in 256 bits so that large balances of
+/// high-decimal tokens can't overflow the intermediate value.
+/// Panics if the final quotient doesn't fit into a `Balance`.
+pub fn mul_div(a: Balance, b: Balance, denominator: Balance, rounding: Rounding) -> Balance {
+    let denominator = U256::from(denominator);
+    let (quotient, remainder) = (U256::from(a) * U256::from(b)).div_mod(denominator);
+    let round_up = match rounding {
+        Rounding::Floor => false,
+        Rounding::Ceil => !remainder.is_zero(),
+        Rounding::HalfEven => {
+            remainder * 2 > denominator || (remainder * 2 == denominator && quotient.bit(0))
+        }
+    };
+    let quotient = if round_up { quotient + 1 } else { quotient };
+    Balance::try_from(quotient).expect("quotient exceeds Balance")
+}
+
+/// Returns the parts of `UNLOCK_PRECISION` unlocked at `age` ms by a schedule
+/// of `step` ms long steps, unlocking `unlocked[n]` after `n` full steps and
+/// the last entry from then on. An empty table unlocks everything.
+pub fn unlocked_fraction(step: Timestamp, unlocked: &[u32], age: Balance) -> Balance {
+    let steps = age / Balance::from(step);
+    let last = unlocked.len().saturating_sub(1);
+    let index = (steps.min(last as Balance)) as usize;
+    unlocked.get(index).copied().unwrap_or(UNLOCK_PRECISION).into()
+}
+
+/// Returns the part of `amount` unlocked at `fraction` parts of
+/// `UNLOCK_PRECISION`.
+pub fn unlocked_amount(fraction: Balance, amount: Balance, rounding: Rounding) -> Balance {
+    mul_div(fraction, amount, UNLOCK_PRECISION.into(), rounding)
+}
+
+/// Returns the reward weight of `principal` staked at `multiplier` per mille.
+pub fn weight(principal: Balance, multiplier: u32) -> Balance {
+    mul_div(
+        principal,
+        multiplier.into(),
+        MULTIPLIER_BASE.into(),
+        Rounding::Floor,
+    )
+}
+
+/// Returns the bonus in per mille of `boosted` rewards locked against
+/// `principal`, capped at `max_permille`.
+pub fn boost_bonus(boosted: Balance, principal: Balance, max_permille: u32) -> u32 {
+    if principal == 0 {
+        return 0;
+    }
+    mul_div(boosted, 1000, principal, Rounding::Floor).min(max_permille.into()) as u32
+}
+
+/// Returns the reward per unit of weight after `emitted` rewards have been
+/// spread over `total_weight`, starting from `stored`.
+pub fn reward_per_token(stored: Balance, emitted: Balance, total_weight: Balance) -> Balance {
+    if total_weight == 0 {
+        return stored;
+    }
+    stored + mul_div(emitted, ACC_PRECISION, total_weight, Rounding::Floor)
+}
+
+/// Returns the rewards accumulated by `weight` at `reward_per_token`.
+pub fn accumulated(weight: Balance, reward_per_token: Balance) -> Balance {
+    mul_div(weight, reward_per_token, ACC_PRECISION, Rounding::Floor)
+}
+
+/// Returns the rewards owed to an account holding `weight` since its
+/// `reward_debt` was set, on top of the `settled` ones.
+pub fn pending(
+    settled: Balance,
+    weight: Balance,
+    reward_per_token: Balance,
+    reward_debt: Balance,
+) -> Balance {
+    settled + accumulated(weight, reward_per_token).saturating_sub(reward_debt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Timestamp = 86400_000;
+
+    #[test]
+    fn mul_div_rounds() {
+        // 7 * 3 / 2 = 10.5
+        assert_eq!(mul_div(7, 3, 2, Rounding::Floor), 10);
+        assert_eq!(mul_div(7, 3, 2, Rounding::Ceil), 11);
+        assert_eq!(mul_div(7, 3, 2, Rounding::HalfEven), 10);
+        // 9 * 3 / 2 = 13.5
+        assert_eq!(mul_div(9, 3, 2, Rounding::HalfEven), 14);
+        // 10 / 3 = 3.33..
+        assert_eq!(mul_div(10, 1, 3, Rounding::Ceil), 4);
+        assert_eq!(mul_div(10, 1, 3, Rounding::HalfEven), 3);
+        // 20 / 3 = 6.66..
+        assert_eq!(mul_div(20, 1, 3, Rounding::HalfEven), 7);
+        // Exact quotients are never rounded.
+        for rounding in [Rounding::Floor, Rounding::Ceil, Rounding::HalfEven] {
+            assert_eq!(mul_div(6, 4, 3, rounding), 8);
+            assert_eq!(mul_div(0, 4, 3, rounding), 0);
+        }
+    }
+
+    #[test]
+    fn mul_div_does_not_overflow_intermediate() {
+        assert_eq!(
+            mul_div(Balance::MAX, Balance::MAX, Balance::MAX, Rounding::Floor),
+            Balance::MAX
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "quotient exceeds Balance")]
+    fn mul_div_panics_on_overflowing_quotient() {
+        mul_div(Balance::MAX, 2, 1, Rounding::Floor);
+    }
+
+    /// Returns `permille` as an unlocked fraction.
+    fn p(permille: u32) -> Balance {
+        from_permille(permille).into()
+    }
+
+    #[test]
+    fn unlocked_fraction_follows_steps() {
+        let unlocked = [0, from_permille(500), UNLOCK_PRECISION];
+        assert_eq!(unlocked_fraction(DAY, &unlocked, 0), 0);
+        assert_eq!(unlocked_fraction(DAY, &unlocked, (DAY - 1).into()), 0);
+        assert_eq!(unlocked_fraction(DAY, &unlocked, DAY.into()), p(500));
+        assert_eq!(unlocked_fraction(DAY, &unlocked, (2 * DAY).into()), p(1000));
+        assert_eq!(unlocked_fraction(DAY, &unlocked, Balance::MAX), p(1000));
+        assert_eq!(unlocked_fraction(DAY, &[], 0), p(1000));
+    }
+
+    #[test]
+    fn unlocked_amount_rounds() {
+        assert_eq!(unlocked_amount(p(500), 3, Rounding::Floor), 1);
+        assert_eq!(unlocked_amount(p(500), 3, Rounding::Ceil), 2);
+        assert_eq!(unlocked_amount(p(1000), 3, Rounding::Floor), 3);
+        assert_eq!(unlocked_amount(0, 3, Rounding::Ceil), 0);
+    }
+
+    #[test]
+    fn weight_scales_by_multiplier() {
+        assert_eq!(weight(1000, MULTIPLIER_BASE), 1000);
+        assert_eq!(weight(1000, 1500), 1500);
+        assert_eq!(weight(3, 1500), 4);
+        assert_eq!(weight(0, 1500), 0);
+    }
+
+    #[test]
+    fn boost_bonus_is_capped() {
+        assert_eq!(boost_bonus(100, 0, 500), 0);
+        assert_eq!(boost_bonus(100, 1000, 500), 100);
+        assert_eq!(boost_bonus(1000, 1000, 500), 500);
+        assert_eq!(boost_bonus(0, 1000, 500), 0);
+    }
+
+    #[test]
+    fn reward_per_token_spreads_emission() {
+        assert_eq!(reward_per_token(7, 100, 0), 7);
+        assert_eq!(reward_per_token(0, 100, 100), ACC_PRECISION);
+        assert_eq!(reward_per_token(5, 1, 3), 5 + ACC_PRECISION / 3);
+    }
+
+    #[test]
+    fn pending_settles_since_debt() {
+        let per_token = reward_per_token(0, 100, 100);
+        assert_eq!(accumulated(40, per_token), 40);
+        assert_eq!(pending(0, 40, per_token, 0), 40);
+        assert_eq!(pending(5, 40, per_token, 30), 15);
+        // A debt above the accumulated rewards never underflows.
+        assert_eq!(pending(5, 40, per_token, 50), 5);
+    }
+}
@@ -0,0 +1,345 @@
+//! Stand-ins for the contracts called by the staking contract, for unit tests.
+//!
+//! The off-chain environment of ink! can't call other contracts, so
+//! `call_contract` answers from here under `cfg(test)`. One mock plays the
+//! staked token, the yield strategy, the swap router, the price oracle, the
+//! successor pool and the sibling pools of a registry, told apart by the
+//! called selector. Its state lives per thread, i.e. per test.
+
+use core::cell::RefCell;
+use ink_prelude::{collections::BTreeMap, vec::Vec};
+use scale::{Decode, Encode};
+use staking_traits::{AccountId, Balance, Error, StakeId};
+
+const TOTAL_SUPPLY: [u8; 4] = ink_lang::selector_bytes!("total_supply");
+const DECIMALS: [u8; 4] = ink_lang::selector_bytes!("decimals");
+const BALANCE_OF: [u8; 4] = ink_lang::selector_bytes!("balance_of");
+const NONCE: [u8; 4] = ink_lang::selector_bytes!("nonce");
+const TRANSFER: [u8; 4] = ink_lang::selector_bytes!("transfer");
+const TRANSFER_FROM: [u8; 4] = ink_lang::selector_bytes!("transfer_from");
+const TRANSFER_WITH_SIGNATURE: [u8; 4] = ink_lang::selector_bytes!("transfer_with_signature");
+const APPROVE: [u8; 4] = ink_lang::selector_bytes!("approve");
+const ALLOWANCE: [u8; 4] = ink_lang::selector_bytes!("allowance");
+const PERMIT: [u8; 4] = ink_lang::selector_bytes!("permit");
+const BURN: [u8; 4] = ink_lang::selector_bytes!("burn");
+const MINT: [u8; 4] = ink_lang::selector_bytes!("mint");
+const DEPOSIT: [u8; 4] = ink_lang::selector_bytes!("deposit");
+const WITHDRAW: [u8; 4] = ink_lang::selector_bytes!("withdraw");
+const GET_AMOUNT_OUT: [u8; 4] = ink_lang::selector_bytes!("get_amount_out");
+const SWAP_EXACT_TOKENS_FOR_NATIVE: [u8; 4] =
+    ink_lang::selector_bytes!("swap_exact_tokens_for_native");
+const SWAP_EXACT_NATIVE_FOR_TOKENS: [u8; 4] =
+    ink_lang::selector_bytes!("swap_exact_native_for_tokens");
+const PRICE: [u8; 4] = ink_lang::selector_bytes!("price");
+const IMPORT_POSITION: [u8; 4] = ink_lang::selector_bytes!("import_position");
+const GET_TOKEN: [u8; 4] = ink_lang::selector_bytes!("get_token");
+const CLAIM_ALL_FOR: [u8; 4] = ink_lang::selector_bytes!("claim_all_for");
+const APPLY_GLOBAL_PAUSE: [u8; 4] = ink_lang::selector_bytes!("apply_global_pause");
+const NOTIFY_REWARD: [u8; 4] = ink_lang::selector_bytes!("notify_reward");
+
+/// Native currency the router pays per token, and tokens per native unit.
+pub const SWAP_RATE: Balance = 2;
+
+/// Tokens a sibling pool claims per `claim_all_for`.
+pub const SIBLING_CLAIM: Balance = 100;
+
+/// Position imported by the successor pool.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Import {
+    pub account: AccountId,
+    pub amount: Balance,
+    pub released: Balance,
+    pub timestamp: Balance,
+    pub multiplier: u32,
+}
+
+#[derive(Default)]
+struct Mock {
+    balances: BTreeMap<AccountId, Balance>,
+    allowances: BTreeMap<(AccountId, AccountId), Balance>,
+    total_supply: Balance,
+    deposited: Balance,
+    native_out: BTreeMap<AccountId, Balance>,
+    imports: Vec<Import>,
+    successor_token: Option<AccountId>,
+    sibling_claims: Vec<(AccountId, AccountId)>,
+    sibling_pauses: Vec<(AccountId, bool)>,
+    price: Balance,
+    failing: Vec<[u8; 4]>,
+    calls: u32,
+}
+
+std::thread_local! {
+    static MOCK: RefCell<Mock> = RefCell::new(Mock {
+        price: 1,
+        ..Default::default()
+    });
+}
+
+/// Staked token.
+pub fn token() -> AccountId {
+    AccountId::from([0x10; 32])
+}
+
+/// Yield strategy.
+pub fn strategy() -> AccountId {
+    AccountId::from([0x20; 32])
+}
+
+/// Swap router.
+pub fn router() -> AccountId {
+    AccountId::from([0x30; 32])
+}
+
+/// Successor pool.
+pub fn successor() -> AccountId {
+    AccountId::from([0x40; 32])
+}
+
+/// Price oracle.
+pub fn oracle() -> AccountId {
+    AccountId::from([0x80; 32])
+}
+
+/// Sibling pool number `n` of a registry.
+pub fn sibling(n: u8) -> AccountId {
+    AccountId::from([0x60 + n; 32])
+}
+
+/// Mints `amount` of the staked token to `account`.
+pub fn mint(account: AccountId, amount: Balance) {
+    MOCK.with(|mock| {
+        let mut mock = mock.borrow_mut();
+        *mock.balances.entry(account).or_default() += amount;
+        mock.total_supply += amount;
+    })
+}
+
+/// Returns the token balance of `account`.
+pub fn balance_of(account: AccountId) -> Balance {
+    MOCK.with(|mock| mock.borrow().balance_of(account))
+}
+
+/// Returns the total supply of the staked token.
+pub fn total_supply() -> Balance {
+    MOCK.with(|mock| mock.borrow().total_supply)
+}
+
+/// Returns the tokens deposited into the strategy and not withdrawn.
+pub fn deposited() -> Balance {
+    MOCK.with(|mock| mock.borrow().deposited)
+}
+
+/// Returns the native currency the router sent to `account`.
+pub fn native_out(account: AccountId) -> Balance {
+    MOCK.with(|mock| mock.borrow().native_out.get(&account).copied().unwrap_or(0))
+}
+
+/// Returns the positions imported by the successor pool.
+pub fn imports() -> Vec<Import> {
+    MOCK.with(|mock| mock.borrow().imports.clone())
+}
+
+/// Makes the successor pool stake `token` instead of the staked token.
+pub fn set_successor_token(token: AccountId) {
+    MOCK.with(|mock| mock.borrow_mut().successor_token = Some(token))
+}
+
+/// Returns the (pool, account) pairs of the `claim_all_for` calls answered by
+/// the sibling pools.
+pub fn sibling_claims() -> Vec<(AccountId, AccountId)> {
+    MOCK.with(|mock| mock.borrow().sibling_claims.clone())
+}
+
+/// Returns the (pool, paused) pairs of the `apply_global_pause` calls answered
+/// by the sibling pools.
+pub fn sibling_pauses() -> Vec<(AccountId, bool)> {
+    MOCK.with(|mock| mock.borrow().sibling_pauses.clone())
+}
+
+/// Makes the price oracle quote `price` for a whole reward token.
+pub fn set_price(price: Balance) {
+    MOCK.with(|mock| mock.borrow_mut().price = price)
+}
+
+/// Lets `spender` pull `amount` of the staked token from `owner`.
+pub fn approve(owner: AccountId, spender: AccountId, amount: Balance) {
+    MOCK.with(|mock| mock.borrow_mut().allowances.insert((owner, spender), amount));
+}
+
+/// Returns the number of calls answered so far, trapped ones included.
+pub fn calls() -> u32 {
+    MOCK.with(|mock| mock.borrow().calls)
+}
+
+/// Makes every later call of `selector` trap.
+pub fn fail(selector: [u8; 4]) {
+    MOCK.with(|mock| mock.borrow_mut().failing.push(selector))
+}
+
+/// Makes the later calls of `selector` answer again after `fail`.
+pub fn restore(selector: [u8; 4]) {
+    MOCK.with(|mock| mock.borrow_mut().failing.retain(|failing| *failing != selector))
+}
+
+/// Answers the call of `selector` with the SCALE encoded `input` made by
+/// `caller`, `None` if the callee traps.
+pub fn call(
+    caller: AccountId,
+    callee: AccountId,
+    selector: [u8; 4],
+    input: &[u8],
+    value: Balance,
+) -> Option<Vec<u8>> {
+    MOCK.with(|mock| {
+        mock.borrow_mut()
+            .call(caller, callee, selector, &mut &input[..], value)
+    })
+}
+
+impl Mock {
+    fn balance_of(&self, account: AccountId) -> Balance {
+        self.balances.get(&account).copied().unwrap_or(0)
+    }
+
+    /// Moves `amount` tokens, `Err` like the token on a short balance.
+    fn move_tokens(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> core::result::Result<(), u8> {
+        let balance = self.balance_of(from);
+        if balance < amount {
+            return Err(0);
+        }
+        self.balances.insert(from, balance - amount);
+        *self.balances.entry(to).or_default() += amount;
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        caller: AccountId,
+        callee: AccountId,
+        selector: [u8; 4],
+        input: &mut &[u8],
+        value: Balance,
+    ) -> Option<Vec<u8>> {
+        self.calls += 1;
+        if self.failing.contains(&selector) {
+            return None;
+        }
+        let output = match selector {
+            TOTAL_SUPPLY => self.total_supply.encode(),
+            DECIMALS => 12u8.encode(),
+            BALANCE_OF => self.balance_of(AccountId::decode(input).ok()?).encode(),
+            NONCE => Balance::from(0u8).encode(),
+            TRANSFER => {
+                let (to, amount) = <(AccountId, Balance)>::decode(input).ok()?;
+                self.move_tokens(caller, to, amount).encode()
+            }
+            TRANSFER_FROM | TRANSFER_WITH_SIGNATURE => {
+                let (from, to, amount) = <(AccountId, AccountId, Balance)>::decode(input).ok()?;
+                self.move_tokens(from, to, amount).encode()
+            }
+            APPROVE => {
+                let (spender, amount) = <(AccountId, Balance)>::decode(input).ok()?;
+                self.allowances.insert((caller, spender), amount);
+                Ok::<(), u8>(()).encode()
+            }
+            ALLOWANCE => {
+                let (owner, spender) = <(AccountId, AccountId)>::decode(input).ok()?;
+                let allowance = self.allowances.get(&(owner, spender)).copied().unwrap_or(0);
+                allowance.encode()
+            }
+            PERMIT => Ok::<(), u8>(()).encode(),
+            BURN => {
+                let amount = Balance::decode(input).ok()?;
+                let balance = self.balance_of(caller);
+                if balance < amount {
+                    return Some(Err::<(), u8>(0).encode());
+                }
+                self.balances.insert(caller, balance - amount);
+                self.total_supply -= amount;
+                Ok::<(), u8>(()).encode()
+            }
+            MINT => {
+                let amount = Balance::decode(input).ok()?;
+                *self.balances.entry(caller).or_default() += amount;
+                self.total_supply += amount;
+                Ok::<(), u8>(()).encode()
+            }
+            DEPOSIT => {
+                // The tokens are transferred to the strategy before.
+                self.deposited += Balance::decode(input).ok()?;
+                Ok::<(), u8>(()).encode()
+            }
+            WITHDRAW => {
+                let amount = Balance::decode(input).ok()?.min(self.deposited);
+                let result = self.move_tokens(callee, caller, amount);
+                if result.is_ok() {
+                    self.deposited -= amount;
+                }
+                result.encode()
+            }
+            GET_AMOUNT_OUT => {
+                let (_token, amount) = <(AccountId, Balance)>::decode(input).ok()?;
+                (amount * SWAP_RATE).encode()
+            }
+            SWAP_EXACT_TOKENS_FOR_NATIVE => {
+                let (_token, amount, min_out, to) =
+                    <(AccountId, Balance, Balance, AccountId)>::decode(input).ok()?;
+                let allowance = self.allowances.get(&(caller, callee)).copied().unwrap_or(0);
+                let out = amount * SWAP_RATE;
+                if allowance < amount || out < min_out {
+                    return None;
+                }
+                self.allowances.insert((caller, callee), allowance - amount);
+                self.move_tokens(caller, callee, amount).ok()?;
+                *self.native_out.entry(to).or_default() += out;
+                out.encode()
+            }
+            SWAP_EXACT_NATIVE_FOR_TOKENS => {
+                let (_token, min_out, to) = <(AccountId, Balance, AccountId)>::decode(input).ok()?;
+                let out = value * SWAP_RATE;
+                if out < min_out {
+                    return None;
+                }
+                self.move_tokens(callee, to, out).ok()?;
+                out.encode()
+            }
+            PRICE => self.price.encode(),
+            IMPORT_POSITION => {
+                let (account, amount, released, timestamp, multiplier) =
+                    <(AccountId, Balance, Balance, Balance, u32)>::decode(input).ok()?;
+                self.imports.push(Import {
+                    account,
+                    amount,
+                    released,
+                    timestamp,
+                    multiplier,
+                });
+                Ok::<StakeId, Error>(self.imports.len() as StakeId - 1).encode()
+            }
+            GET_TOKEN => self.successor_token.unwrap_or_else(token).encode(),
+            CLAIM_ALL_FOR => {
+                self.sibling_claims.push((callee, AccountId::decode(input).ok()?));
+                Ok::<Balance, Error>(SIBLING_CLAIM).encode()
+            }
+            APPLY_GLOBAL_PAUSE => {
+                self.sibling_pauses.push((callee, bool::decode(input).ok()?));
+                Ok::<(), Error>(()).encode()
+            }
+            NOTIFY_REWARD => {
+                // The sibling pool pulls the approved rewards from the injector.
+                let amount = Balance::decode(input).ok()?;
+                self.move_tokens(caller, callee, amount)
+                    .map_err(|_| Error::TransferFailed { amount })
+                    .encode()
+            }
+            _ => return None,
+        };
+        Some(output)
+    }
+}
@@ -0,0 +1,286 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use self::multisig::{Multisig, MultisigRef};
+
+use ink_lang as ink;
+
+#[ink::contract(env = staking_traits::StakingEnvironment)]
+mod multisig {
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+    use ink_prelude::vec::Vec;
+    use ink_storage::{
+        traits::{PackedLayout, SpreadAllocate, SpreadLayout},
+        Mapping,
+    };
+
+    /// Identifier of a proposed transaction.
+    pub type TransactionId = u32;
+
+    /// A call to another contract, e.g. an admin message of the staking contract.
+    #[derive(
+        Clone, Debug, PartialEq, Eq, PackedLayout, SpreadLayout, scale::Encode, scale::Decode,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Transaction {
+        /// Contract to call.
+        pub callee: AccountId,
+        /// Selector of the message to call.
+        pub selector: [u8; 4],
+        /// SCALE encoded arguments of the message.
+        pub input: Vec<u8>,
+        /// Number of owners who confirmed the transaction.
+        pub confirmations: u32,
+        /// Whether the transaction has been executed.
+        pub executed: bool,
+    }
+
+    /// A simple multi-signature wallet which can own the staking contract.
+    ///
+    /// Any owner proposes a call, `threshold` owners confirm it and then
+    /// anyone can execute it.
+    #[ink(storage)]
+    #[derive(SpreadAllocate)]
+    pub struct Multisig {
+        /// Accounts allowed to propose and confirm.
+        owners: Vec<AccountId>,
+        /// Number of confirmations required to execute a transaction.
+        threshold: u32,
+        /// Proposed transactions.
+        transactions: Mapping<TransactionId, Transaction>,
+        /// Confirmations per (transaction, owner).
+        confirmed: Mapping<(TransactionId, AccountId), ()>,
+        /// Identifier of the next proposed transaction.
+        next_id: TransactionId,
+    }
+
+    /// Event emitted when an owner proposes a transaction.
+    #[ink(event)]
+    pub struct Proposed {
+        #[ink(topic)]
+        id: TransactionId,
+        #[ink(topic)]
+        callee: AccountId,
+    }
+
+    /// Event emitted when an owner confirms a transaction.
+    #[ink(event)]
+    pub struct Confirmed {
+        #[ink(topic)]
+        id: TransactionId,
+        #[ink(topic)]
+        owner: AccountId,
+    }
+
+    /// Event emitted when a transaction has been executed.
+    #[ink(event)]
+    pub struct Executed {
+        #[ink(topic)]
+        id: TransactionId,
+    }
+
+    /// The Multisig error types.
+    ///
+    /// The SCALE index of each variant is its stable error code, shown in the
+    /// contract metadata.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if the caller is not an owner.
+        #[codec(index = 0)]
+        NotOwner,
+        /// Returned if the transaction does not exist.
+        #[codec(index = 1)]
+        UnknownTransaction,
+        /// Returned if the caller already confirmed the transaction.
+        #[codec(index = 2)]
+        AlreadyConfirmed,
+        /// Returned if the transaction lacks confirmations.
+        #[codec(index = 3)]
+        NotConfirmed,
+        /// Returned if the transaction has already been executed.
+        #[codec(index = 4)]
+        AlreadyExecuted,
+        /// Returned if the called contract trapped, could not be called or returned
+        /// an error.
+        #[codec(index = 5)]
+        ExecutionFailed,
+    }
+
+    /// The Multisig result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Forwards the raw encoded arguments of a transaction.
+    struct CallInput<'a>(&'a [u8]);
+
+    impl<'a> scale::Encode for CallInput<'a> {
+        fn encode_to<T: scale::Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    impl Multisig {
+        /// Creates a new multisig of `owners` requiring `threshold` confirmations.
+        #[ink(constructor)]
+        pub fn new(owners: Vec<AccountId>, threshold: u32) -> Self {
+            assert!(
+                threshold > 0 && threshold as usize <= owners.len(),
+                "threshold must be between 1 and the number of owners"
+            );
+            ink_lang::utils::initialize_contract(|contract: &mut Self| {
+                contract.owners = owners;
+                contract.threshold = threshold;
+            })
+        }
+
+        /// Proposes a call of `selector` on `callee` with the encoded `input`.
+        ///
+        /// The proposal counts as the first confirmation of the caller.
+        #[ink(message)]
+        pub fn propose(
+            &mut self,
+            callee: AccountId,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        ) -> Result<TransactionId> {
+            self.ensure_owner()?;
+            let id = self.next_id;
+            self.next_id += 1;
+            self.transactions.insert(
+                id,
+                &Transaction {
+                    callee,
+                    selector,
+                    input,
+                    confirmations: 0,
+                    executed: false,
+                },
+            );
+            self.env().emit_event(Proposed { id, callee });
+            self.confirm(id)?;
+            Ok(id)
+        }
+
+        /// Confirms the transaction `id`.
+        #[ink(message)]
+        pub fn confirm(&mut self, id: TransactionId) -> Result<()> {
+            self.ensure_owner()?;
+            let owner = self.env().caller();
+            let mut transaction = self.transaction(id).ok_or(Error::UnknownTransaction)?;
+            if self.confirmed.get((id, owner)).is_some() {
+                return Err(Error::AlreadyConfirmed);
+            }
+            transaction.confirmations += 1;
+            self.transactions.insert(id, &transaction);
+            self.confirmed.insert((id, owner), &());
+            self.env().emit_event(Confirmed { id, owner });
+            Ok(())
+        }
+
+        /// Executes the transaction `id` once it reached the threshold.
+        ///
+        /// The called contract sees this multisig as its caller. The called message
+        /// must return a `Result`, an `Err` fails the execution and leaves the
+        /// transaction executable.
+        #[ink(message)]
+        pub fn execute(&mut self, id: TransactionId) -> Result<()> {
+            let mut transaction = self.transaction(id).ok_or(Error::UnknownTransaction)?;
+            if transaction.executed {
+                return Err(Error::AlreadyExecuted);
+            }
+            if transaction.confirmations < self.threshold {
+                return Err(Error::NotConfirmed);
+            }
+            build_call::<staking_traits::StakingEnvironment>()
+                .call_type(Call::new().callee(transaction.callee).gas_limit(0))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(transaction.selector))
+                        .push_arg(CallInput(&transaction.input)),
+                )
+                .returns::<core::result::Result<(), u8>>()
+                .fire()
+                .map_err(|_| Error::ExecutionFailed)?
+                .map_err(|_| Error::ExecutionFailed)?;
+            transaction.executed = true;
+            self.transactions.insert(id, &transaction);
+            self.env().emit_event(Executed { id });
+            Ok(())
+        }
+
+        /// Returns the transaction `id`.
+        #[ink(message)]
+        pub fn transaction(&self, id: TransactionId) -> Option<Transaction> {
+            self.transactions.get(id)
+        }
+
+        /// Returns the owners and the confirmation threshold.
+        #[ink(message)]
+        pub fn owners(&self) -> (Vec<AccountId>, u32) {
+            (self.owners.clone(), self.threshold)
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if !self.owners.contains(&self.env().caller()) {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use ink_lang as ink;
+
+        fn accounts() -> ink_env::test::DefaultAccounts<staking_traits::StakingEnvironment> {
+            ink_env::test::default_accounts::<staking_traits::StakingEnvironment>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink_env::test::set_caller::<staking_traits::StakingEnvironment>(caller);
+        }
+
+        /// Selector of the staking contract's `set_paused` message.
+        const SET_PAUSED: [u8; 4] = ink_lang::selector_bytes!("set_paused");
+
+        #[ink::test]
+        fn propose_counts_as_confirmation() {
+            let accounts = accounts();
+            let mut multisig = Multisig::new(vec![accounts.alice, accounts.bob], 2);
+            let id = multisig
+                .propose(accounts.charlie, SET_PAUSED, scale::Encode::encode(&true))
+                .unwrap();
+            assert_eq!(multisig.transaction(id).unwrap().confirmations, 1);
+            assert_eq!(multisig.confirm(id), Err(Error::AlreadyConfirmed));
+        }
+
+        #[ink::test]
+        fn execute_requires_threshold() {
+            let accounts = accounts();
+            let mut multisig = Multisig::new(vec![accounts.alice, accounts.bob], 2);
+            let id = multisig
+                .propose(accounts.charlie, SET_PAUSED, scale::Encode::encode(&true))
+                .unwrap();
+            assert_eq!(multisig.execute(id), Err(Error::NotConfirmed));
+
+            set_caller(accounts.bob);
+            assert_eq!(multisig.confirm(id), Ok(()));
+            assert_eq!(multisig.transaction(id).unwrap().confirmations, 2);
+        }
+
+        #[ink::test]
+        fn non_owner_is_rejected() {
+            let accounts = accounts();
+            let mut multisig = Multisig::new(vec![accounts.alice, accounts.bob], 1);
+            set_caller(accounts.eve);
+            assert_eq!(
+                multisig.propose(accounts.charlie, SET_PAUSED, Vec::new()),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(multisig.confirm(0), Err(Error::NotOwner));
+        }
+    }
+}
@@ -0,0 +1,17 @@
+#!/usr/bin/env bash
+# Builds the staking contract for production and fails if the Wasm blob
+# grows beyond MAX_WASM_SIZE bytes (default: 64 KiB).
+set -euo pipefail
+
+MAX_WASM_SIZE="${MAX_WASM_SIZE:-65536}"
+ROOT="$(cd "$(dirname "$0")/.." && pwd)"
+
+cargo contract build --release --manifest-path "$ROOT/Cargo.toml"
+
+WASM="$ROOT/target/ink/Staking.wasm"
+SIZE="$(wc -c < "$WASM")"
+echo "Staking.wasm: $SIZE bytes (limit $MAX_WASM_SIZE)"
+if [ "$SIZE" -gt "$MAX_WASM_SIZE" ]; then
+    echo "error: Wasm blob exceeds the size limit" >&2
+    exit 1
+fi
@@ -0,0 +1,282 @@
+//! Deterministic scenario fixtures for integrators (feature `testing`).
+//!
+//! A `Scenario` replays stakes and elapsed time against the unlock and reward
+//! math of the contract, without an ink! environment or a token contract:
+//!
+//! ```ignore
+//! let scenario = ScenarioBuilder::new()
+//!     .with_reward_rate(10)
+//!     .with_staker(alice, 1000)
+//!     .advance_steps(3)
+//!     .build();
+//! assert_eq!(scenario.unlocked(alice), 700);
+//! ```
+//!
+//! Times start at `0` and only move forward through `advance`, so the same
+//! builder calls always yield the same numbers.
+
+use crate::math::{self, INITIAL_STEP, INITIAL_UNLOCKED_PERMILLE, MULTIPLIER_BASE};
+use ink_prelude::vec::Vec;
+use staking_traits::{AccountId, Balance, Timestamp};
+
+pub use crate::math::Rounding;
+
+/// One day in ms.
+pub const DAY: Timestamp = 86400_000;
+
+/// A stake of a scenario.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScenarioStake {
+    /// Owner of the stake.
+    pub account: AccountId,
+    /// Staked principal.
+    pub amount: Balance,
+    /// Timestamp of the stake.
+    pub timestamp: Timestamp,
+    /// Reward multiplier in per mille.
+    pub multiplier: u32,
+}
+
+/// Reward state of an account, as kept by the contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct RewardState {
+    weight: Balance,
+    reward_debt: Balance,
+    pending: Balance,
+}
+
+/// Builds a `Scenario` step by step.
+#[derive(Clone, Debug)]
+pub struct ScenarioBuilder {
+    step: Timestamp,
+    unlocked: Vec<u32>,
+    rounding: Rounding,
+    reward_rate: Balance,
+    now: Timestamp,
+    last_update: Timestamp,
+    reward_per_token: Balance,
+    total_weight: Balance,
+    stakes: Vec<ScenarioStake>,
+    rewards: Vec<(AccountId, RewardState)>,
+}
+
+impl Default for ScenarioBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScenarioBuilder {
+    /// Starts an empty scenario with the schedule active at deployment and
+    /// no rewards.
+    pub fn new() -> Self {
+        Self {
+            step: INITIAL_STEP,
+            unlocked: INITIAL_UNLOCKED_PERMILLE
+                .iter()
+                .map(|&permille| math::from_permille(permille))
+                .collect(),
+            rounding: Rounding::Floor,
+            reward_rate: 0,
+            now: 0,
+            last_update: 0,
+            reward_per_token: 0,
+            total_weight: 0,
+            stakes: Vec::new(),
+            rewards: Vec::new(),
+        }
+    }
+
+    /// Unlocks `unlocked[n]` parts of `UNLOCK_PRECISION` after `n` full steps
+    /// of `step` ms.
+    pub fn with_schedule(mut self, step: Timestamp, unlocked: Vec<u32>) -> Self {
+        self.step = step;
+        self.unlocked = unlocked;
+        self
+    }
+
+    /// Rounds unlocked amounts with `rounding`.
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Emits `reward_rate` rewards per second from now on.
+    pub fn with_reward_rate(mut self, reward_rate: Balance) -> Self {
+        self.update_pool();
+        self.reward_rate = reward_rate;
+        self
+    }
+
+    /// Stakes `amount` for `account` now, without bonus.
+    pub fn with_staker(self, account: AccountId, amount: Balance) -> Self {
+        self.with_bonus_staker(account, amount, MULTIPLIER_BASE)
+    }
+
+    /// Stakes `amount` for `account` now, earning rewards at `multiplier` per
+    /// mille.
+    pub fn with_bonus_staker(
+        mut self,
+        account: AccountId,
+        amount: Balance,
+        multiplier: u32,
+    ) -> Self {
+        self.update_pool();
+        let reward_per_token = self.reward_per_token;
+        let added = math::weight(amount, multiplier);
+        let state = self.reward_state_mut(account);
+        state.pending = math::pending(
+            state.pending,
+            state.weight,
+            reward_per_token,
+            state.reward_debt,
+        );
+        state.weight += added;
+        state.reward_debt = math::accumulated(state.weight, reward_per_token);
+        self.total_weight += added;
+        let timestamp = self.now;
+        self.stakes.push(ScenarioStake {
+            account,
+            amount,
+            timestamp,
+            multiplier,
+        });
+        self
+    }
+
+    /// Lets `duration` ms pass.
+    pub fn advance(mut self, duration: Timestamp) -> Self {
+        self.now += duration;
+        self
+    }
+
+    /// Lets `days` days pass.
+    pub fn advance_days(self, days: u64) -> Self {
+        self.advance(days * DAY)
+    }
+
+    /// Lets `steps` steps of the initial schedule pass, a day each unless the
+    /// `demo` feature shortens them.
+    pub fn advance_steps(self, steps: u64) -> Self {
+        self.advance(steps * INITIAL_STEP)
+    }
+
+    /// Finishes the scenario at the current time.
+    pub fn build(mut self) -> Scenario {
+        self.update_pool();
+        Scenario { state: self }
+    }
+
+    fn update_pool(&mut self) {
+        let elapsed = self.now - self.last_update;
+        let emitted = math::mul_div(self.reward_rate, elapsed.into(), 1000, Rounding::Floor);
+        self.reward_per_token =
+            math::reward_per_token(self.reward_per_token, emitted, self.total_weight);
+        self.last_update = self.now;
+    }
+
+    fn reward_state_mut(&mut self, account: AccountId) -> &mut RewardState {
+        let index = match self.rewards.iter().position(|(owner, _)| *owner == account) {
+            Some(index) => index,
+            None => {
+                self.rewards.push((account, RewardState::default()));
+                self.rewards.len() - 1
+            }
+        };
+        &mut self.rewards[index].1
+    }
+}
+
+/// Outcome of a built scenario.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    state: ScenarioBuilder,
+}
+
+impl Scenario {
+    /// Returns the current time of the scenario.
+    pub fn now(&self) -> Timestamp {
+        self.state.now
+    }
+
+    /// Returns the stakes of `account`.
+    pub fn stakes_of(&self, account: AccountId) -> Vec<ScenarioStake> {
+        self.stakes(account).cloned().collect()
+    }
+
+    /// Returns the principal staked by `account`.
+    pub fn staked(&self, account: AccountId) -> Balance {
+        self.stakes(account).map(|stake| stake.amount).sum()
+    }
+
+    /// Returns the principal of `account` unstakable now.
+    pub fn unlocked(&self, account: AccountId) -> Balance {
+        self.stakes(account)
+            .map(|stake| {
+                let age = Balance::from(self.state.now - stake.timestamp);
+                let fraction = math::unlocked_fraction(self.state.step, &self.state.unlocked, age);
+                math::unlocked_amount(fraction, stake.amount, self.state.rounding)
+            })
+            .sum()
+    }
+
+    /// Returns the rewards `account` could claim now.
+    pub fn pending_rewards(&self, account: AccountId) -> Balance {
+        self.state
+            .rewards
+            .iter()
+            .find(|(owner, _)| *owner == account)
+            .map_or(0, |(_, state)| {
+                math::pending(
+                    state.pending,
+                    state.weight,
+                    self.state.reward_per_token,
+                    state.reward_debt,
+                )
+            })
+    }
+
+    fn stakes(&self, account: AccountId) -> impl Iterator<Item = &ScenarioStake> {
+        self.state
+            .stakes
+            .iter()
+            .filter(move |stake| stake.account == account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(byte: u8) -> AccountId {
+        AccountId::from([byte; 32])
+    }
+
+    #[test]
+    fn stakes_unlock_over_steps() {
+        let alice = account(1);
+        let scenario = ScenarioBuilder::new()
+            .with_staker(alice, 1000)
+            .advance_steps(3)
+            .build();
+        assert_eq!(scenario.now(), 3 * INITIAL_STEP);
+        assert_eq!(scenario.staked(alice), 1000);
+        assert_eq!(scenario.unlocked(alice), 700);
+        assert_eq!(scenario.unlocked(account(2)), 0);
+    }
+
+    #[test]
+    fn rewards_are_shared_by_weight() {
+        let (alice, bob) = (account(1), account(2));
+        let scenario = ScenarioBuilder::new()
+            .with_reward_rate(10)
+            .with_staker(alice, 1000)
+            .advance(100_000)
+            .with_bonus_staker(bob, 1000, 3000)
+            .advance(100_000)
+            .build();
+        // Alice earns the first 100 s alone, then a quarter of the next 100 s.
+        assert_eq!(scenario.pending_rewards(alice), 1000 + 250);
+        assert_eq!(scenario.pending_rewards(bob), 750);
+    }
+}
@@ -0,0 +1,593 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Read-only interface of the staking contract and the types it exposes.
+//!
+//! Integrators can depend on this crate to call a staking pool or decode its
+//! results without pulling in the whole contract.
+
+use ink_env::{DefaultEnvironment, Environment};
+use ink_lang as ink;
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+
+/// Environment of the staking contracts.
+///
+/// Same as the default environment, except that balances are `u64` with the
+/// `u64-balance` feature, for chains whose native balance type is `u64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum StakingEnvironment {}
+
+impl Environment for StakingEnvironment {
+    const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <DefaultEnvironment as Environment>::AccountId;
+    #[cfg(not(feature = "u64-balance"))]
+    type Balance = u128;
+    #[cfg(feature = "u64-balance")]
+    type Balance = u64;
+    type Hash = <DefaultEnvironment as Environment>::Hash;
+    type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+    type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+    type ChainExtension = <DefaultEnvironment as Environment>::ChainExtension;
+}
+
+pub type AccountId = <StakingEnvironment as Environment>::AccountId;
+pub type Balance = <StakingEnvironment as Environment>::Balance;
+pub type BlockNumber = <StakingEnvironment as Environment>::BlockNumber;
+pub type Timestamp = <StakingEnvironment as Environment>::Timestamp;
+
+/// Identifier of a stake, unique over the contract's lifetime.
+pub type StakeId = u64;
+
+/// Version of an unlock schedule.
+pub type ScheduleVersion = u32;
+
+/// Identifier of a gifted stake.
+pub type GiftId = u64;
+
+/// Identifier of an OTC offer.
+pub type OfferId = u64;
+
+/// Global sequential identifier of a claim receipt.
+pub type ReceiptId = u64;
+
+/// Staking data per wallet, one entry per stake.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Default,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Stake {
+    pub amount: Balance,
+    /// Creation time in ms.
+    pub timestamp: Balance,
+    /// Reward multiplier in per mille, 1000 being 1x.
+    pub multiplier: u32,
+    pub id: StakeId,
+    /// Unlock schedule active when the stake was created.
+    pub schedule: ScheduleVersion,
+    /// Part of `amount` already claimed.
+    pub released: Balance,
+}
+
+impl Stake {
+    /// Returns the principal not released yet.
+    pub fn remaining(&self) -> Balance {
+        self.amount - self.released
+    }
+}
+
+/// Permissions the owner can grant to other accounts.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum Role {
+    /// May add rewards with `notify_reward`, e.g. a fee-sharing DEX.
+    RewardInjector,
+    /// May pause, but never unpause, configure or move funds, e.g. a
+    /// fast-response security council.
+    Guardian,
+    /// May pause and resume every pool listed in the registry with
+    /// `set_global_paused`, e.g. the security council of the deployment.
+    GlobalGuardian,
+}
+
+/// The Staking error types.
+///
+/// Variants carry the values the check failed on, so that dry-runs and
+/// frontends can show actionable messages.
+///
+/// The SCALE index of each variant is its stable error code, shown in the
+/// contract metadata. New variants take the next free index; indices of
+/// removed variants are never reused.
+#[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Error {
+    /// Returned if the ERC20 token transfer of `amount` did not succeed.
+    #[codec(index = 0)]
+    TransferFailed {
+        /// Amount that could not be transferred.
+        amount: Balance,
+    },
+    /// Returned if the caller has nothing left in the escrow.
+    #[codec(index = 1)]
+    EmptyEscrow,
+    /// Returned if the requested amount exceeds the current unstakable balance.
+    #[codec(index = 2)]
+    ExceedsUnstakable {
+        /// Amount the caller asked for.
+        requested: Balance,
+        /// Amount the caller can claim now.
+        unstakable: Balance,
+        /// Block the balance was evaluated at.
+        block: BlockNumber,
+    },
+    /// Returned if the caller is not the owner.
+    #[codec(index = 3)]
+    NotOwner,
+    /// Returned if there is no surplus to sweep.
+    #[codec(index = 4)]
+    NoSurplus,
+    /// Returned if the amount is below the configured minimum.
+    #[codec(index = 5)]
+    BelowMinimum {
+        /// Rejected amount.
+        amount: Balance,
+        /// Smallest accepted amount.
+        minimum: Balance,
+    },
+    /// Returned if the amount is above the configured maximum or share of the pool.
+    #[codec(index = 6)]
+    AboveMaximum {
+        /// Rejected amount.
+        amount: Balance,
+        /// Largest accepted amount.
+        maximum: Balance,
+    },
+    /// Returned if the caller's token balance doesn't cover the amount.
+    #[codec(index = 7)]
+    InsufficientBalance {
+        /// Token balance of the caller.
+        balance: Balance,
+        /// Amount the operation needs.
+        required: Balance,
+    },
+    /// Returned if a whole-token limit doesn't fit into a `Balance`.
+    #[codec(index = 8)]
+    LimitOverflow,
+    /// Returned if the account is not a callable ERC20 contract.
+    #[codec(index = 9)]
+    InvalidToken,
+    /// Returned if the token is replaced while stakes or escrow are outstanding.
+    #[codec(index = 10)]
+    OutstandingBalances {
+        /// Staked principal.
+        staked: Balance,
+        /// Escrowed payouts.
+        escrowed: Balance,
+        /// Funded, owed and boosted rewards.
+        rewards: Balance,
+    },
+    /// Returned if no matching admin action has been proposed.
+    #[codec(index = 11)]
+    NotProposed,
+    /// Returned if the timelock of a proposed admin action has not elapsed.
+    #[codec(index = 12)]
+    TimelockActive {
+        /// Current block timestamp.
+        now: Timestamp,
+        /// Time the action becomes executable.
+        eta: Timestamp,
+    },
+    /// Returned if the contract is paused.
+    #[codec(index = 13)]
+    Paused,
+    /// Returned if the operation is disabled in emergency mode.
+    #[codec(index = 14)]
+    Emergency,
+    /// Returned if the operation requires the contract to be paused.
+    #[codec(index = 15)]
+    NotPaused,
+    /// Returned if the caller has no rewards to claim.
+    #[codec(index = 16)]
+    NoRewards,
+    /// Returned if a multiplier is below 1x or above `MAX_MULTIPLIER`.
+    #[codec(index = 17)]
+    InvalidMultiplier {
+        /// Rejected multiplier in per mille.
+        multiplier: u32,
+    },
+    /// Returned if the early-bird window ends before the deployment block.
+    #[codec(index = 18)]
+    BonusWindowElapsed {
+        /// Requested end of the early-bird window.
+        bonus_end_block: BlockNumber,
+        /// Deployment block.
+        block: BlockNumber,
+    },
+    /// Returned if a duration is zero.
+    #[codec(index = 19)]
+    ZeroDuration,
+    /// Returned if an unlock schedule is empty, decreasing or never fully unlocks.
+    #[codec(index = 20)]
+    InvalidSchedule,
+    /// Returned if a new reward period would emit outside the owner-set band.
+    #[codec(index = 21)]
+    RewardRateOutOfBounds {
+        /// Rate the new period would emit per second.
+        reward_rate: Balance,
+        /// Lower bound set by the owner.
+        min_reward_rate: Balance,
+        /// Upper bound set by the owner, `0` if unbounded.
+        max_reward_rate: Balance,
+    },
+    /// Returned if the bounds of the rate model are inverted.
+    #[codec(index = 22)]
+    InvalidRateModel {
+        /// Requested lower bound in per mille.
+        min_permille: u32,
+        /// Requested upper bound in per mille.
+        max_permille: u32,
+    },
+    /// Returned if the reward period can't be changed before it finished.
+    #[codec(index = 23)]
+    RewardPeriodActive {
+        /// End of the current reward period.
+        period_finish: Timestamp,
+    },
+    /// Returned if a fee exceeds its upper bound.
+    #[codec(index = 24)]
+    FeeTooHigh {
+        /// Rejected fee in basis points.
+        fee_bps: u32,
+        /// Highest accepted fee in basis points.
+        max_fee_bps: u32,
+    },
+    /// Returned if the caller is not the guardian of the account.
+    #[codec(index = 25)]
+    NotGuardian,
+    /// Returned if the recovery delay is below `MIN_RECOVERY_DELAY`.
+    #[codec(index = 26)]
+    RecoveryDelayTooShort {
+        /// Rejected delay in ms.
+        delay: Timestamp,
+        /// Shortest accepted delay in ms.
+        min_delay: Timestamp,
+    },
+    /// Returned if the account already holds entries.
+    #[codec(index = 27)]
+    AccountInUse,
+    /// Returned if the caller lacks the required role.
+    #[codec(index = 28)]
+    MissingRole(Role),
+    /// Returned if the boost is still in its cooldown.
+    #[codec(index = 29)]
+    BoostLocked {
+        /// Time the boost can be unlocked.
+        unlock_at: Timestamp,
+    },
+    /// Returned if the pool has been deprecated.
+    #[codec(index = 30)]
+    Deprecated {
+        /// Pool replacing this one.
+        successor: AccountId,
+    },
+    /// Returned if the pool has not been deprecated.
+    #[codec(index = 31)]
+    NotDeprecated,
+    /// Returned if the caller holds no open stake with this identifier.
+    #[codec(index = 32)]
+    UnknownStake {
+        /// Requested stake.
+        stake_id: StakeId,
+    },
+    /// Returned if the caller is not the predecessor pool.
+    #[codec(index = 33)]
+    NotPredecessor,
+    /// Returned if an obligations report was published within `REPORT_INTERVAL`.
+    #[codec(index = 34)]
+    ReportTooEarly {
+        /// Time the next report can be published.
+        next_report: Timestamp,
+    },
+    /// Returned if less than the caller's minimum would be paid out.
+    #[codec(index = 35)]
+    InsufficientOutput {
+        /// Amount that would be paid out.
+        out: Balance,
+        /// Minimum requested by the caller.
+        min_expected_out: Balance,
+    },
+    /// Returned if the legacy rewards of the last migration can still be claimed.
+    #[codec(index = 37)]
+    LegacyRewardsActive {
+        /// End of the grace period.
+        claim_deadline: Timestamp,
+    },
+    /// Returned if the grace period to claim the legacy rewards is over.
+    #[codec(index = 38)]
+    GracePeriodOver {
+        /// End of the grace period.
+        claim_deadline: Timestamp,
+    },
+    /// Returned if discount tier thresholds are not ascending or too many.
+    #[codec(index = 39)]
+    InvalidDiscountTiers,
+    /// Returned if no yield strategy is registered.
+    #[codec(index = 40)]
+    NoStrategy,
+    /// Returned if the strategy is replaced while tokens are invested.
+    #[codec(index = 41)]
+    StrategyInUse {
+        /// Tokens still invested into the strategy.
+        invested: Balance,
+    },
+    /// Returned if the strategy could not be called or rejected the call.
+    #[codec(index = 42)]
+    StrategyFailed,
+    /// Returned if the account did not opt in to claims by anyone.
+    #[codec(index = 43)]
+    NotOptedIn,
+    /// Returned if the successor pool rejected the position.
+    #[codec(index = 44)]
+    MigrationFailed,
+    /// Returned if the reward pool holds the other currency.
+    #[codec(index = 45)]
+    WrongRewardCurrency {
+        /// Whether the reward pool holds the native currency.
+        native_rewards: bool,
+    },
+    /// Returned if no price oracle is configured.
+    #[codec(index = 46)]
+    NoOracle,
+    /// Returned if the price oracle could not be called or quoted zero.
+    #[codec(index = 47)]
+    OracleFailed,
+    /// Returned if the reward rate was recalculated within the current epoch.
+    #[codec(index = 48)]
+    EpochNotElapsed {
+        /// Time the rate can be recalculated again.
+        next_retarget: Timestamp,
+    },
+    /// Returned if the call was executed after the caller's deadline.
+    #[codec(index = 49)]
+    DeadlineExpired {
+        /// Deadline set by the caller.
+        deadline: Timestamp,
+        /// Current block timestamp.
+        now: Timestamp,
+    },
+    /// Returned if the gift does not exist or is not addressed to the caller.
+    #[codec(index = 50)]
+    UnknownGift {
+        /// Requested gift.
+        gift_id: GiftId,
+    },
+    /// Returned if the acceptance window of the gift is over.
+    #[codec(index = 51)]
+    GiftExpired {
+        /// End of the acceptance window.
+        expires_at: Timestamp,
+    },
+    /// Returned if the gift can still be accepted by its recipient.
+    #[codec(index = 52)]
+    GiftPending {
+        /// End of the acceptance window.
+        expires_at: Timestamp,
+    },
+    /// Returned if the OTC offer does not exist or the caller is not part of it.
+    #[codec(index = 53)]
+    UnknownOffer {
+        /// Requested offer.
+        offer_id: OfferId,
+    },
+    /// Returned if contract callers are restricted and the caller is a contract
+    /// not on the integrator allowlist.
+    #[codec(index = 54)]
+    ContractCaller {
+        /// Rejected caller.
+        caller: AccountId,
+    },
+    /// Returned if no swap router is configured.
+    #[codec(index = 55)]
+    NoRouter,
+    /// Returned if the swap router could not be called or rejected the swap. If
+    /// returned after the release, the claim was paid out in the staked token.
+    #[codec(index = 56)]
+    SwapFailed,
+    /// Returned if the schedule version is not offered as a lock tier.
+    #[codec(index = 57)]
+    UnknownTier {
+        /// Requested tier.
+        tier: ScheduleVersion,
+    },
+    /// Returned if the requested tier does not mature later than the current one.
+    #[codec(index = 58)]
+    TierDowngrade {
+        /// Schedule version of the stake.
+        current: ScheduleVersion,
+        /// Requested tier.
+        requested: ScheduleVersion,
+    },
+    /// Returned if the caller has no staked principal.
+    #[codec(index = 59)]
+    NotStaker,
+    /// Returned if the caller already voted in the current refund vote.
+    #[codec(index = 60)]
+    AlreadyVoted,
+    /// Returned if the emergency refund is already open.
+    #[codec(index = 61)]
+    RefundOpen,
+    /// Returned if the owner inactivity before a failover is below the minimum.
+    #[codec(index = 62)]
+    FailoverIntervalTooShort {
+        /// Rejected interval in ms.
+        interval: Timestamp,
+        /// Minimal interval in ms.
+        min_interval: Timestamp,
+    },
+    /// Returned if the backup owner tries to take over before the owner's
+    /// inactivity elapsed.
+    #[codec(index = 63)]
+    OwnerActive {
+        /// Time from which the takeover is possible, `None` while not paused.
+        failover_at: Option<Timestamp>,
+    },
+    /// Returned if the account holds the maximal number of open stakes.
+    #[codec(index = 64)]
+    TooManyStakes {
+        /// Maximal number of open stakes per account.
+        maximum: u32,
+    },
+    /// Returned if data is passed along with received tokens.
+    #[codec(index = 65)]
+    InvalidPayload,
+    /// Returned if the step period of a schedule doesn't divide its total
+    /// duration into at most `MAX_SCHEDULE_STEPS` steps.
+    #[codec(index = 66)]
+    InvalidStepPeriod {
+        /// Requested step period in ms.
+        step: Timestamp,
+        /// Requested total duration in ms.
+        duration: Timestamp,
+    },
+    /// Returned if a call to an ERC20 token trapped or returned an undecodable
+    /// result.
+    #[codec(index = 67)]
+    TokenCallFailed {
+        /// Called token.
+        token: AccountId,
+        /// Selector of the called message.
+        selector: [u8; 4],
+    },
+    /// Returned if the caller has no pending deposit to refund.
+    #[codec(index = 68)]
+    NoPendingDeposit,
+    /// Returned if a payout split has too many beneficiaries, an empty share or
+    /// shares not summing up to 1000 per mille.
+    #[codec(index = 70)]
+    InvalidSplit,
+    /// Returned if the merchant isn't authorized to pull from the account.
+    #[codec(index = 71)]
+    NoPullAuthorization,
+    /// Returned if a merchant pulls before the next period started.
+    #[codec(index = 72)]
+    PullNotDue {
+        /// Time from which the next pull is possible.
+        next_pull: Timestamp,
+    },
+    /// Returned if a new staker would exceed the staker cap of the pool.
+    #[codec(index = 73)]
+    StakerCapReached {
+        /// Maximal number of stakers.
+        maximum: u32,
+    },
+    /// Returned if the caller isn't the pool registry trusted by this pool.
+    #[codec(index = 74)]
+    NotHub,
+    /// Returned if the pool is already listed in the registry.
+    #[codec(index = 75)]
+    PoolRegistered,
+    /// Returned if no pool is listed under the identifier.
+    #[codec(index = 76)]
+    UnknownPool,
+    /// Returned if the registry can't list another pool.
+    #[codec(index = 77)]
+    TooManyPools {
+        /// Maximal number of pools ever listed.
+        maximum: u32,
+    },
+    /// Returned if the gauge epoch hasn't ended yet.
+    #[codec(index = 78)]
+    GaugeEpochActive {
+        /// Block the epoch ends at.
+        epoch_end: BlockNumber,
+    },
+    /// Returned if a gauge vote exceeds the voting power left in the epoch.
+    #[codec(index = 79)]
+    VotingPowerExceeded {
+        /// Voting power left to the account.
+        available: Balance,
+        /// Requested vote weight.
+        requested: Balance,
+    },
+    /// Returned if no listed pool got a gauge vote in the epoch, or the epoch
+    /// was distributed already.
+    #[codec(index = 80)]
+    NoGaugeVotes,
+    /// Returned if the caller didn't approve this contract to pull the stake.
+    #[codec(index = 81)]
+    InsufficientAllowance {
+        /// Tokens this contract may pull from the caller.
+        allowance: Balance,
+        /// Allowance to add with the token's `approve` for the stake to go
+        /// through.
+        missing: Balance,
+    },
+}
+
+/// The Staking result type.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Position of an account, returned by `account_summary`.
+#[derive(Debug, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct AccountSummary {
+    /// Staked principal not released yet.
+    pub staked: Balance,
+    /// Principal claimable now.
+    pub unstakable: Balance,
+    /// Rewards claimable now.
+    pub pending_rewards: Balance,
+    /// Payouts held back after a failed transfer.
+    pub escrow: Balance,
+    /// Rewards locked into the boost vault.
+    pub boost: Balance,
+    /// Number of open stakes.
+    pub stakes: u32,
+}
+
+/// Read-only interface of a staking pool.
+#[ink::trait_definition]
+pub trait StakingView {
+    /// Returns the staked token.
+    #[ink(message)]
+    fn token(&self) -> AccountId;
+
+    /// Returns the principal staked by all accounts.
+    #[ink(message)]
+    fn total_staked(&self) -> Balance;
+
+    /// Returns the open stakes of `account`.
+    #[ink(message)]
+    fn stakes_of(&self, account: AccountId) -> Vec<Stake>;
+
+    /// Returns the position of `account`.
+    #[ink(message)]
+    fn account_summary(&self, account: AccountId) -> AccountSummary;
+}
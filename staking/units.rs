@@ -0,0 +1,174 @@
+//! Newtypes keeping token amounts and block numbers apart.
+//!
+//! Only the meaningful combinations are implemented, so that mixing units
+//! (e.g. multiplying two amounts or adding a block number to an amount) is
+//! a compile error. Messages keep the plain `Balance`/`BlockNumber` types and
+//! convert at the boundary.
+
+use core::ops::{Add, Div, Mul, Sub};
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use staking_traits::{Balance, BlockNumber};
+
+/// A block number of the chain.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct BlockNumberOf(pub BlockNumber);
+
+/// A number of blocks between two block numbers.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DurationBlocks(pub BlockNumber);
+
+/// An amount of the staked token.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct TokenAmount(pub Balance);
+
+/// An amount of the staked token held over a number of blocks.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    PackedLayout,
+    SpreadLayout,
+    scale::Encode,
+    scale::Decode,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct TokenBlocks(pub Balance);
+
+impl BlockNumberOf {
+    /// Returns the number of blocks since `earlier`, `0` if `earlier` is later.
+    pub fn since(self, earlier: BlockNumberOf) -> DurationBlocks {
+        DurationBlocks(self.0.saturating_sub(earlier.0))
+    }
+}
+
+// The release profile doesn't check for overflows, so the operators do it
+// themselves and trap with a static message.
+
+impl Add for TokenAmount {
+    type Output = TokenAmount;
+
+    fn add(self, rhs: TokenAmount) -> TokenAmount {
+        TokenAmount(self.0.checked_add(rhs.0).unwrap_or_else(|| panic!("token amount overflow")))
+    }
+}
+
+impl Sub for TokenAmount {
+    type Output = TokenAmount;
+
+    fn sub(self, rhs: TokenAmount) -> TokenAmount {
+        TokenAmount(self.0.checked_sub(rhs.0).unwrap_or_else(|| panic!("token amount underflow")))
+    }
+}
+
+impl Mul<DurationBlocks> for TokenAmount {
+    type Output = TokenBlocks;
+
+    fn mul(self, rhs: DurationBlocks) -> TokenBlocks {
+        let product = self.0.checked_mul(Balance::from(rhs.0));
+        TokenBlocks(product.unwrap_or_else(|| panic!("token blocks overflow")))
+    }
+}
+
+impl Add for TokenBlocks {
+    type Output = TokenBlocks;
+
+    fn add(self, rhs: TokenBlocks) -> TokenBlocks {
+        TokenBlocks(self.0.checked_add(rhs.0).unwrap_or_else(|| panic!("token blocks overflow")))
+    }
+}
+
+impl Sub for TokenBlocks {
+    type Output = TokenBlocks;
+
+    fn sub(self, rhs: TokenBlocks) -> TokenBlocks {
+        TokenBlocks(self.0.checked_sub(rhs.0).unwrap_or_else(|| panic!("token blocks underflow")))
+    }
+}
+
+impl Div<DurationBlocks> for TokenBlocks {
+    type Output = TokenAmount;
+
+    fn div(self, rhs: DurationBlocks) -> TokenAmount {
+        let quotient = self.0.checked_div(Balance::from(rhs.0));
+        TokenAmount(quotient.unwrap_or_else(|| panic!("division by zero blocks")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_saturates() {
+        assert_eq!(BlockNumberOf(10).since(BlockNumberOf(4)), DurationBlocks(6));
+        assert_eq!(BlockNumberOf(4).since(BlockNumberOf(10)), DurationBlocks(0));
+    }
+
+    #[test]
+    fn average_round_trips() {
+        let held = TokenAmount(300) * DurationBlocks(4) + TokenAmount(100) * DurationBlocks(4);
+        assert_eq!(held, TokenBlocks(1600));
+        assert_eq!(held / DurationBlocks(8), TokenAmount(200));
+        assert_eq!(TokenAmount(5) - TokenAmount(2), TokenAmount(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "token blocks overflow")]
+    fn mul_overflow_traps() {
+        let _ = TokenAmount(Balance::MAX) * DurationBlocks(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "token amount underflow")]
+    fn sub_underflow_traps() {
+        let _ = TokenAmount(1) - TokenAmount(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "division by zero blocks")]
+    fn div_by_zero_blocks_traps() {
+        let _ = TokenBlocks(1) / DurationBlocks(0);
+    }
+}